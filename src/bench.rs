@@ -0,0 +1,131 @@
+use crate::classical::fen::from_fen;
+use crate::generic::ai::{AiOff, NodeCount, SearchConfig};
+use crate::generic::board_data::perft;
+use crate::generic::evaluation::EvalParams;
+use rayon::prelude::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//an opening, a middlegame and an endgame
+const POSITIONS: [&str; 3] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+];
+
+//how deep a search of the position gets within the time limit, how many nodes it visits and how long it ran
+//the search may end sooner if it runs out of nodes
+pub fn depth_reached(
+    fen: &str,
+    config: SearchConfig,
+    limit: Duration,
+) -> Result<(usize, usize, Duration), String> {
+    let ai = AiOff::with_config(from_fen(fen)?, EvalParams::default(), config).start();
+    let start = Instant::now();
+    while start.elapsed() < limit && !ai.is_finished() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let result = (ai.current_depth(), ai.nodes_searched(), start.elapsed());
+    ai.finish();
+    Ok(result)
+}
+
+//counting as the search once did, every thread taking the same lock for each node and another to read the stop flag
+#[derive(Default)]
+struct LockedCount {
+    count: Mutex<usize>,
+    stop: Mutex<bool>,
+}
+
+impl LockedCount {
+    fn visit(&self, limit: usize) -> Result<(), ()> {
+        let mut count = self.count.lock().unwrap();
+        *count += 1;
+        match *count > limit || *self.stop.lock().unwrap() {
+            true => Err(()),
+            false => Ok(()),
+        }
+    }
+}
+
+//how many nodes a second the given number of threads count, under the old locks and in their own slots
+//the threads do nothing else, so this is the most counting can cost the search
+pub fn counting(threads: usize, nodes: usize) -> (f64, f64) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    let rate = |visit: &(dyn Fn() -> Result<(), ()> + Sync)| {
+        let start = Instant::now();
+        pool.install(|| (0..nodes).into_par_iter().for_each(|_| visit().unwrap()));
+        nodes as f64 / start.elapsed().as_secs_f64()
+    };
+    let locked = LockedCount::default();
+    let per_thread = NodeCount::new(threads, usize::MAX);
+    (
+        rate(&|| locked.visit(usize::MAX)),
+        rate(&|| per_thread.visit(usize::MAX)),
+    )
+}
+
+//how many positions a second perft of the position to the depth counts, with the board's bitboards and without them
+pub fn perft_rate(fen: &str, depth: usize) -> Result<(f64, f64), String> {
+    let mut board = from_fen(fen)?;
+    let rate = |board: &mut crate::generic::Board| {
+        let start = Instant::now();
+        let count = perft(board, depth);
+        count as f64 / start.elapsed().as_secs_f64()
+    };
+    let with = rate(&mut board);
+    board.drop_bitboards();
+    Ok((with, rate(&mut board)))
+}
+
+//compare the ways of counting nodes, perft with and without bitboards,
+//then plain alpha-beta with the full search on each position on one thread and on more
+//plain alpha-beta searches the same tree every time, so its nodes per second compare best
+pub fn run(limit: Duration) -> Result<(), String> {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    println!("{} cores", cores);
+    for threads in [1, 2, 4, 8] {
+        let (locked, per_thread) = counting(threads, 10_000_000);
+        println!(
+            "{:>2} threads counting: {:>11.0} nodes per second locked, {:>11.0} per thread",
+            threads, locked, per_thread
+        );
+    }
+    for fen in POSITIONS {
+        let (with, without) = perft_rate(fen, 4)?;
+        println!(
+            "perft 4: {:>9.0} positions per second with bitboards, {:>9.0} without  {}",
+            with, without, fen
+        );
+    }
+    let threads = |threads: usize, config: SearchConfig| SearchConfig { threads, ..config };
+    let mut results = vec![];
+    for fen in POSITIONS {
+        for (name, config) in [
+            ("plain", threads(1, SearchConfig::plain())),
+            ("full", threads(1, SearchConfig::default())),
+            ("full 2", threads(2, SearchConfig::default())),
+            ("full 4", threads(4, SearchConfig::default())),
+        ] {
+            let (depth, nodes, elapsed) = depth_reached(fen, config, limit)?;
+            results.push((fen, name, depth, nodes, elapsed));
+        }
+    }
+    println!();
+    for (fen, name, depth, nodes, elapsed) in results {
+        let seconds = elapsed.as_secs_f64();
+        println!(
+            "{:>12}: depth {:>2} in {:>5.1}s = {:.2} plies per second, {:>8.0} nodes per second  {}",
+            name,
+            depth,
+            seconds,
+            depth as f64 / seconds,
+            nodes as f64 / seconds,
+            fen
+        );
+    }
+    Ok(())
+}
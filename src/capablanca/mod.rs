@@ -0,0 +1,128 @@
+use crate::classical::graphical::{Game, Geometry, GridGeometry};
+use crate::classical::{grid, Topology};
+use crate::generic::*;
+use std::collections::HashMap;
+
+use self::signature::CastleSignature;
+
+const FILES: u8 = 10;
+const RANKS: u8 = 8;
+
+const GRID: grid::Grid = grid::Grid {
+    files: FILES,
+    ranks: RANKS,
+    topology: Topology::Flat,
+};
+
+//the king starts on the f file and castles three squares towards either rook
+//ending on the c or i file with the rook beside it on the d or h file
+fn castle_signature(y: u8, king_x: u8, rook_x: u8) -> CastleSignature {
+    let to = match rook_x < king_x {
+        true => (2, 3),
+        false => (8, 7),
+    };
+    grid::castle_signature(FILES, y, (king_x, rook_x), to)
+}
+
+pub fn create_signature() -> signature::Signature {
+    grid::create_signature(
+        &GRID,
+        &[
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+            PieceKind::Archbishop,
+            PieceKind::Chancellor,
+        ],
+        &[RANKS - 1],
+        &|_team, y| match y {
+            0 => None,
+            y => Some(y == 1),
+        },
+        vec![castle_signature(0, 5, 0), castle_signature(0, 5, 9)],
+        vec![castle_signature(7, 5, 0), castle_signature(7, 5, 9)],
+    )
+}
+
+pub enum CapablancaGameType {
+    Capablanca,
+    Gothic,
+}
+
+impl CapablancaGameType {
+    pub fn back_rank(&self) -> [PieceKind; FILES as usize] {
+        match self {
+            CapablancaGameType::Capablanca => [
+                PieceKind::Rook,
+                PieceKind::Knight,
+                PieceKind::Archbishop,
+                PieceKind::Bishop,
+                PieceKind::Queen,
+                PieceKind::King,
+                PieceKind::Bishop,
+                PieceKind::Chancellor,
+                PieceKind::Knight,
+                PieceKind::Rook,
+            ],
+            CapablancaGameType::Gothic => [
+                PieceKind::Rook,
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Queen,
+                PieceKind::Chancellor,
+                PieceKind::King,
+                PieceKind::Archbishop,
+                PieceKind::Bishop,
+                PieceKind::Knight,
+                PieceKind::Rook,
+            ],
+        }
+    }
+
+    pub fn create_game(&self) -> Board {
+        let rank = self.back_rank();
+
+        let mut white_pieces = HashMap::new();
+        let mut black_pieces = HashMap::new();
+        for x in 0..FILES {
+            white_pieces.insert(GRID.sq(x, 1), PieceKind::Pawn(EnCroissantable::No));
+            white_pieces.insert(GRID.sq(x, 0), rank[x as usize]);
+            black_pieces.insert(GRID.sq(x, 6), PieceKind::Pawn(EnCroissantable::No));
+            black_pieces.insert(GRID.sq(x, 7), rank[x as usize]);
+        }
+
+        Board::new(Team::White, create_signature(), white_pieces, black_pieces)
+    }
+}
+
+impl Game for CapablancaGameType {
+    fn geometry(&self) -> Box<dyn Geometry> {
+        Box::new(GridGeometry::new(FILES, RANKS, false))
+    }
+
+    fn create_game(&self) -> Board {
+        CapablancaGameType::create_game(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test() {
+        for idx in 0..(FILES * RANKS) as usize {
+            let sq = Square { idx };
+            let (x, y) = GRID.xy(sq);
+            assert_eq!(GRID.sq(x, y), sq);
+        }
+
+        //20 pawn moves and 8 knight jumps
+        for game_type in [CapablancaGameType::Capablanca, CapablancaGameType::Gothic] {
+            let mut board = game_type.create_game();
+            let data = board_data::BoardData::new(&mut board);
+            assert_eq!(data.get_moves().len(), 28);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use super::*;
+
+pub const NUM_POSITIONS: usize = 960;
+pub const CLASSICAL_POSITION: usize = 518;
+
+//the white back rank of chess960 start position n, numbered as in Scharnagl's scheme
+//so that position 518 is the classical setup
+pub fn back_rank(n: usize) -> [PieceKind; 8] {
+    assert!(n < NUM_POSITIONS);
+    let mut rank: [Option<PieceKind>; 8] = [None; 8];
+
+    //one bishop on each colour
+    let (n, light) = (n / 4, n % 4);
+    rank[2 * light + 1] = Some(PieceKind::Bishop);
+    let (n, dark) = (n / 4, n % 4);
+    rank[2 * dark] = Some(PieceKind::Bishop);
+
+    //the remaining pieces fill the empty squares from left to right
+    let mut place = |k: usize, kind: PieceKind| {
+        let x = (0..8).filter(|x| rank[*x].is_none()).nth(k).unwrap();
+        rank[x] = Some(kind);
+    };
+    let (n, queen) = (n / 6, n % 6);
+    place(queen, PieceKind::Queen);
+    let (knight_a, knight_b) = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ][n];
+    //place the second knight first so the first knight's index is unaffected
+    place(knight_b, PieceKind::Knight);
+    place(knight_a, PieceKind::Knight);
+    place(0, PieceKind::Rook);
+    place(0, PieceKind::King);
+    place(0, PieceKind::Rook);
+
+    rank.map(|kind| kind.unwrap())
+}
+
+fn king_and_rook_files(rank: &[PieceKind; 8]) -> (u8, Vec<u8>) {
+    let king_x = (0..8u8)
+        .find(|x| rank[*x as usize] == PieceKind::King)
+        .unwrap();
+    let rook_xs = (0..8u8)
+        .filter(|x| rank[*x as usize] == PieceKind::Rook)
+        .collect();
+    (king_x, rook_xs)
+}
+
+pub fn create_signature(n: usize) -> signature::Signature {
+    let (king_x, rook_xs) = king_and_rook_files(&back_rank(n));
+    create_signature_with_castles(
+        Topology::Flat,
+        rook_xs
+            .iter()
+            .map(|rook_x| castle_signature(0, king_x, *rook_x))
+            .collect(),
+        rook_xs
+            .iter()
+            .map(|rook_x| castle_signature(7, king_x, *rook_x))
+            .collect(),
+    )
+}
+
+pub fn create_game(n: usize) -> Board {
+    let rank = back_rank(n);
+
+    let mut white_pieces = HashMap::new();
+    let mut black_pieces = HashMap::new();
+    for x in 0..8u8 {
+        white_pieces.insert(grid_to_sq(x, 1), PieceKind::Pawn(EnCroissantable::No));
+        white_pieces.insert(grid_to_sq(x, 0), rank[x as usize]);
+        black_pieces.insert(grid_to_sq(x, 6), PieceKind::Pawn(EnCroissantable::No));
+        black_pieces.insert(grid_to_sq(x, 7), rank[x as usize]);
+    }
+
+    Board::new(Team::White, create_signature(n), white_pieces, black_pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_back_ranks() {
+        assert_eq!(
+            back_rank(CLASSICAL_POSITION),
+            [
+                PieceKind::Rook,
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Queen,
+                PieceKind::King,
+                PieceKind::Bishop,
+                PieceKind::Knight,
+                PieceKind::Rook,
+            ]
+        );
+
+        let mut seen = HashSet::new();
+        for n in 0..NUM_POSITIONS {
+            let rank = back_rank(n);
+            assert!(seen.insert(rank));
+            let bishops: Vec<usize> = (0..8).filter(|x| rank[*x] == PieceKind::Bishop).collect();
+            assert_eq!(bishops.len(), 2);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+            let (king_x, rook_xs) = king_and_rook_files(&rank);
+            assert_eq!(rook_xs.len(), 2);
+            assert!(rook_xs[0] < king_x && king_x < rook_xs[1]);
+        }
+    }
+
+    #[test]
+    fn test_castle_signatures() {
+        //classical castling
+        let long = castle_signature(0, 4, 0);
+        assert_eq!(long.king_to, grid_to_sq(2, 0));
+        assert_eq!(long.rook_to, grid_to_sq(3, 0));
+        assert_eq!(long.not_chcked, vec![grid_to_sq(3, 0)]);
+        assert_eq!(long.not_occupied, vec![grid_to_sq(1, 0)]);
+        let short = castle_signature(0, 4, 7);
+        assert_eq!(short.not_chcked, vec![grid_to_sq(5, 0)]);
+        assert_eq!(short.not_occupied, vec![]);
+
+        //king on b1 with the rook on its right castles through c1 to f1
+        let cs = castle_signature(0, 1, 2);
+        assert_eq!(cs.king_to, grid_to_sq(6, 0));
+        assert_eq!(cs.rook_to, grid_to_sq(5, 0));
+        assert_eq!(
+            cs.not_chcked,
+            (2..6).map(|x| grid_to_sq(x, 0)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            cs.not_occupied,
+            (3..5).map(|x| grid_to_sq(x, 0)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_castling() {
+        let castles = |fen: &str| {
+            let mut board = fen::from_fen(fen).unwrap();
+            let data = board_data::BoardData::new(&mut board);
+            let mut castles = vec![];
+            for m in data.get_moves() {
+                if let Move::Castle {
+                    king_to, rook_to, ..
+                } = m
+                {
+                    castles.push((sq_to_grid(*king_to), sq_to_grid(*rook_to)));
+                    //make and unmake to run the debug assertions
+                    let mut test_board = board.clone();
+                    test_board.make_move(*m);
+                    test_board.unmake_move().unwrap();
+                    assert_eq!(test_board, board);
+                }
+            }
+            castles.sort();
+            castles
+        };
+
+        //the king on b1 passes over the rook on c1
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/1KR5 w C - 0 1"),
+            vec![((6, 0), (5, 0))]
+        );
+        //the king stays on g1 and only the rook moves
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/R5KR w HA - 0 1"),
+            vec![((2, 0), (3, 0)), ((6, 0), (5, 0))]
+        );
+        //the king may not pass through check on f1
+        assert_eq!(castles("5rk1/8/8/8/8/8/8/1KR5 w C - 0 1"), vec![]);
+        //the rook on b1 must get out of the way
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/RN4KR w HA - 0 1"),
+            vec![((6, 0), (5, 0))]
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastlingNotation {
+    //KQkq where that identifies the rook, otherwise the file of the rook. For classical positions this is plain FEN
+    XFen,
+    //always the file of the rook, e.g. HAha
+    Shredder,
+}
+
+fn piece_char(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn(..) => 'p',
+        PieceKind::Grasshopper => 'g',
+        PieceKind::Rook => 'r',
+        PieceKind::Knight => 'n',
+        PieceKind::Bishop => 'b',
+        PieceKind::Queen => 'q',
+        PieceKind::Archbishop => 'a',
+        PieceKind::Chancellor => 'c',
+        PieceKind::King => 'k',
+        //fairy pieces have no agreed letter
+        PieceKind::Custom(..) => '?',
+    }
+}
+
+fn char_piece(c: char) -> Option<PieceKind> {
+    match c.to_ascii_lowercase() {
+        'p' => Some(PieceKind::Pawn(EnCroissantable::No)),
+        'g' => Some(PieceKind::Grasshopper),
+        'r' => Some(PieceKind::Rook),
+        'n' => Some(PieceKind::Knight),
+        'b' => Some(PieceKind::Bishop),
+        'q' => Some(PieceKind::Queen),
+        'a' => Some(PieceKind::Archbishop),
+        'c' => Some(PieceKind::Chancellor),
+        'k' => Some(PieceKind::King),
+        _ => None,
+    }
+}
+
+fn back_rank_y(team: Team) -> u8 {
+    match team {
+        Team::White => 0,
+        Team::Black => 7,
+    }
+}
+
+fn square_name(sq: Square) -> String {
+    let (x, y) = sq_to_grid(sq);
+    format!("{}{}", (b'a' + x) as char, y + 1)
+}
+
+pub fn to_fen(board: &Board, notation: CastlingNotation) -> String {
+    let mut fen = String::new();
+
+    for y in (0..8u8).rev() {
+        let mut empty = 0;
+        for x in 0..8u8 {
+            match board.get_square(grid_to_sq(x, y)) {
+                Some(piece) => {
+                    if empty != 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    let c = piece_char(piece.kind);
+                    fen.push(match piece.team {
+                        Team::White => c.to_ascii_uppercase(),
+                        Team::Black => c,
+                    });
+                }
+                None => {
+                    empty += 1;
+                }
+            }
+        }
+        if empty != 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if y != 0 {
+            fen.push('/');
+        }
+    }
+
+    fen.push(' ');
+    fen.push(match board.get_turn() {
+        Team::White => 'w',
+        Team::Black => 'b',
+    });
+
+    //a castle is available while both its king and rook are unmoved
+    let mut castling = String::new();
+    for team in [Team::White, Team::Black] {
+        let mut rights = vec![];
+        for (cs_team, cs) in board.get_signature().get_castles() {
+            if cs_team != team {
+                continue;
+            }
+            if let (Some(king), Some(rook)) = (
+                board.get_square(cs.king_from),
+                board.get_square(cs.rook_from),
+            ) {
+                if king.kind == PieceKind::King
+                    && rook.kind == PieceKind::Rook
+                    && king.team == team
+                    && rook.team == team
+                    && !king.moved
+                    && !rook.moved
+                {
+                    rights.push((sq_to_grid(cs.king_from).0, sq_to_grid(cs.rook_from).0));
+                }
+            }
+        }
+        //kingside first
+        rights.sort_by_key(|(_king_x, rook_x)| 7 - rook_x);
+        for (king_x, rook_x) in rights {
+            let y = back_rank_y(team);
+            //the rook is outermost if no other rook of ours lies between it and the edge
+            let outermost = if rook_x > king_x {
+                rook_x + 1..8
+            } else {
+                0..rook_x
+            }
+            .all(|x| {
+                board.get_square(grid_to_sq(x, y))
+                    != Some(Piece {
+                        kind: PieceKind::Rook,
+                        team,
+                        moved: false,
+                    })
+            });
+            let c = match notation {
+                CastlingNotation::XFen if outermost && rook_x > king_x => 'k',
+                CastlingNotation::XFen if outermost => 'q',
+                _ => (b'a' + rook_x) as char,
+            };
+            castling.push(match team {
+                Team::White => c.to_ascii_uppercase(),
+                Team::Black => c,
+            });
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    fen.push(' ');
+    fen.push_str(&castling);
+
+    //en passant is possible if a pawn double stepped on the previous move
+    let mut en_passant = String::from("-");
+    for (_sq, piece) in board.get_pieces() {
+        if let PieceKind::Pawn(EnCroissantable::Yes { move_num, take_sq }) = piece.kind {
+            if move_num == board.get_move_num() {
+                en_passant = square_name(take_sq);
+            }
+        }
+    }
+    fen.push(' ');
+    fen.push_str(&en_passant);
+
+    //halfmove clock is not tracked
+    fen.push_str(&format!(" 0 {}", 1 + board.get_move_num() / 2));
+
+    fen
+}
+
+//accepts FEN, X-FEN and Shredder-FEN castling fields
+pub fn from_fen(fen: &str) -> Result<Board, String> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+        return Err(format!("expected 4 to 6 fields but found {}", fields.len()));
+    }
+
+    let mut white_pieces = HashMap::new();
+    let mut black_pieces = HashMap::new();
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(format!("expected 8 ranks but found {}", ranks.len()));
+    }
+    for (i, rank) in ranks.iter().enumerate() {
+        let y = 7 - i as u8;
+        let mut x = 0u8;
+        for c in rank.chars() {
+            if let Some(d) = c.to_digit(10) {
+                x += d as u8;
+                if x > 8 {
+                    return Err(format!("rank {} is too long", y + 1));
+                }
+            } else {
+                let kind = match char_piece(c) {
+                    Some(kind) => kind,
+                    None => return Err(format!("unknown piece '{}'", c)),
+                };
+                if x >= 8 {
+                    return Err(format!("rank {} is too long", y + 1));
+                }
+                if c.is_ascii_uppercase() {
+                    white_pieces.insert(grid_to_sq(x, y), kind);
+                } else {
+                    black_pieces.insert(grid_to_sq(x, y), kind);
+                }
+                x += 1;
+            }
+        }
+        if x != 8 {
+            return Err(format!("rank {} does not have 8 squares", y + 1));
+        }
+    }
+    for (team, pieces) in [(Team::White, &white_pieces), (Team::Black, &black_pieces)] {
+        if pieces.values().filter(|k| **k == PieceKind::King).count() != 1 {
+            return Err(format!("{:?} must have exactly one king", team));
+        }
+    }
+
+    let turn = match fields[1] {
+        "w" => Team::White,
+        "b" => Team::Black,
+        other => return Err(format!("unknown side to move '{}'", other)),
+    };
+
+    let mut white_castles = vec![];
+    let mut black_castles = vec![];
+    if fields[2] != "-" {
+        for c in fields[2].chars() {
+            let (team, pieces, castles) = if c.is_ascii_uppercase() {
+                (Team::White, &white_pieces, &mut white_castles)
+            } else {
+                (Team::Black, &black_pieces, &mut black_castles)
+            };
+            let y = back_rank_y(team);
+            let is_kind = |x: u8, kind: PieceKind| pieces.get(&grid_to_sq(x, y)) == Some(&kind);
+            let king_x = match (0..8u8).find(|x| is_kind(*x, PieceKind::King)) {
+                Some(king_x) => king_x,
+                None => return Err(format!("castling '{}' needs the king on its back rank", c)),
+            };
+            let rook_x = match c.to_ascii_lowercase() {
+                'k' => (king_x + 1..8).rev().find(|x| is_kind(*x, PieceKind::Rook)),
+                'q' => (0..king_x).find(|x| is_kind(*x, PieceKind::Rook)),
+                f @ 'a'..='h' => Some(f as u8 - b'a').filter(|x| is_kind(*x, PieceKind::Rook)),
+                _ => return Err(format!("unknown castling right '{}'", c)),
+            };
+            let rook_x = match rook_x {
+                Some(rook_x) => rook_x,
+                None => return Err(format!("no rook for castling right '{}'", c)),
+            };
+            //'K' and 'H' may name the same rook
+            let cs = castle_signature(y, king_x, rook_x);
+            if castles
+                .iter()
+                .any(|other: &CastleSignature| other.rook_from == cs.rook_from)
+            {
+                return Err(format!("repeated castling right '{}'", c));
+            }
+            castles.push(cs);
+        }
+    }
+
+    if fields[3] != "-" {
+        let sq_chars: Vec<char> = fields[3].chars().collect();
+        if sq_chars.len() != 2
+            || !('a'..='h').contains(&sq_chars[0])
+            || !('1'..='8').contains(&sq_chars[1])
+        {
+            return Err(format!("invalid en passant square '{}'", fields[3]));
+        }
+        let (x, take_y) = (sq_chars[0] as u8 - b'a', sq_chars[1] as u8 - b'1');
+        //the pawn which just double stepped sits one square beyond the take square
+        let (pawn_y, pieces) = match (turn, take_y) {
+            (Team::White, 5) => (4, &mut black_pieces),
+            (Team::Black, 2) => (3, &mut white_pieces),
+            _ => return Err(format!("invalid en passant square '{}'", fields[3])),
+        };
+        match pieces.get_mut(&grid_to_sq(x, pawn_y)) {
+            Some(kind @ PieceKind::Pawn(..)) => {
+                *kind = PieceKind::Pawn(EnCroissantable::Yes {
+                    move_num: 0,
+                    take_sq: grid_to_sq(x, take_y),
+                });
+            }
+            _ => return Err(format!("no pawn to take en passant on '{}'", fields[3])),
+        }
+    }
+
+    Ok(Board::new(
+        turn,
+        create_signature_with_castles(Topology::Flat, white_castles, black_castles),
+        white_pieces,
+        black_pieces,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for (fen, notation) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                CastlingNotation::XFen,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1",
+                CastlingNotation::Shredder,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b Kq e3 0 1",
+                CastlingNotation::XFen,
+            ),
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 0 1",
+                CastlingNotation::Shredder,
+            ),
+            //the inner rook on b1 is named by its file
+            ("4k3/8/8/8/8/8/8/RR2K3 w B - 0 1", CastlingNotation::XFen),
+        ] {
+            let board = from_fen(fen).unwrap();
+            assert_eq!(to_fen(&board, notation), fen);
+        }
+
+        //all notations describe the same position
+        let board = from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(
+            to_fen(&board, CastlingNotation::Shredder),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+        assert_eq!(board, ClassicalGameType::Classical.create_game());
+    }
+
+    #[test]
+    fn test_chess960() {
+        for n in 0..chess960::NUM_POSITIONS {
+            let board = chess960::create_game(n);
+            let fen = to_fen(&board, CastlingNotation::Shredder);
+            assert_eq!(
+                to_fen(&from_fen(&fen).unwrap(), CastlingNotation::Shredder),
+                fen
+            );
+            let xfen = to_fen(&board, CastlingNotation::XFen);
+            assert!(xfen.contains(" w KQkq - "));
+            assert_eq!(from_fen(&xfen).unwrap(), board);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for fen in [
+            //digits adding up past the end of the rank
+            "4k3/8/8/8/8/8/8/99K w - - 0 1",
+            "4k3/8/8/8/8/8/8/9999999999999999999999999999K w - - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3R w - - 0 1",
+            //the same rook named twice
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KKkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KHkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQqa - 0 1",
+        ] {
+            assert!(from_fen(fen).is_err(), "{}", fen);
+        }
+    }
+}
@@ -3,7 +3,6 @@ use std::time::{Duration, Instant};
 use glium::glutin::event::ElementState;
 use glium::{implement_vertex, uniform, Program, Surface};
 
-use crate::classical;
 use crate::graphical::Canvas;
 
 use self::ai::BigData;
@@ -12,14 +11,6 @@ use self::{Move, MoveIdx};
 use super::super::generic;
 use super::*;
 
-#[derive(Debug)]
-struct Rect {
-    x: f64,
-    y: f64,
-    w: f64,
-    h: f64,
-}
-
 struct Textures {
     white_pawn: glium::texture::Texture2d,
     white_grasshopper: glium::texture::Texture2d,
@@ -27,6 +18,8 @@ struct Textures {
     white_knight: glium::texture::Texture2d,
     white_bishop: glium::texture::Texture2d,
     white_queen: glium::texture::Texture2d,
+    white_archbishop: glium::texture::Texture2d,
+    white_chancellor: glium::texture::Texture2d,
     white_king: glium::texture::Texture2d,
     black_pawn: glium::texture::Texture2d,
     black_grasshopper: glium::texture::Texture2d,
@@ -34,16 +27,24 @@ struct Textures {
     black_knight: glium::texture::Texture2d,
     black_bishop: glium::texture::Texture2d,
     black_queen: glium::texture::Texture2d,
+    black_archbishop: glium::texture::Texture2d,
+    black_chancellor: glium::texture::Texture2d,
     black_king: glium::texture::Texture2d,
+    //white and black icons for custom pieces, found by the name of the piece
+    fairies: HashMap<&'static str, (glium::texture::Texture2d, glium::texture::Texture2d)>,
 }
 
-fn load_texture(
-    facade: &impl glium::backend::Facade,
-    filename: &'static str,
-) -> glium::texture::Texture2d {
+const FAIRY_ICONS: [&str; 13] = [
+    "archer", "berolina", "cannon", "elephant", "ferz", "giraffe", "man", "prince", "ship", "tank",
+    "unicorn", "wazir", "zebra",
+];
+//the icon for custom pieces with a name not in FAIRY_ICONS
+const DEFAULT_FAIRY_ICON: &str = "man";
+
+fn load_texture(facade: &impl glium::backend::Facade, filename: &str) -> glium::texture::Texture2d {
     let image = image::load(
         std::io::BufReader::new(
-            std::fs::File::open(String::from("src/classical/icons/") + &filename).unwrap(),
+            std::fs::File::open(String::from("src/classical/icons/") + filename).unwrap(),
         ),
         image::ImageFormat::Png,
     )
@@ -64,6 +65,8 @@ impl Textures {
             white_knight: load_texture(facade, "white knight.png"),
             white_bishop: load_texture(facade, "white bishop.png"),
             white_queen: load_texture(facade, "white queen.png"),
+            white_archbishop: load_texture(facade, "white archbish.png"),
+            white_chancellor: load_texture(facade, "white chancellor.png"),
             white_king: load_texture(facade, "white king.png"),
             black_pawn: load_texture(facade, "black pawn.png"),
             black_grasshopper: load_texture(facade, "black grasshopper.png"),
@@ -71,7 +74,127 @@ impl Textures {
             black_knight: load_texture(facade, "black knight.png"),
             black_bishop: load_texture(facade, "black bishop.png"),
             black_queen: load_texture(facade, "black queen.png"),
+            black_archbishop: load_texture(facade, "black archbish.png"),
+            black_chancellor: load_texture(facade, "black chancellor.png"),
             black_king: load_texture(facade, "black king.png"),
+            fairies: FAIRY_ICONS
+                .iter()
+                .map(|name| {
+                    let white = load_texture(facade, &format!("white {}.png", name));
+                    let black = load_texture(facade, &format!("black {}.png", name));
+                    (*name, (white, black))
+                })
+                .collect(),
+        }
+    }
+
+    fn get(&self, signature: &signature::Signature, piece: &Piece) -> &glium::texture::Texture2d {
+        if let PieceKind::Custom(idx) = piece.kind {
+            let name = signature.get_custom_piece(idx).name.as_str();
+            let (white, black) = match self.fairies.get(name) {
+                Some(textures) => textures,
+                None => &self.fairies[DEFAULT_FAIRY_ICON],
+            };
+            return match piece.team {
+                Team::White => white,
+                Team::Black => black,
+            };
+        }
+        match piece {
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Pawn(..),
+                ..
+            } => &self.white_pawn,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Grasshopper,
+                ..
+            } => &self.white_grasshopper,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Rook,
+                ..
+            } => &self.white_rook,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Knight,
+                ..
+            } => &self.white_knight,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Bishop,
+                ..
+            } => &self.white_bishop,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Queen,
+                ..
+            } => &self.white_queen,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Archbishop,
+                ..
+            } => &self.white_archbishop,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::Chancellor,
+                ..
+            } => &self.white_chancellor,
+            Piece {
+                team: Team::White,
+                kind: PieceKind::King,
+                ..
+            } => &self.white_king,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Pawn(..),
+                ..
+            } => &self.black_pawn,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Grasshopper,
+                ..
+            } => &self.black_grasshopper,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Rook,
+                ..
+            } => &self.black_rook,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Knight,
+                ..
+            } => &self.black_knight,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Bishop,
+                ..
+            } => &self.black_bishop,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Queen,
+                ..
+            } => &self.black_queen,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Archbishop,
+                ..
+            } => &self.black_archbishop,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::Chancellor,
+                ..
+            } => &self.black_chancellor,
+            Piece {
+                team: Team::Black,
+                kind: PieceKind::King,
+                ..
+            } => &self.black_king,
+            Piece {
+                kind: PieceKind::Custom(..),
+                ..
+            } => unreachable!(),
         }
     }
 }
@@ -110,13 +233,256 @@ impl Textures {
 //     }
 // }
 
+//a place on the screen which can be clicked, being a square of the board or a slot of a team's pocket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Square(Square),
+    Pocket(Team, usize),
+}
+
+//how the cells of a game are laid out on the screen
+//positions are in units of the game's choosing, with the centre of the drawing at the origin and y going up
+pub trait Geometry {
+    //the width and height of the drawing
+    fn size(&self) -> (f64, f64);
+
+    //the corners of the cell, in order around it
+    fn outline(&self, cell: Cell) -> Vec<(f64, f64)>;
+
+    fn colour(&self, cell: Cell) -> [f32; 3];
+
+    //the cell under the point, if there is one
+    fn cell_at(&self, point: (f64, f64)) -> Option<Cell>;
+
+    fn centre(&self, cell: Cell) -> (f64, f64) {
+        let outline = self.outline(cell);
+        let n = outline.len() as f64;
+        let (x, y) = outline
+            .iter()
+            .fold((0.0, 0.0), |(x, y), corner| (x + corner.0, y + corner.1));
+        (x / n, y / n)
+    }
+
+    //how many kinds of piece each pocket has room to show
+    fn pocket_slots(&self) -> usize {
+        0
+    }
+
+    //the width and height of the icon of a piece
+    fn icon_size(&self) -> f64 {
+        1.0
+    }
+}
+
+//a rectangle of squares indexed by x + y * files, with white's first rank at the top
+//in games with drops there is a column either side of the board for the pockets
+pub struct GridGeometry {
+    files: u8,
+    ranks: u8,
+    pocket_cols: u8,
+}
+
+impl GridGeometry {
+    pub fn new(files: u8, ranks: u8, drops: bool) -> Self {
+        Self {
+            files,
+            ranks,
+            pocket_cols: match drops {
+                true => 1,
+                false => 0,
+            },
+        }
+    }
+
+    //the cells drawn, being the board and any pocket columns
+    fn layout_dims(&self) -> (u8, u8) {
+        (self.files + 2 * self.pocket_cols, self.ranks)
+    }
+
+    //the column and row of the cell counting from the top left
+    //white's pocket runs down the left column from white's side, black's up the right from black's
+    fn position(&self, cell: Cell) -> (u8, u8) {
+        let (layout_w, layout_h) = self.layout_dims();
+        match cell {
+            Cell::Square(sq) => {
+                assert!(sq.idx < self.files as usize * self.ranks as usize);
+                (
+                    (sq.idx % self.files as usize) as u8 + self.pocket_cols,
+                    (sq.idx / self.files as usize) as u8,
+                )
+            }
+            Cell::Pocket(Team::White, i) => (0, i as u8),
+            Cell::Pocket(Team::Black, i) => (layout_w - 1, layout_h - 1 - i as u8),
+        }
+    }
+}
+
+impl Geometry for GridGeometry {
+    fn size(&self) -> (f64, f64) {
+        let (layout_w, layout_h) = self.layout_dims();
+        (layout_w as f64, layout_h as f64)
+    }
+
+    fn outline(&self, cell: Cell) -> Vec<(f64, f64)> {
+        let (w, h) = self.size();
+        let (x, y) = self.position(cell);
+        let (left, top) = (x as f64 - w / 2.0, h / 2.0 - y as f64);
+        vec![
+            (left, top - 1.0),
+            (left + 1.0, top - 1.0),
+            (left + 1.0, top),
+            (left, top),
+        ]
+    }
+
+    fn colour(&self, cell: Cell) -> [f32; 3] {
+        match cell {
+            Cell::Square(sq) => {
+                match (sq.idx % self.files as usize + sq.idx / self.files as usize) % 2 {
+                    0 => [0.9, 0.5, 0.15],
+                    _ => [0.6, 0.3, 0.05],
+                }
+            }
+            Cell::Pocket(..) => [0.3, 0.15, 0.05],
+        }
+    }
+
+    fn cell_at(&self, point: (f64, f64)) -> Option<Cell> {
+        let (w, h) = self.size();
+        let (x, y) = ((point.0 + w / 2.0).floor(), (h / 2.0 - point.1).floor());
+        if x < 0.0 || x >= w || y < 0.0 || y >= h {
+            return None;
+        }
+        let (x, y) = (x as u8, y as u8);
+        if x < self.pocket_cols {
+            Some(Cell::Pocket(Team::White, y as usize))
+        } else if x >= self.files + self.pocket_cols {
+            Some(Cell::Pocket(Team::Black, (self.ranks - 1 - y) as usize))
+        } else {
+            Some(Cell::Square(Square {
+                idx: (x - self.pocket_cols) as usize + y as usize * self.files as usize,
+            }))
+        }
+    }
+
+    fn pocket_slots(&self) -> usize {
+        match self.pocket_cols {
+            0 => 0,
+            _ => self.ranks as usize,
+        }
+    }
+}
+
+//a game the interface can play, and how its board is drawn
+pub trait Game {
+    fn geometry(&self) -> Box<dyn Geometry>;
+    fn create_game(&self) -> Board;
+
+    //the weights the ai evaluates positions with
+    fn eval_params(&self) -> generic::evaluation::EvalParams {
+        generic::evaluation::EvalParams::default()
+    }
+}
+
+//a game whose ai uses evaluation weights other than the defaults
+pub struct Tuned {
+    pub game: Box<dyn Game>,
+    pub params: generic::evaluation::EvalParams,
+}
+
+impl Game for Tuned {
+    fn geometry(&self) -> Box<dyn Geometry> {
+        self.game.geometry()
+    }
+
+    fn create_game(&self) -> Board {
+        self.game.create_game()
+    }
+
+    fn eval_params(&self) -> generic::evaluation::EvalParams {
+        self.params.clone()
+    }
+}
+
+//a game whose ai looks up positions with few pieces in tablebases
+pub struct WithTablebases {
+    pub game: Box<dyn Game>,
+    pub tablebases: std::sync::Arc<generic::tablebase::Tablebases>,
+}
+
+impl Game for WithTablebases {
+    fn geometry(&self) -> Box<dyn Geometry> {
+        self.game.geometry()
+    }
+
+    fn create_game(&self) -> Board {
+        let mut board = self.game.create_game();
+        board.set_tablebases(self.tablebases.clone());
+        board
+    }
+
+    fn eval_params(&self) -> generic::evaluation::EvalParams {
+        self.game.eval_params()
+    }
+}
+
+//a game of classical chess whose ai looks up positions with few pieces in the syzygy tables
+pub struct WithSyzygy {
+    pub game: Box<dyn Game>,
+    pub syzygy: std::sync::Arc<generic::syzygy::Syzygy>,
+}
+
+impl Game for WithSyzygy {
+    fn geometry(&self) -> Box<dyn Geometry> {
+        self.game.geometry()
+    }
+
+    fn create_game(&self) -> Board {
+        let mut board = self.game.create_game();
+        board.set_syzygy(self.syzygy.clone());
+        board
+    }
+
+    fn eval_params(&self) -> generic::evaluation::EvalParams {
+        self.game.eval_params()
+    }
+}
+
+impl Game for ClassicalGameType {
+    fn geometry(&self) -> Box<dyn Geometry> {
+        Box::new(GridGeometry::new(
+            8,
+            8,
+            matches!(self, ClassicalGameType::Crazyhouse),
+        ))
+    }
+
+    fn create_game(&self) -> Board {
+        ClassicalGameType::create_game(self)
+    }
+}
+
 struct MoveButton {
-    pos: (u8, u8),
+    cell: Cell,
     colour: (f32, f32, f32),
     move_idx: generic::MoveIdx,
 }
 
+#[derive(Copy, Clone)]
+struct CellVertex {
+    vert: [f32; 2],
+    colour: [f32; 3],
+}
+implement_vertex!(CellVertex, vert, colour);
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    vert: [f32; 2],
+}
+implement_vertex!(Vertex, vert);
+
 pub struct GameInterface {
+    geometry: Box<dyn Geometry>,
     board: Board,
     moves: Vec<Move>,
     board_ai: Option<generic::ai::AiOn>,
@@ -125,64 +491,92 @@ pub struct GameInterface {
     move_buttons: Vec<MoveButton>,
     big_datas: Vec<Box<dyn BigData>>,
     last_action_time: Instant,
-    selected: Option<(u8, u8)>,
+    selected: Option<Cell>,
     textures: Textures,
-    board_program: Program,
+    cell_program: Program,
     texture_program: Program,
     highlight_program: Program,
 }
 
 impl GameInterface {
-    fn get_board_pixel_rect(&self, state: &crate::graphical::State) -> Rect {
-        if state.display_size.0 >= state.display_size.1 {
-            let pad = (state.display_size.0 as f64 - state.display_size.1 as f64) / 2.0;
-            Rect {
-                x: pad,
-                y: 0.0,
-                w: state.display_size.1 as f64,
-                h: state.display_size.1 as f64,
+    //pixels per unit of the geometry, with the whole drawing fitting on the display
+    fn get_unit_pixels(&self, state: &crate::graphical::State) -> f64 {
+        let (display_w, display_h) = (state.display_size.0 as f64, state.display_size.1 as f64);
+        let (w, h) = self.geometry.size();
+        (display_w / w).min(display_h / h)
+    }
+
+    //units of the geometry to normalised device coordinates
+    fn get_scale(&self, state: &crate::graphical::State) -> (f32, f32) {
+        let size = self.get_unit_pixels(state);
+        (
+            (2.0 * size / state.display_size.0 as f64) as f32,
+            (2.0 * size / state.display_size.1 as f64) as f32,
+        )
+    }
+
+    fn pixel_to_cell(&self, state: &crate::graphical::State, pixels: (f64, f64)) -> Option<Cell> {
+        let size = self.get_unit_pixels(state);
+        let x = (pixels.0 - state.display_size.0 as f64 / 2.0) / size;
+        let y = (state.display_size.1 as f64 / 2.0 - pixels.1) / size;
+        self.geometry.cell_at((x, y))
+    }
+
+    //triangles fanning out from the centre of the cell to its corners
+    fn cell_vertices(&self, cell: Cell) -> Vec<Vertex> {
+        let centre = self.geometry.centre(cell);
+        let corners = self.geometry.outline(cell);
+        let mut vertices = vec![];
+        for i in 0..corners.len() {
+            for (x, y) in [centre, corners[i], corners[(i + 1) % corners.len()]] {
+                vertices.push(Vertex {
+                    vert: [x as f32, y as f32],
+                });
             }
-        } else {
-            let pad = (state.display_size.1 as f64 - state.display_size.0 as f64) / 2.0;
-            Rect {
-                x: 0.0,
-                y: pad,
-                w: state.display_size.0 as f64,
-                h: state.display_size.0 as f64,
+        }
+        vertices
+    }
+
+    //the kinds of piece in each pocket and how many of each, as many as there is room to show
+    fn pocket_cells(&self) -> Vec<(Cell, PieceKind, usize)> {
+        let mut cells = vec![];
+        for team in [Team::White, Team::Black] {
+            for (i, (kind, count)) in self
+                .board
+                .get_pocket(team)
+                .iter()
+                .take(self.geometry.pocket_slots())
+                .enumerate()
+            {
+                cells.push((Cell::Pocket(team, i), *kind, *count));
             }
         }
+        cells
     }
 
-    fn pixel_to_square(
-        &self,
-        state: &crate::graphical::State,
-        pixels: (f64, f64),
-    ) -> Option<(u8, u8)> {
-        let rect = self.get_board_pixel_rect(state);
-        let x_frac = (8.0 * (pixels.0 - rect.x) / rect.w).floor() as i128;
-        let y_frac = (8.0 * (pixels.1 - rect.y) / rect.h).floor() as i128;
-        if 0 <= x_frac && x_frac < 8 && 0 <= y_frac && y_frac < 8 {
-            Some((x_frac as u8, y_frac as u8))
-        } else {
-            None
+    fn cell_to_pocket(&self, cell: Cell) -> Option<(Team, PieceKind)> {
+        match cell {
+            Cell::Square(_) => None,
+            Cell::Pocket(team, _) => self
+                .pocket_cells()
+                .into_iter()
+                .find(|(pocket_cell, ..)| *pocket_cell == cell)
+                .map(|(_cell, kind, _count)| (team, kind)),
         }
     }
 }
 
 impl Canvas for GameInterface {
-    type Init = ClassicalGameType;
+    type Init = Box<dyn Game>;
 
-    fn new(facade: &impl glium::backend::Facade, init: ClassicalGameType) -> Self {
+    fn new(facade: &impl glium::backend::Facade, init: Box<dyn Game>) -> Self {
         let board = init.create_game();
-        let board_ai_off = generic::ai::AiOff::new(board.clone());
-        let moves = board_ai_off
-            .get_moves()
-            .into_iter()
-            .map(|m| m.clone())
-            .collect();
+        let board_ai_off = generic::ai::AiOff::with_params(board.clone(), init.eval_params());
+        let moves = board_ai_off.get_moves().into_iter().copied().collect();
         let board_ai = board_ai_off.start();
 
         Self {
+            geometry: init.geometry(),
             board,
             moves,
             board_ai: Some(board_ai),
@@ -193,58 +587,30 @@ impl Canvas for GameInterface {
             big_datas: vec![],
             last_action_time: Instant::now(),
             textures: Textures::new(facade),
-            board_program: {
+            cell_program: {
                 let vertex_shader_src = r#"
                     #version 330
 
                     in vec2 vert;
-                    out vec2 v_vert;
-                    uniform vec2 display_size;
-    
-                    void main() {
-                        float board_x;
-                        float board_y;
-                        float board_w;
-                        float board_h;
-                        if (display_size.x > display_size.y) {
-                            float pad = display_size.x - display_size.y;
-                            board_x = 2.0 * (0.5 * pad / display_size.x) - 1.0;
-                            board_y = 1.0;
-                            board_w = 2.0 * (display_size.x - pad) / display_size.x;
-                            board_h = -2.0;
-                        } else {
-                            float pad = display_size.y - display_size.x;
-                            board_x = -1.0;
-                            board_y = -2.0 * (0.5 * pad / display_size.y) + 1.0;
-                            board_w = 2.0;
-                            board_h = -2.0 * (display_size.y - pad) / display_size.y;
-                        }
+                    in vec3 colour;
+                    out vec3 v_colour;
+                    uniform vec2 scale;
 
-                        gl_Position = vec4(board_x + vert.x * board_w, board_y + vert.y * board_h, 0.0, 1.0);
-                        v_vert = vert;
+                    void main() {
+                        gl_Position = vec4(vert * scale, 0.0, 1.0);
+                        v_colour = colour;
                     }
                 "#;
 
                 let fragment_shader_src = r#"
                     #version 330
-                    
-                    in vec2 v_vert;
+
+                    in vec3 v_colour;
 
                     out vec4 f_color;
-    
+
                     void main() {
-                        int c = 0;
-                        if (mod(v_vert.x * 8, 2) < 1) {
-                            c ++;
-                        }
-                        if (mod(v_vert.y * 8, 2) < 1) {
-                            c ++;
-                        }
-                        if (c == 1)  {
-                            f_color = vec4(0.6, 0.3, 0.05, 1.0);
-                        } else {
-                            f_color = vec4(0.9, 0.5, 0.15, 1.0);
-                        }
+                        f_color = vec4(v_colour, 1.0);
                     }
                 "#;
 
@@ -257,44 +623,27 @@ impl Canvas for GameInterface {
 
                     in vec2 vert;
                     out vec2 v_vert;
-                    uniform vec2 display_size;
-                    uniform vec2 square;
-    
-                    void main() {
-                        float board_x;
-                        float board_y;
-                        float board_w;
-                        float board_h;
-                        if (display_size.x > display_size.y) {
-                            float pad = display_size.x - display_size.y;
-                            board_x = 2.0 * (0.5 * pad / display_size.x) - 1.0;
-                            board_y = 1.0;
-                            board_w = 2.0 * (display_size.x - pad) / display_size.x;
-                            board_h = -2.0;
-                        } else {
-                            float pad = display_size.y - display_size.x;
-                            board_x = -1.0;
-                            board_y = -2.0 * (0.5 * pad / display_size.y) + 1.0;
-                            board_w = 2.0;
-                            board_h = -2.0 * (display_size.y - pad) / display_size.y;
-                        }
+                    uniform vec2 scale;
+                    uniform vec2 centre;
+                    uniform float size;
 
-                        gl_Position = vec4(board_x + board_w * vert.x / 8.0 + square.x * board_w / 8.0, board_y + board_h * vert.y / 8.0 + square.y * board_h / 8.0, 0.0, 1.0);
+                    void main() {
+                        gl_Position = vec4((centre + size * (vert - 0.5)) * scale, 0.0, 1.0);
                         v_vert = vert;
                     }
                 "#;
 
                 let fragment_shader_src = r#"
                     #version 330
-                    
+
                     in vec2 v_vert;
 
                     uniform sampler2D tex;
 
                     out vec4 f_color;
-    
+
                     void main() {
-                        f_color = texture(tex, vec2(v_vert.x, 1 - v_vert.y));
+                        f_color = texture(tex, v_vert);
                     }
                 "#;
 
@@ -306,43 +655,20 @@ impl Canvas for GameInterface {
                     #version 330
 
                     in vec2 vert;
-                    out vec2 v_vert;
-                    uniform vec2 display_size;
-                    uniform vec2 square;
-    
-                    void main() {
-                        float board_x;
-                        float board_y;
-                        float board_w;
-                        float board_h;
-                        if (display_size.x > display_size.y) {
-                            float pad = display_size.x - display_size.y;
-                            board_x = 2.0 * (0.5 * pad / display_size.x) - 1.0;
-                            board_y = 1.0;
-                            board_w = 2.0 * (display_size.x - pad) / display_size.x;
-                            board_h = -2.0;
-                        } else {
-                            float pad = display_size.y - display_size.x;
-                            board_x = -1.0;
-                            board_y = -2.0 * (0.5 * pad / display_size.y) + 1.0;
-                            board_w = 2.0;
-                            board_h = -2.0 * (display_size.y - pad) / display_size.y;
-                        }
+                    uniform vec2 scale;
 
-                        gl_Position = vec4(board_x + board_w * vert.x / 8.0 + square.x * board_w / 8.0, board_y + board_h * vert.y / 8.0 + square.y * board_h / 8.0, 0.0, 1.0);
-                        v_vert = vert;
+                    void main() {
+                        gl_Position = vec4(vert * scale, 0.0, 1.0);
                     }
                 "#;
 
                 let fragment_shader_src = r#"
                     #version 330
-                    
-                    in vec2 v_vert;
 
                     uniform vec3 colour;
 
                     out vec4 f_color;
-    
+
                     void main() {
                         f_color = vec4(colour, 0.5);
                     }
@@ -354,47 +680,49 @@ impl Canvas for GameInterface {
         }
     }
 
-    fn tick(&mut self, state: &crate::graphical::State, dt: f64) {
-        let _ = &state;
-        let _ = &dt;
+    fn tick(&mut self, _state: &crate::graphical::State, _dt: f64) {
+        //the trees left behind by moves are dropped once the player has paused, since that takes a while
         if Instant::now() - self.last_action_time > Duration::from_millis(1000)
             && !self.big_datas.is_empty()
         {
-            println!("Started Cleanup...");
             self.big_datas = vec![];
-            println!("Finished Cleanup");
         }
     }
 
     fn draw(&mut self, state: &crate::graphical::State, display: &glium::Display) {
         let mut target = display.draw();
         target.clear_color(0.0, 0.3, 0.0, 1.0);
+        let scale = self.get_scale(state);
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
 
         {
-            #[derive(Copy, Clone)]
-            struct Vertex {
-                vert: [f32; 2],
+            let mut cells = (0..self.board.get_signature().num())
+                .map(|idx| Cell::Square(Square { idx }))
+                .collect::<Vec<_>>();
+            for team in [Team::White, Team::Black] {
+                for i in 0..self.geometry.pocket_slots() {
+                    cells.push(Cell::Pocket(team, i));
+                }
+            }
+            let mut shape = vec![];
+            for cell in cells {
+                let colour = self.geometry.colour(cell);
+                for v in self.cell_vertices(cell) {
+                    shape.push(CellVertex {
+                        vert: v.vert,
+                        colour,
+                    });
+                }
             }
-            implement_vertex!(Vertex, vert);
-
-            let shape = vec![
-                Vertex { vert: [0.0, 0.0] },
-                Vertex { vert: [0.0, 1.0] },
-                Vertex { vert: [1.0, 1.0] },
-                Vertex { vert: [1.0, 0.0] },
-            ];
 
             let vertex_buffer = glium::VertexBuffer::new(display, &shape).unwrap();
-            let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan);
-
             target
                 .draw(
                     &vertex_buffer,
-                    &indices,
-                    &self.board_program,
-                    // &glium::uniforms::EmptyUniforms,
+                    indices,
+                    &self.cell_program,
                     &uniform! {
-                        display_size : (state.display_size.0 as f32, state.display_size.1 as f32),
+                        scale : scale,
                     },
                     &Default::default(),
                 )
@@ -402,12 +730,6 @@ impl Canvas for GameInterface {
         }
 
         {
-            #[derive(Copy, Clone)]
-            struct Vertex {
-                vert: [f32; 2],
-            }
-            implement_vertex!(Vertex, vert);
-
             let shape = vec![
                 Vertex { vert: [0.0, 0.0] },
                 Vertex { vert: [0.0, 1.0] },
@@ -418,151 +740,39 @@ impl Canvas for GameInterface {
             let vertex_buffer = glium::VertexBuffer::new(display, &shape).unwrap();
             let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan);
 
-            for (sq_idx, piece) in self.board.get_pieces() {
-                let sq = classical::sq_to_grid(sq_idx);
-                let tex = match piece {
-                    Piece {
-                        team: Team::White,
-                        kind: PieceKind::Pawn(..),
-                        ..
-                    } => &self.textures.white_pawn,
-                    Piece {
-                        team: Team::White,
-                        kind: PieceKind::Grasshopper,
-                        ..
-                    } => &self.textures.white_grasshopper,
-                    Piece {
-                        team: Team::White,
-                        kind: PieceKind::Rook,
-                        ..
-                    } => &self.textures.white_rook,
-                    Piece {
-                        team: Team::White,
-                        kind: PieceKind::Knight,
-                        ..
-                    } => &self.textures.white_knight,
-                    Piece {
-                        team: Team::White,
-                        kind: PieceKind::Bishop,
-                        ..
-                    } => &self.textures.white_bishop,
-                    Piece {
-                        team: Team::White,
-                        kind: PieceKind::Queen,
-                        ..
-                    } => &self.textures.white_queen,
-                    Piece {
-                        team: Team::White,
-                        kind: PieceKind::King,
-                        ..
-                    } => &self.textures.white_king,
-                    Piece {
-                        team: Team::Black,
-                        kind: PieceKind::Pawn(..),
-                        ..
-                    } => &self.textures.black_pawn,
-                    Piece {
-                        team: Team::Black,
-                        kind: PieceKind::Grasshopper,
-                        ..
-                    } => &self.textures.black_grasshopper,
-                    Piece {
-                        team: Team::Black,
-                        kind: PieceKind::Rook,
-                        ..
-                    } => &self.textures.black_rook,
-                    Piece {
-                        team: Team::Black,
-                        kind: PieceKind::Knight,
-                        ..
-                    } => &self.textures.black_knight,
-                    Piece {
-                        team: Team::Black,
-                        kind: PieceKind::Bishop,
-                        ..
-                    } => &self.textures.black_bishop,
-                    Piece {
-                        team: Team::Black,
-                        kind: PieceKind::Queen,
-                        ..
-                    } => &self.textures.black_queen,
-                    Piece {
-                        team: Team::Black,
-                        kind: PieceKind::King,
-                        ..
-                    } => &self.textures.black_king,
-                };
-                target
-                .draw(
-                    &vertex_buffer,
-                    &indices,
-                    &self.texture_program,
-                    // &glium::uniforms::EmptyUniforms,
-                    &uniform! {
-                        display_size : (state.display_size.0 as f32, state.display_size.1 as f32),
-                        square : (sq.0 as f32, sq.1 as f32),
-                        tex: tex,
-                    },
-                    &glium::DrawParameters {
-                        blend: glium::Blend::alpha_blending(),
-                        ..Default::default()
-                    },
-                )
-                .unwrap();
-            }
-        }
-
-        {
-            #[derive(Copy, Clone)]
-            struct Vertex {
-                vert: [f32; 2],
+            //pocketed pieces of a kind are drawn stacked, up to a few deep
+            let mut icons = vec![];
+            for (sq, piece) in self.board.get_pieces() {
+                icons.push((self.geometry.centre(Cell::Square(sq)), piece));
             }
-            implement_vertex!(Vertex, vert);
-
-            let shape = vec![
-                Vertex { vert: [0.0, 0.0] },
-                Vertex { vert: [0.0, 1.0] },
-                Vertex { vert: [1.0, 1.0] },
-                Vertex { vert: [1.0, 0.0] },
-            ];
-
-            let vertex_buffer = glium::VertexBuffer::new(display, &shape).unwrap();
-            let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan);
-
-            match self.selected {
-                Some(square) => {
-                    target
-                    .draw(
-                        &vertex_buffer,
-                        &indices,
-                        &self.highlight_program,
-                        // &glium::uniforms::EmptyUniforms,
-                        &uniform! {
-                            display_size : (state.display_size.0 as f32, state.display_size.1 as f32),
-                            square : (square.0 as f32, square.1 as f32),
-                            colour : (0.0f32, 1.0f32, 0.0f32),
-                        },
-                        &glium::DrawParameters {
-                            blend: glium::Blend::alpha_blending(),
-                            ..Default::default()
-                        },
-                    )
-                    .unwrap();
+            for (cell, kind, count) in self.pocket_cells() {
+                let (x, y) = self.geometry.centre(cell);
+                let piece = Piece {
+                    team: match cell {
+                        Cell::Pocket(team, _) => team,
+                        Cell::Square(_) => unreachable!(),
+                    },
+                    kind,
+                    moved: true,
+                };
+                for i in (0..count.min(4)).rev() {
+                    let offset = 0.1 * i as f64;
+                    icons.push(((x + offset, y + offset), piece));
                 }
-                None => {}
             }
 
-            for move_button in &self.move_buttons {
+            for ((x, y), piece) in icons {
+                let tex = self.textures.get(self.board.get_signature(), &piece);
                 target
                     .draw(
                         &vertex_buffer,
-                        &indices,
-                        &self.highlight_program,
-                        // &glium::uniforms::EmptyUniforms,
+                        indices,
+                        &self.texture_program,
                         &uniform! {
-                            display_size : (state.display_size.0 as f32, state.display_size.1 as f32),
-                            square : (move_button.pos.0 as f32, move_button.pos.1 as f32),
-                            colour : move_button.colour,
+                            scale : scale,
+                            centre : (x as f32, y as f32),
+                            size : self.geometry.icon_size() as f32,
+                            tex: tex,
                         },
                         &glium::DrawParameters {
                             blend: glium::Blend::alpha_blending(),
@@ -571,34 +781,48 @@ impl Canvas for GameInterface {
                     )
                     .unwrap();
             }
+        }
 
+        {
+            let mut highlights = vec![];
+            if let Some(cell) = self.selected {
+                highlights.push((cell, (0.0f32, 1.0f32, 0.0f32)));
+            }
+            for move_button in &self.move_buttons {
+                highlights.push((move_button.cell, move_button.colour));
+            }
             if match self.board.get_turn() {
                 Team::White => self.show_white_ai,
                 Team::Black => self.show_black_ai,
             } {
-                match self.board_ai.as_ref().unwrap().current_best_move() {
-                    Some(m_idx) => {
-                        let m = &self.moves[m_idx.idx];
-                        let squares = match m {
-                            Move::Standard { from_sq, to_sq, .. } => vec![from_sq, to_sq],
-                            Move::Castle {
-                                king_from, king_to, ..
-                            } => vec![king_from, king_to],
-                            Move::EnCroissant {
-                                pawn_from, pawn_to, ..
-                            } => vec![pawn_from, pawn_to],
-                        };
-                        for square in squares.into_iter().map(|s| sq_to_grid(*s)) {
-                            target
+                if let Some(m_idx) = self.board_ai.as_ref().unwrap().current_best_move() {
+                    let squares = match &self.moves[m_idx.idx] {
+                        Move::Standard { from_sq, to_sq, .. } => vec![from_sq, to_sq],
+                        Move::Castle {
+                            king_from, king_to, ..
+                        } => vec![king_from, king_to],
+                        Move::EnCroissant {
+                            pawn_from, pawn_to, ..
+                        } => vec![pawn_from, pawn_to],
+                        Move::Drop { to_sq, .. } => vec![to_sq],
+                    };
+                    for sq in squares {
+                        highlights.push((Cell::Square(*sq), (1.0, 0.2, 0.0)));
+                    }
+                }
+            }
+
+            for (cell, colour) in highlights {
+                let vertex_buffer =
+                    glium::VertexBuffer::new(display, &self.cell_vertices(cell)).unwrap();
+                target
                     .draw(
                         &vertex_buffer,
-                        &indices,
+                        indices,
                         &self.highlight_program,
-                        // &glium::uniforms::EmptyUniforms,
                         &uniform! {
-                            display_size : (state.display_size.0 as f32, state.display_size.1 as f32),
-                            square : (square.0 as f32, square.1 as f32),
-                            colour : (1.0f32, 0.2f32, 0.0f32),
+                            scale : scale,
+                            colour : colour,
                         },
                         &glium::DrawParameters {
                             blend: glium::Blend::alpha_blending(),
@@ -606,10 +830,6 @@ impl Canvas for GameInterface {
                         },
                     )
                     .unwrap();
-                        }
-                    }
-                    None => {}
-                }
             }
         }
 
@@ -621,69 +841,65 @@ impl Canvas for GameInterface {
         interface_state: &crate::graphical::State,
         ev: &glium::glutin::event::Event<'_, ()>,
     ) {
-        match ev {
-            glium::glutin::event::Event::DeviceEvent {
-                device_id: _,
-                event,
-            } => match event {
+        if let glium::glutin::event::Event::DeviceEvent {
+            device_id: _,
+            event,
+        } = ev
+        {
+            match event {
                 glium::glutin::event::DeviceEvent::Button { button, state } => {
-                    match (button, state) {
-                        (1, ElementState::Pressed) => {
-                            match self.pixel_to_square(interface_state, interface_state.mouse_pos) {
-                                Some(clicked) => {
-                                    let mut move_idx_opt = None;
-
-                                    for move_button in self.move_buttons.iter() {
-                                        if move_button.pos == clicked {
-                                            move_idx_opt = Some(move_button.move_idx);
-                                        }
+                    if let (1, ElementState::Pressed) = (button, state) {
+                        match self.pixel_to_cell(interface_state, interface_state.mouse_pos) {
+                            Some(clicked) => {
+                                let mut move_idx_opt = None;
+
+                                for move_button in self.move_buttons.iter() {
+                                    if move_button.cell == clicked {
+                                        move_idx_opt = Some(move_button.move_idx);
                                     }
+                                }
 
-                                    match move_idx_opt {
-                                        Some(move_idx) => {
-                                            self.make_move(move_idx);
-                                        }
-                                        None => {
-                                            match self
-                                                .board
-                                                .get_square(grid_to_sq(clicked.0, clicked.1))
-                                            {
-                                                Some(piece) => {
-                                                    if piece.team == self.board.get_turn() {
-                                                        {
-                                                            self.set_selected(Some(clicked));
-                                                        }
-                                                    } else {
-                                                        {
-                                                            self.set_selected(None);
-                                                        }
-                                                    }
-                                                }
-                                                None => {
-                                                    self.set_selected(None);
-                                                }
+                                match move_idx_opt {
+                                    Some(move_idx) => {
+                                        self.make_move(move_idx);
+                                    }
+                                    None => {
+                                        let clicked_team = match clicked {
+                                            Cell::Square(sq) => {
+                                                self.board.get_square(sq).map(|piece| piece.team)
+                                            }
+                                            Cell::Pocket(..) => self
+                                                .cell_to_pocket(clicked)
+                                                .map(|(team, _kind)| team),
+                                        };
+                                        match clicked_team {
+                                            Some(team) if team == self.board.get_turn() => {
+                                                self.set_selected(Some(clicked));
+                                            }
+                                            _ => {
+                                                self.set_selected(None);
                                             }
                                         }
                                     }
                                 }
-                                None => {
-                                    self.set_selected(None);
-                                }
+                            }
+                            None => {
+                                self.set_selected(None);
                             }
                         }
-                        _ => {}
                     };
                 }
                 glium::glutin::event::DeviceEvent::Key(k) => match (k.state, k.virtual_keycode) {
                     (
                         glium::glutin::event::ElementState::Pressed,
                         Some(glium::glutin::event::VirtualKeyCode::Return),
-                    ) => match self.board_ai.as_ref().unwrap().current_best_move() {
-                        Some(current_best_move) => {
+                    ) => {
+                        if let Some(current_best_move) =
+                            self.board_ai.as_ref().unwrap().current_best_move()
+                        {
                             self.make_move(current_best_move);
                         }
-                        None => {}
-                    },
+                    }
                     (
                         glium::glutin::event::ElementState::Pressed,
                         Some(glium::glutin::event::VirtualKeyCode::Back),
@@ -703,69 +919,86 @@ impl Canvas for GameInterface {
                     _ => {}
                 },
                 _ => {}
-            },
-            _ => {}
+            }
         }
     }
 }
 
 impl GameInterface {
-    fn set_selected(&mut self, selected: Option<(u8, u8)>) {
+    fn set_selected(&mut self, selected: Option<Cell>) {
         self.selected = selected;
         self.move_buttons = vec![];
-        match self.selected {
-            Some(pos) => {
-                // let sq = grid_to_sq(pos.0, pos.1);
-                for (m_idx, m) in self
-                    .moves
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, m)| (MoveIdx { idx }, m))
-                {
-                    match m {
-                        Move::Standard {
-                            victim: victim_opt,
-                            from_sq,
-                            to_sq,
-                            ..
-                        } => {
-                            if grid_to_sq(pos.0, pos.1) == *from_sq {
-                                self.move_buttons.push(MoveButton {
-                                    pos: sq_to_grid(*to_sq),
-                                    colour: match victim_opt {
-                                        Some(_) => (1.0, 0.0, 0.0),
-                                        None => (0.0, 0.5, 1.0),
-                                    },
-                                    move_idx: m_idx,
-                                });
-                            }
+        if let Some(cell) = self.selected {
+            let selected_sq = match cell {
+                Cell::Square(sq) => Some(sq),
+                Cell::Pocket(..) => None,
+            };
+            let selected_pocket = self.cell_to_pocket(cell);
+            for (m_idx, m) in self
+                .moves
+                .iter()
+                .enumerate()
+                .map(|(idx, m)| (MoveIdx { idx }, m))
+            {
+                match m {
+                    Move::Standard {
+                        victim: victim_opt,
+                        from_sq,
+                        to_sq,
+                        ..
+                    } => {
+                        if selected_sq == Some(*from_sq) {
+                            self.move_buttons.push(MoveButton {
+                                cell: Cell::Square(*to_sq),
+                                colour: match victim_opt {
+                                    Some(_) => (1.0, 0.0, 0.0),
+                                    None => (0.0, 0.5, 1.0),
+                                },
+                                move_idx: m_idx,
+                            });
                         }
-                        Move::Castle {
-                            king_from, king_to, ..
-                        } => {
-                            if grid_to_sq(pos.0, pos.1) == *king_from {
-                                self.move_buttons.push(MoveButton {
-                                    pos: sq_to_grid(*king_to),
-                                    colour: (0.0, 0.5, 1.0),
-                                    move_idx: m_idx,
-                                });
-                            }
+                    }
+                    Move::Castle {
+                        king_from,
+                        king_to,
+                        rook_from,
+                        ..
+                    } => {
+                        if selected_sq == Some(*king_from) {
+                            //in chess960 the king may not move at all, in which case click on the rook
+                            self.move_buttons.push(MoveButton {
+                                cell: Cell::Square(if king_to == king_from {
+                                    *rook_from
+                                } else {
+                                    *king_to
+                                }),
+                                colour: (0.0, 0.5, 1.0),
+                                move_idx: m_idx,
+                            });
                         }
-                        Move::EnCroissant {
-                            pawn_from, pawn_to, ..
-                        } => {
-                            if grid_to_sq(pos.0, pos.1) == *pawn_from {
-                                self.move_buttons.push(MoveButton {
-                                    pos: sq_to_grid(*pawn_to),
-                                    colour: (1.0, 0.0, 0.0),
-                                    move_idx: m_idx,
-                                });
-                            }
+                    }
+                    Move::EnCroissant {
+                        pawn_from, pawn_to, ..
+                    } => {
+                        if selected_sq == Some(*pawn_from) {
+                            self.move_buttons.push(MoveButton {
+                                cell: Cell::Square(*pawn_to),
+                                colour: (1.0, 0.0, 0.0),
+                                move_idx: m_idx,
+                            });
+                        }
+                    }
+                    Move::Drop { piece, to_sq } => {
+                        if selected_pocket == Some((piece.team, piece.kind)) {
+                            self.move_buttons.push(MoveButton {
+                                cell: Cell::Square(*to_sq),
+                                colour: (0.0, 0.5, 1.0),
+                                move_idx: m_idx,
+                            });
                         }
                     }
                 }
             }
-            None => {}
         }
     }
 
@@ -775,7 +1008,7 @@ impl GameInterface {
         let big_data = ai_off.make_move(m);
         self.big_datas.push(big_data);
         self.board = ai_off.get_board().clone();
-        self.moves = ai_off.get_moves().into_iter().map(|m| m.clone()).collect();
+        self.moves = ai_off.get_moves().into_iter().copied().collect();
         self.board_ai = Some(ai_off.start());
         self.last_action_time = Instant::now();
     }
@@ -785,8 +1018,41 @@ impl GameInterface {
         let (mut ai_off, _best_move) = self.board_ai.take().unwrap().finish();
         let _ = ai_off.unmake_move();
         self.board = ai_off.get_board().clone();
-        self.moves = ai_off.get_moves().into_iter().map(|m| m.clone()).collect();
+        self.moves = ai_off.get_moves().into_iter().copied().collect();
         self.board_ai = Some(ai_off.start());
         self.last_action_time = Instant::now();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_geometry() {
+        //every cell is found again from its centre, pockets included
+        for drops in [false, true] {
+            let geometry = GridGeometry::new(10, 8, drops);
+            let mut cells = (0..80)
+                .map(|idx| Cell::Square(Square { idx }))
+                .collect::<Vec<_>>();
+            for team in [Team::White, Team::Black] {
+                for i in 0..geometry.pocket_slots() {
+                    cells.push(Cell::Pocket(team, i));
+                }
+            }
+            for cell in cells {
+                assert_eq!(geometry.cell_at(geometry.centre(cell)), Some(cell));
+            }
+            let (w, h) = geometry.size();
+            assert_eq!(geometry.cell_at((w / 2.0 + 0.5, 0.0)), None);
+            assert_eq!(geometry.cell_at((0.0, h / 2.0 + 0.5)), None);
+        }
+        //white's first rank is along the top
+        let geometry = GridGeometry::new(8, 8, false);
+        assert_eq!(
+            geometry.centre(Cell::Square(Square { idx: 0 })),
+            (-3.5, 3.5)
+        );
+    }
+}
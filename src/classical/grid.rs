@@ -0,0 +1,193 @@
+use super::Topology;
+use crate::generic::*;
+use std::collections::HashMap;
+
+use self::signature::CastleSignature;
+
+//a board of files and ranks, square idx = x + files * y, with its edges glued together as the topology says
+//the classical board, capablanca's 10x8 board and the boards of variant files are all laid out this way
+#[derive(Debug, Clone, Copy)]
+pub struct Grid {
+    pub files: u8,
+    pub ranks: u8,
+    pub topology: Topology,
+}
+
+impl Grid {
+    pub fn sq(&self, x: u8, y: u8) -> Square {
+        assert!(x < self.files && y < self.ranks);
+        Square {
+            idx: x as usize + y as usize * self.files as usize,
+        }
+    }
+
+    pub fn xy(&self, sq: Square) -> (u8, u8) {
+        assert!(sq.idx < self.files as usize * self.ranks as usize);
+        (
+            (sq.idx % self.files as usize) as u8,
+            (sq.idx / self.files as usize) as u8,
+        )
+    }
+
+    pub fn wrap(&self, x: i8, y: i8) -> Option<Square> {
+        let (files, ranks) = (self.files as i8, self.ranks as i8);
+        let (x, y) = match self.topology {
+            Topology::Flat => (x, y),
+            Topology::Cylinder => (x.rem_euclid(files), y),
+            Topology::Torus => (x.rem_euclid(files), y.rem_euclid(ranks)),
+        };
+        if 0 <= x && x < files && 0 <= y && y < ranks {
+            Some(self.sq(x as u8, y as u8))
+        } else {
+            None
+        }
+    }
+
+    //reflect top to bottom, taking white's squares to black's
+    pub fn mirror(&self, sq: Square) -> Square {
+        let (x, y) = self.xy(sq);
+        self.sq(x, self.ranks - 1 - y)
+    }
+
+    pub fn parse_square(&self, name: &str) -> Option<Square> {
+        let mut chars = name.chars();
+        let file = chars.next()?;
+        if !file.is_ascii_lowercase() {
+            return None;
+        }
+        let x = file as u8 - b'a';
+        let y = chars.as_str().parse::<u8>().ok()?.checked_sub(1)?;
+        if x < self.files && y < self.ranks {
+            Some(self.sq(x, y))
+        } else {
+            None
+        }
+    }
+}
+
+//castling on rank y of a board with so many files, the king and rook each moving along the rank to their destinations
+//every square either passes over must be empty, and the king may not pass through check
+//as in chess960 the king and rook may start on each other's destinations
+pub fn castle_signature(
+    files: u8,
+    y: u8,
+    (king_x, rook_x): (u8, u8),
+    (king_to_x, rook_to_x): (u8, u8),
+) -> CastleSignature {
+    assert!(king_x != rook_x && king_to_x != rook_to_x);
+    let sq = |x: u8| Square {
+        idx: x as usize + y as usize * files as usize,
+    };
+    let between = |a: u8, b: u8| a.min(b)..=a.max(b);
+    let mut not_occupied = vec![];
+    for x in 0..files {
+        if (between(king_x, king_to_x).contains(&x) || between(rook_x, rook_to_x).contains(&x))
+            && ![king_x, rook_x, king_to_x, rook_to_x].contains(&x)
+        {
+            not_occupied.push(sq(x));
+        }
+    }
+    CastleSignature {
+        king_from: sq(king_x),
+        king_to: sq(king_to_x),
+        rook_from: sq(rook_x),
+        rook_to: sq(rook_to_x),
+        not_chcked: between(king_x, king_to_x)
+            .filter(|x| *x != king_x && *x != king_to_x)
+            .map(sq)
+            .collect(),
+        not_occupied,
+    }
+}
+
+//white's pawns go up the board and black's down it, each promoting on the ranks given for white and mirrored for black
+//pawn_steps says whether a pawn of the team on a rank, counted from its own side, may move two squares, one or none
+pub fn create_signature(
+    grid: &Grid,
+    promotions: &[PieceKind],
+    promotion_ranks: &[u8],
+    pawn_steps: &dyn Fn(Team, u8) -> Option<bool>,
+    white_castles: Vec<CastleSignature>,
+    black_castles: Vec<CastleSignature>,
+) -> signature::Signature {
+    let (files, ranks) = (grid.files as i8, grid.ranks as i8);
+
+    let opp = |i: Square, j: Square| -> Vec<Square> {
+        let (xi, yi) = grid.xy(i);
+        let (xj, yj) = grid.xy(j);
+        //step from j in the direction from i to j, which may cross a glued edge
+        let (mut dx, mut dy) = (xj as i8 - xi as i8, yj as i8 - yi as i8);
+        if grid.topology != Topology::Flat {
+            dx = (dx + 1).rem_euclid(files) - 1;
+        }
+        if grid.topology == Topology::Torus {
+            dy = (dy + 1).rem_euclid(ranks) - 1;
+        }
+        match grid.wrap(xj as i8 + dx, yj as i8 + dy) {
+            Some(k) => vec![k],
+            None => vec![],
+        }
+    };
+
+    let nbs = |idx: Square, dirs: [(i8, i8); 4]| -> Vec<Square> {
+        let (x, y) = grid.xy(idx);
+        dirs.iter()
+            .filter_map(|(dx, dy)| grid.wrap(x as i8 + dx, y as i8 + dy))
+            .collect()
+    };
+    let flat_nbs = |idx: Square| nbs(idx, [(1, 0), (-1, 0), (0, 1), (0, -1)]);
+    let diag_nbs = |idx: Square| nbs(idx, [(1, 1), (1, -1), (-1, 1), (-1, -1)]);
+
+    //pawns never cross the glued ranks of the torus
+    let pawn_moves = |team: Team, idx: Square| -> Vec<(Square, Vec<Square>)> {
+        let (x, y) = match team {
+            Team::White => grid.xy(idx),
+            Team::Black => grid.xy(grid.mirror(idx)),
+        };
+        let unmirror = |sq: Square| match team {
+            Team::White => sq,
+            Team::Black => grid.mirror(sq),
+        };
+        if y + 1 >= grid.ranks {
+            return vec![];
+        }
+        let first = unmirror(grid.sq(x, y + 1));
+        match pawn_steps(team, y) {
+            None => vec![],
+            Some(true) if y + 2 < grid.ranks => {
+                vec![(first, vec![unmirror(grid.sq(x, y + 2))])]
+            }
+            Some(_) => vec![(first, vec![])],
+        }
+    };
+
+    let promotion_squares = |team: Team| -> HashMap<Square, Vec<PieceKind>> {
+        let mut squares = HashMap::new();
+        for y in promotion_ranks {
+            for x in 0..grid.files {
+                let sq = grid.sq(x, *y);
+                squares.insert(
+                    match team {
+                        Team::White => sq,
+                        Team::Black => grid.mirror(sq),
+                    },
+                    promotions.to_vec(),
+                );
+            }
+        }
+        squares
+    };
+
+    signature::Signature::new(
+        grid.files as usize * grid.ranks as usize,
+        &flat_nbs,
+        &diag_nbs,
+        &opp,
+        &opp,
+        &pawn_moves,
+        promotion_squares(Team::White),
+        promotion_squares(Team::Black),
+        white_castles,
+        black_castles,
+    )
+}
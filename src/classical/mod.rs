@@ -4,7 +4,11 @@ use std::collections::HashMap;
 use self::signature::CastleSignature;
 
 // pub mod terminal;
+pub mod chess960;
+pub mod fen;
 pub mod graphical;
+pub mod grid;
+pub mod piece_square;
 
 fn sq_to_grid(sq: Square) -> (u8, u8) {
     let idx = sq.idx;
@@ -14,156 +18,155 @@ fn sq_to_grid(sq: Square) -> (u8, u8) {
 
 fn grid_to_sq(x: u8, y: u8) -> Square {
     assert!(x < 8 && y < 8);
-    return Square {
+    Square {
         idx: (x + y * 8) as usize,
-    };
+    }
 }
 
 pub fn create_signature() -> signature::Signature {
-    let opp = |i: Square, j: Square| -> Vec<Square> {
-        let (xi, yi) = sq_to_grid(i);
-        let (xj, yj) = sq_to_grid(j);
-        let (xk, yk) = (2 * xj as i8 - xi as i8, 2 * yj as i8 - yi as i8);
-        if 0 <= xk && xk < 8 && 0 <= yk && yk < 8 {
-            return vec![grid_to_sq(xk as u8, yk as u8)];
-        }
-        return vec![];
-    };
+    create_signature_with_castles(
+        Topology::Flat,
+        vec![castle_signature(0, 4, 0), castle_signature(0, 4, 7)],
+        vec![castle_signature(7, 4, 0), castle_signature(7, 4, 7)],
+    )
+}
 
-    let flat_nbs = |idx: Square| -> Vec<Square> {
-        let mut nbs: Vec<Square> = vec![];
-        let (x, y) = sq_to_grid(idx);
-        for (dx, dy) in vec![(1i8, 0i8), (-1, 0), (0, 1), (0, -1)] {
-            let (ax, ay) = ((x as i8) + dx, (y as i8) + dy);
-            if 0 <= ax && ax < 8 && 0 <= ay && ay < 8 {
-                nbs.push(grid_to_sq(ax as u8, ay as u8));
-            }
-        }
-        return nbs;
+//castling as in chess960: the king ends on the c or g file with the rook beside it on the d or f file
+//in classical chess this reduces to the usual castling rules
+fn castle_signature(y: u8, king_x: u8, rook_x: u8) -> CastleSignature {
+    let to = match rook_x < king_x {
+        true => (2, 3),
+        false => (6, 5),
     };
+    grid::castle_signature(8, y, (king_x, rook_x), to)
+}
 
-    let diag_nbs = |idx: Square| -> Vec<Square> {
-        let mut nbs: Vec<Square> = vec![];
-        let (x, y) = sq_to_grid(idx);
-        for (dx, dy) in vec![(1i8, 1i8), (1, -1), (-1, 1), (-1, -1)] {
-            let (ax, ay) = ((x as i8) + dx, (y as i8) + dy);
-            if 0 <= ax && ax < 8 && 0 <= ay && ay < 8 {
-                nbs.push(grid_to_sq(ax as u8, ay as u8));
-            }
+//how the edges of the board are glued together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Flat,
+    Cylinder, //the first and last files are adjacent
+    Torus,    //the first and last files are adjacent and so are the first and last ranks
+}
+
+impl Topology {
+    //the ranks of the white and black pieces
+    //on the torus these are moved in from the edge so that the armies do not start touching across it
+    pub fn back_ranks(&self) -> (u8, u8) {
+        match self {
+            Topology::Flat | Topology::Cylinder => (0, 7),
+            Topology::Torus => (1, 6),
         }
-        return nbs;
-    };
+    }
+}
 
-    let pawn_moves = |team: Team, idx: Square| -> Vec<(Square, Vec<Square>)> {
-        let (x, y) = sq_to_grid(idx);
-
-        match (team, y) {
-            (_, 0) => vec![],
-            (_, 7) => vec![],
-            (Team::White, y) => {
-                if y == 1 {
-                    vec![(grid_to_sq(x, y + 1), vec![grid_to_sq(x, y + 2)])]
-                } else if y <= 6 {
-                    vec![(grid_to_sq(x, y + 1), vec![])]
-                } else {
-                    panic!()
-                }
-            }
-            (Team::Black, y) => {
-                if y == 6 {
-                    vec![(grid_to_sq(x, y - 1), vec![grid_to_sq(x, y - 2)])]
-                } else if y >= 1 {
-                    vec![(grid_to_sq(x, y - 1), vec![])]
-                } else {
-                    panic!()
-                }
-            }
+fn create_signature_with_castles(
+    topology: Topology,
+    white_castles: Vec<CastleSignature>,
+    black_castles: Vec<CastleSignature>,
+) -> signature::Signature {
+    create_signature_with_pawns(topology, white_castles, black_castles, false)
+}
+
+//in horde white's pawns start on the first rank too, and may move one or two squares from there
+fn create_signature_with_pawns(
+    topology: Topology,
+    white_castles: Vec<CastleSignature>,
+    black_castles: Vec<CastleSignature>,
+    white_first_rank_pawns: bool,
+) -> signature::Signature {
+    //pawns never cross the glued ranks of the torus
+    let (white_y, black_y) = topology.back_ranks();
+    let pawn_steps = |team: Team, y: u8| {
+        if white_first_rank_pawns && team == Team::White && y == white_y {
+            return Some(true);
+        }
+        match y <= white_y || y >= black_y {
+            true => None,
+            false => Some(y == white_y + 1),
         }
     };
-
-    signature::Signature::new(
-        64,
-        &flat_nbs,
-        &diag_nbs,
-        &opp,
-        &opp,
-        &pawn_moves,
-        (0..8)
-            .map(|x| {
-                (
-                    grid_to_sq(x, 7),
-                    vec![
-                        PieceKind::Knight,
-                        PieceKind::Bishop,
-                        PieceKind::Rook,
-                        PieceKind::Queen,
-                    ],
-                )
-            })
-            .into_iter()
-            .collect(),
-        (0..8)
-            .map(|x| {
-                (
-                    grid_to_sq(x, 0),
-                    vec![
-                        PieceKind::Knight,
-                        PieceKind::Bishop,
-                        PieceKind::Rook,
-                        PieceKind::Queen,
-                    ],
-                )
-            })
-            .into_iter()
-            .collect(),
-        vec![
-            CastleSignature {
-                king_from: grid_to_sq(4, 0),
-                king_to: grid_to_sq(2, 0),
-                rook_from: grid_to_sq(0, 0),
-                rook_to: grid_to_sq(3, 0),
-                not_chcked: vec![grid_to_sq(3, 0)],
-                not_occupied: vec![grid_to_sq(1, 0)],
-            },
-            CastleSignature {
-                king_from: grid_to_sq(4, 0),
-                king_to: grid_to_sq(6, 0),
-                rook_from: grid_to_sq(7, 0),
-                rook_to: grid_to_sq(5, 0),
-                not_chcked: vec![grid_to_sq(5, 0)],
-                not_occupied: vec![],
-            },
-        ],
-        vec![
-            CastleSignature {
-                king_from: grid_to_sq(4, 7),
-                king_to: grid_to_sq(2, 7),
-                rook_from: grid_to_sq(0, 7),
-                rook_to: grid_to_sq(3, 7),
-                not_chcked: vec![grid_to_sq(3, 7)],
-                not_occupied: vec![grid_to_sq(1, 7)],
-            },
-            CastleSignature {
-                king_from: grid_to_sq(4, 7),
-                king_to: grid_to_sq(6, 7),
-                rook_from: grid_to_sq(7, 7),
-                rook_to: grid_to_sq(5, 7),
-                not_chcked: vec![grid_to_sq(5, 7)],
-                not_occupied: vec![],
-            },
+    let signature = grid::create_signature(
+        &grid::Grid {
+            files: 8,
+            ranks: 8,
+            topology,
+        },
+        &[
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
         ],
-    )
+        &[black_y],
+        &pawn_steps,
+        white_castles,
+        black_castles,
+    );
+    //the hand-written tables assume the edges of the board are edges
+    match topology {
+        Topology::Flat => piece_square::with_tables(signature),
+        Topology::Cylinder | Topology::Torus => signature,
+    }
 }
 
 pub enum ClassicalGameType {
     Classical,
     Grasshopper,
+    Chess960(usize),
+    Cylinder,
+    Torus,
+    Crazyhouse,
+    Atomic,
+    Antichess,
+    KingOfTheHill,
+    ThreeCheck,
+    Horde,
+}
+
+//the usual armies on the back ranks of the topology, with castling as normal
+fn create_wrapped_game(topology: Topology) -> Board {
+    let (white_y, black_y) = topology.back_ranks();
+    let rank = chess960::back_rank(chess960::CLASSICAL_POSITION);
+
+    let mut white_pieces = HashMap::new();
+    let mut black_pieces = HashMap::new();
+    for x in 0..8u8 {
+        white_pieces.insert(
+            grid_to_sq(x, white_y + 1),
+            PieceKind::Pawn(EnCroissantable::No),
+        );
+        white_pieces.insert(grid_to_sq(x, white_y), rank[x as usize]);
+        black_pieces.insert(
+            grid_to_sq(x, black_y - 1),
+            PieceKind::Pawn(EnCroissantable::No),
+        );
+        black_pieces.insert(grid_to_sq(x, black_y), rank[x as usize]);
+    }
+
+    let signature = create_signature_with_castles(
+        topology,
+        vec![
+            castle_signature(white_y, 4, 0),
+            castle_signature(white_y, 4, 7),
+        ],
+        vec![
+            castle_signature(black_y, 4, 0),
+            castle_signature(black_y, 4, 7),
+        ],
+    );
+    Board::new(Team::White, signature, white_pieces, black_pieces)
 }
 
 impl ClassicalGameType {
     pub fn create_game(&self) -> Board {
         match self {
-            ClassicalGameType::Classical => {
+            ClassicalGameType::Classical
+            | ClassicalGameType::Crazyhouse
+            | ClassicalGameType::Atomic
+            | ClassicalGameType::Antichess
+            | ClassicalGameType::KingOfTheHill
+            | ClassicalGameType::ThreeCheck => {
                 //white team
                 let mut white_pieces = HashMap::new();
                 for x in 0..8u8 {
@@ -192,7 +195,28 @@ impl ClassicalGameType {
                 black_pieces.insert(grid_to_sq(6, 7), PieceKind::Knight);
                 black_pieces.insert(grid_to_sq(7, 7), PieceKind::Rook);
 
-                Board::new(Team::White, create_signature(), white_pieces, black_pieces)
+                //in crazyhouse captured pieces change sides and may be dropped back on the board
+                let signature = match self {
+                    ClassicalGameType::Crazyhouse => create_signature().with_drops(),
+                    //in atomic chess captures blow up everything around them but pawns
+                    ClassicalGameType::Atomic => create_signature().with_atomic(),
+                    ClassicalGameType::Antichess => create_signature().with_antichess(),
+                    ClassicalGameType::KingOfTheHill => {
+                        create_signature().with_rules(rules::KingOfTheHill {
+                            hill: vec![
+                                grid_to_sq(3, 3),
+                                grid_to_sq(4, 3),
+                                grid_to_sq(3, 4),
+                                grid_to_sq(4, 4),
+                            ],
+                        })
+                    }
+                    ClassicalGameType::ThreeCheck => {
+                        create_signature().with_rules(rules::ThreeCheck { checks: 3 })
+                    }
+                    _ => create_signature(),
+                };
+                Board::new(Team::White, signature, white_pieces, black_pieces)
             }
             ClassicalGameType::Grasshopper => {
                 //white team
@@ -231,6 +255,37 @@ impl ClassicalGameType {
 
                 Board::new(Team::White, create_signature(), white_pieces, black_pieces)
             }
+            ClassicalGameType::Horde => {
+                //white has 36 pawns and no king, and loses by having them all captured
+                let mut white_pieces = HashMap::new();
+                for x in 0..8u8 {
+                    for y in 0..4u8 {
+                        white_pieces.insert(grid_to_sq(x, y), PieceKind::Pawn(EnCroissantable::No));
+                    }
+                }
+                for x in [1, 2, 5, 6] {
+                    white_pieces.insert(grid_to_sq(x, 4), PieceKind::Pawn(EnCroissantable::No));
+                }
+
+                let mut black_pieces = HashMap::new();
+                let rank = chess960::back_rank(chess960::CLASSICAL_POSITION);
+                for x in 0..8u8 {
+                    black_pieces.insert(grid_to_sq(x, 6), PieceKind::Pawn(EnCroissantable::No));
+                    black_pieces.insert(grid_to_sq(x, 7), rank[x as usize]);
+                }
+
+                let signature = create_signature_with_pawns(
+                    Topology::Flat,
+                    vec![],
+                    vec![castle_signature(7, 4, 0), castle_signature(7, 4, 7)],
+                    true,
+                )
+                .with_rules(rules::Horde {});
+                Board::new(Team::White, signature, white_pieces, black_pieces)
+            }
+            ClassicalGameType::Chess960(n) => chess960::create_game(*n),
+            ClassicalGameType::Cylinder => create_wrapped_game(Topology::Cylinder),
+            ClassicalGameType::Torus => create_wrapped_game(Topology::Torus),
         }
     }
 }
@@ -254,4 +309,391 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_piece_square() {
+        let mirror = |sq: Square| {
+            let (x, y) = sq_to_grid(sq);
+            grid_to_sq(x, 7 - y)
+        };
+        for topology in [Topology::Flat, Topology::Cylinder] {
+            let signature = create_signature_with_castles(topology, vec![], vec![]);
+            let tables = signature.get_piece_square_tables();
+            for kind in [
+                PieceKind::Pawn(EnCroissantable::No),
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Rook,
+                PieceKind::Queen,
+                PieceKind::King,
+            ] {
+                for idx in 0..64 {
+                    let sq = Square { idx };
+                    let piece = |team| Piece {
+                        kind,
+                        team,
+                        moved: false,
+                    };
+                    assert_eq!(
+                        tables.value(&piece(Team::White), sq),
+                        tables.value(&piece(Team::Black), mirror(sq))
+                    );
+                }
+            }
+        }
+        let signature = create_signature_with_castles(Topology::Flat, vec![], vec![]);
+        let tables = signature.get_piece_square_tables();
+        let king = |sq| {
+            tables.value(
+                &Piece {
+                    kind: PieceKind::King,
+                    team: Team::White,
+                    moved: false,
+                },
+                sq,
+            )
+        };
+        //g1 is safer than e4 until the pieces come off
+        assert!(king(grid_to_sq(6, 0)).0 > king(grid_to_sq(4, 3)).0);
+        assert!(king(grid_to_sq(6, 0)).1 < king(grid_to_sq(4, 3)).1);
+
+        //white's knight on a1 is much worse than black's on f6
+        let mut board = Board::new(
+            Team::White,
+            signature,
+            HashMap::from([
+                (grid_to_sq(0, 0), PieceKind::Knight),
+                (grid_to_sq(4, 0), PieceKind::King),
+            ]),
+            HashMap::from([
+                (grid_to_sq(5, 5), PieceKind::Knight),
+                (grid_to_sq(4, 7), PieceKind::King),
+            ]),
+        );
+        match board_data::BoardData::new(&mut board).get_evaluation() {
+            score::Score::Heuristic(score) => assert!(score < 0),
+            score => panic!("{:?}", score),
+        }
+    }
+
+    #[test]
+    fn test_topologies() {
+        //a rook on a1 with the white king on d1
+        for (topology, expected) in [
+            (Topology::Flat, 9),
+            (Topology::Cylinder, 13),
+            (Topology::Torus, 13),
+        ] {
+            let mut board = Board::new(
+                Team::White,
+                create_signature_with_castles(topology, vec![], vec![]),
+                HashMap::from([
+                    (grid_to_sq(0, 0), PieceKind::Rook),
+                    (grid_to_sq(3, 0), PieceKind::King),
+                ]),
+                HashMap::from([(grid_to_sq(6, 5), PieceKind::King)]),
+            );
+            let data = board_data::BoardData::new(&mut board);
+            let rook_moves = data
+                .get_moves()
+                .into_iter()
+                .filter(
+                    |m| matches!(m, Move::Standard { from_sq, .. } if *from_sq == grid_to_sq(0, 0)),
+                )
+                .count();
+            assert_eq!(rook_moves, expected);
+        }
+
+        //slides around a closed loop end where they started
+        let signature = create_signature_with_castles(Topology::Torus, vec![], vec![]);
+        for slide in signature.get_flat_slides(grid_to_sq(2, 3)) {
+            assert_eq!(slide.len(), 8);
+            assert_eq!(slide.last(), Some(&grid_to_sq(2, 3)));
+        }
+        for slide in signature.get_diag_slides(grid_to_sq(2, 3)) {
+            assert_eq!(slide.len(), 8);
+            assert_eq!(slide.last(), Some(&grid_to_sq(2, 3)));
+        }
+
+        //play out every two move sequence so the debug legality checks run
+        for game_type in [ClassicalGameType::Cylinder, ClassicalGameType::Torus] {
+            let mut board = game_type.create_game();
+            for m in board_data::BoardData::new(&mut board).get_moves() {
+                board.make_move(*m);
+                board_data::BoardData::new(&mut board);
+                board.unmake_move().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_crazyhouse() {
+        let play = |board: &mut Board, from: (u8, u8), to: (u8, u8)| {
+            let m = *board_data::BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .find(|m| {
+                    matches!(m, Move::Standard { from_sq, to_sq, .. }
+                        if *from_sq == grid_to_sq(from.0, from.1) && *to_sq == grid_to_sq(to.0, to.1))
+                })
+                .unwrap();
+            board.make_move(m);
+        };
+        let drops = |board: &mut Board| -> Vec<Move> {
+            board_data::BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .filter(|m| matches!(m, Move::Drop { .. }))
+                .cloned()
+                .collect()
+        };
+
+        //1. e4 d5 2. exd5 puts a pawn in white's pocket
+        let mut board = ClassicalGameType::Crazyhouse.create_game();
+        let start = board.clone();
+        play(&mut board, (4, 1), (4, 3));
+        play(&mut board, (3, 6), (3, 4));
+        play(&mut board, (4, 3), (3, 4));
+        let pawn = PieceKind::Pawn(EnCroissantable::No);
+        assert_eq!(board.get_pocket(Team::White).get(&pawn), Some(&1));
+        play(&mut board, (3, 7), (3, 4));
+        assert_eq!(board.get_pocket(Team::Black).get(&pawn), Some(&1));
+
+        //pawns may go on any of the 34 empty squares except d8
+        let white_drops = drops(&mut board);
+        assert_eq!(white_drops.len(), 33);
+        for m in &white_drops {
+            if let Move::Drop { to_sq, .. } = m {
+                let (_x, y) = sq_to_grid(*to_sq);
+                assert!(y != 0 && y != 7);
+            }
+        }
+        board.make_move(white_drops[0]);
+        assert!(board.get_pocket(Team::White).is_empty());
+        board.unmake_move().unwrap();
+        assert_eq!(board.get_pocket(Team::White).get(&pawn), Some(&1));
+        for _ in 0..4 {
+            board.unmake_move().unwrap();
+        }
+        assert_eq!(board, start);
+
+        //a promoted piece goes back to the pocket as a pawn
+        let mut board = Board::new(
+            Team::White,
+            create_signature().with_drops(),
+            HashMap::from([
+                (grid_to_sq(0, 6), PieceKind::Pawn(EnCroissantable::No)),
+                (grid_to_sq(4, 0), PieceKind::King),
+            ]),
+            HashMap::from([
+                (grid_to_sq(7, 7), PieceKind::Rook),
+                (grid_to_sq(4, 5), PieceKind::King),
+            ]),
+        );
+        let promote = *board_data::BoardData::new(&mut board)
+            .get_moves()
+            .into_iter()
+            .find(|m| matches!(m, Move::Standard { to_piece, .. } if to_piece.kind == PieceKind::Queen))
+            .unwrap();
+        board.make_move(promote);
+        play(&mut board, (7, 7), (0, 7));
+        assert_eq!(board.get_pocket(Team::Black).get(&pawn), Some(&1));
+        assert!(drops(&mut board).is_empty());
+        board.unmake_move().unwrap();
+        assert!(board.get_pocket(Team::Black).is_empty());
+    }
+
+    #[test]
+    fn test_atomic() {
+        let atomic_game = |white: Vec<((u8, u8), PieceKind)>, black: Vec<((u8, u8), PieceKind)>| {
+            let pieces = |pieces: Vec<((u8, u8), PieceKind)>| {
+                pieces
+                    .into_iter()
+                    .map(|((x, y), kind)| (grid_to_sq(x, y), kind))
+                    .collect()
+            };
+            Board::new(
+                Team::White,
+                create_signature().with_atomic(),
+                pieces(white),
+                pieces(black),
+            )
+        };
+        let find = |board: &mut Board, from: (u8, u8), to: (u8, u8)| {
+            board_data::BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .find(|m| {
+                    matches!(m, Move::Standard { from_sq, to_sq, .. }
+                        if *from_sq == grid_to_sq(from.0, from.1) && *to_sq == grid_to_sq(to.0, to.1))
+                })
+                .cloned()
+        };
+        let pawn = PieceKind::Pawn(EnCroissantable::No);
+
+        //Qxf7 blows up the black king on e8 but not the pawn on g7
+        let mut board = atomic_game(
+            vec![((4, 0), PieceKind::King), ((7, 4), PieceKind::Queen)],
+            vec![((4, 7), PieceKind::King), ((5, 6), pawn), ((6, 6), pawn)],
+        );
+        let before = board.clone();
+        let m = find(&mut board, (7, 4), (5, 6)).unwrap();
+        board.make_move(m);
+        assert_eq!(board.get_square(grid_to_sq(5, 6)), None);
+        assert_eq!(board.get_square(grid_to_sq(4, 7)), None);
+        assert!(board.get_square(grid_to_sq(6, 6)).is_some());
+        let data = board_data::BoardData::new(&mut board);
+        assert!(data.is_terminal());
+        assert_eq!(data.get_evaluation(), score::Score::Lost(1));
+        board.unmake_move().unwrap();
+        assert_eq!(board, before);
+
+        //kings may not capture, and touching kings do not give check
+        let mut board = atomic_game(
+            vec![((4, 3), PieceKind::King), ((0, 4), PieceKind::Rook)],
+            vec![((4, 4), PieceKind::King), ((3, 3), PieceKind::Knight)],
+        );
+        assert!(find(&mut board, (4, 3), (3, 3)).is_none());
+        let m = find(&mut board, (0, 4), (1, 4)).unwrap();
+        board.make_move(m);
+        assert!(!board_data::BoardData::new(&mut board).is_check());
+
+        //play out every two move sequence so the explosions are made and unmade
+        let mut board = ClassicalGameType::Atomic.create_game();
+        let start = board.clone();
+        for m in board_data::BoardData::new(&mut board).get_moves() {
+            board.make_move(*m);
+            for reply in board_data::BoardData::new(&mut board).get_moves() {
+                board.make_move(*reply);
+                board_data::BoardData::new(&mut board);
+                board.unmake_move().unwrap();
+            }
+            board.unmake_move().unwrap();
+        }
+        assert_eq!(board, start);
+    }
+
+    #[test]
+    fn test_antichess() {
+        let moves = |board: &mut Board| -> Vec<Move> {
+            board_data::BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .cloned()
+                .collect()
+        };
+        let find = |moves: &Vec<Move>, from: (u8, u8), to: (u8, u8)| {
+            moves
+                .iter()
+                .find(|m| {
+                    matches!(m, Move::Standard { from_sq, to_sq, .. }
+                        if *from_sq == grid_to_sq(from.0, from.1) && *to_sq == grid_to_sq(to.0, to.1))
+                })
+                .cloned()
+                .unwrap()
+        };
+
+        //after 1. e3 b5 the bishop must take
+        let mut board = ClassicalGameType::Antichess.create_game();
+        let start_moves = moves(&mut board);
+        assert_eq!(start_moves.len(), 20);
+        board.make_move(find(&start_moves, (4, 1), (4, 2)));
+        let replies = moves(&mut board);
+        board.make_move(find(&replies, (1, 6), (1, 4)));
+        let forced = moves(&mut board);
+        assert_eq!(forced.len(), 1);
+        assert_eq!(forced[0], find(&forced, (5, 0), (1, 4)));
+
+        //the king is just another piece, and whoever runs out of pieces wins
+        let mut board = Board::new(
+            Team::White,
+            create_signature().with_antichess(),
+            HashMap::from([(grid_to_sq(4, 0), PieceKind::King)]),
+            HashMap::from([(grid_to_sq(3, 1), PieceKind::King)]),
+        );
+        let king_takes = moves(&mut board);
+        assert_eq!(king_takes.len(), 1);
+        board.make_move(king_takes[0]);
+        let data = board_data::BoardData::new(&mut board);
+        assert!(data.is_terminal());
+        assert_eq!(data.get_evaluation(), score::Score::Won(1));
+        board.unmake_move().unwrap();
+
+        //as does whoever is stalemated, here by a king boxed in by stuck pawns
+        let pawn = PieceKind::Pawn(EnCroissantable::No);
+        let mut board = Board::new(
+            Team::White,
+            create_signature().with_antichess(),
+            HashMap::from([
+                (grid_to_sq(0, 7), PieceKind::King),
+                (grid_to_sq(1, 7), pawn),
+                (grid_to_sq(0, 6), pawn),
+                (grid_to_sq(1, 6), pawn),
+            ]),
+            HashMap::from([(grid_to_sq(7, 0), PieceKind::King)]),
+        );
+        assert_eq!(
+            board_data::BoardData::new(&mut board).get_evaluation(),
+            score::Score::Won(0)
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        let play = |board: &mut Board, from: (u8, u8), to: (u8, u8)| {
+            let m = *board_data::BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .find(|m| {
+                    matches!(m, Move::Standard { from_sq, to_sq, .. }
+                        if *from_sq == grid_to_sq(from.0, from.1) && *to_sq == grid_to_sq(to.0, to.1))
+                })
+                .unwrap();
+            board.make_move(m);
+            board_data::BoardData::new(board)
+        };
+
+        //a king reaching the centre wins
+        let mut board = Board::new(
+            Team::White,
+            create_signature().with_rules(rules::KingOfTheHill {
+                hill: vec![grid_to_sq(3, 3)],
+            }),
+            HashMap::from([(grid_to_sq(3, 2), PieceKind::King)]),
+            HashMap::from([(grid_to_sq(7, 7), PieceKind::King)]),
+        );
+        let data = play(&mut board, (3, 2), (3, 3));
+        assert!(data.is_terminal());
+        assert_eq!(data.get_evaluation(), score::Score::Lost(1));
+
+        //Qh5+ Kd8 Qh4+ is the second check
+        let mut board = Board::new(
+            Team::White,
+            create_signature().with_rules(rules::ThreeCheck { checks: 2 }),
+            HashMap::from([
+                (grid_to_sq(3, 0), PieceKind::Queen),
+                (grid_to_sq(4, 0), PieceKind::King),
+            ]),
+            HashMap::from([(grid_to_sq(4, 7), PieceKind::King)]),
+        );
+        assert!(play(&mut board, (3, 0), (7, 4)).is_check());
+        assert!(!play(&mut board, (4, 7), (3, 7)).is_terminal());
+        assert_eq!(board.count_checks(Team::White), 1);
+        let data = play(&mut board, (7, 4), (7, 3));
+        assert_eq!(data.get_evaluation(), score::Score::Lost(3));
+        assert_eq!(board.count_checks(Team::White), 2);
+        assert_eq!(board.count_checks(Team::Black), 0);
+
+        //white's horde has no king, 8 moves to start with, and loses when its last pawn is taken
+        let mut board = ClassicalGameType::Horde.create_game();
+        assert_eq!(board_data::BoardData::new(&mut board).get_moves().len(), 8);
+        let mut board = Board::new(
+            Team::Black,
+            create_signature().with_rules(rules::Horde {}),
+            HashMap::from([(grid_to_sq(3, 6), PieceKind::Pawn(EnCroissantable::No))]),
+            HashMap::from([(grid_to_sq(4, 7), PieceKind::King)]),
+        );
+        let data = play(&mut board, (4, 7), (3, 6));
+        assert_eq!(data.get_evaluation(), score::Score::Lost(1));
+    }
 }
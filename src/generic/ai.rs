@@ -1,8 +1,11 @@
 use std::ops::Neg;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 
 use super::board_data::*;
+use super::evaluation::EvalParams;
+use super::ordering::{self, MoveOrdering};
 use super::score::*;
 use super::*;
 
@@ -11,6 +14,148 @@ use super::*;
 //     score: Score,
 // }
 
+//how many plies less deeply to search after a null move
+const NULL_MOVE_REDUCTION: usize = 2;
+//how many moves are searched in full before late move reductions start
+const FULL_DEPTH_MOVES: usize = 3;
+//aspiration windows which would be wider than this are dropped for a full search
+const MAX_ASPIRATION_WINDOW: i64 = 50000;
+//how many plies must be left to search below a node for its moves to be shared between threads
+const PARALLEL_DEPTH: usize = 3;
+//the search recurses deeply and threads may pick up more of it while waiting for their brothers
+const SEARCH_STACK_SIZE: usize = 64 << 20;
+
+//how many nodes a thread counts by itself before adding them to the total
+const NODE_COUNT_BATCH: usize = 1024;
+
+//a count of its own for each thread, on a cache line of its own so that threads counting at once do not slow each other
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct ThreadNodeCount(AtomicUsize);
+
+//how many nodes every thread searching the tree has visited, and how much memory the nodes kept in the tree take
+//threads count in their own slots and add to the shared total only every so often, which is what limits the search
+#[derive(Debug)]
+pub struct NodeCount {
+    threads: Vec<ThreadNodeCount>,
+    total: AtomicUsize,
+    tree_bytes: AtomicUsize, //as last measured, and added to by the nodes kept since
+    tree_budget: usize,
+    search: AtomicUsize, //how many searches of the tree there have been
+}
+
+impl NodeCount {
+    pub fn new(threads: usize, tree_budget: usize) -> Self {
+        Self {
+            threads: (0..threads.max(1))
+                .map(|_| ThreadNodeCount::default())
+                .collect(),
+            total: AtomicUsize::new(0),
+            tree_bytes: AtomicUsize::new(0),
+            tree_budget,
+            search: AtomicUsize::new(0),
+        }
+    }
+
+    //make room for a new node in the tree, failing once the tree is as big as it may get
+    fn keep(&self, bytes: usize) -> bool {
+        if self.tree_bytes.load(Ordering::Relaxed) >= self.tree_budget {
+            return false;
+        }
+        self.tree_bytes.fetch_add(bytes, Ordering::Relaxed);
+        true
+    }
+
+    //how much memory the nodes kept in the tree take
+    pub fn get_tree_bytes(&self) -> usize {
+        self.tree_bytes.load(Ordering::Relaxed)
+    }
+
+    fn set_tree_bytes(&self, bytes: usize) {
+        self.tree_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    fn get_search(&self) -> usize {
+        self.search.load(Ordering::Relaxed)
+    }
+
+    fn next_search(&self) -> usize {
+        self.search.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    //count a node, failing once the total has gone past the limit
+    pub(crate) fn visit(&self, limit: usize) -> Result<(), ()> {
+        let slot = rayon::current_thread_index().unwrap_or(0) % self.threads.len();
+        let count = self.threads[slot].0.fetch_add(1, Ordering::Relaxed) + 1;
+        if count.is_multiple_of(NODE_COUNT_BATCH) {
+            self.total.fetch_add(NODE_COUNT_BATCH, Ordering::Relaxed);
+        }
+        match self.total.load(Ordering::Relaxed) > limit {
+            true => Err(()),
+            false => Ok(()),
+        }
+    }
+
+    //the exact number of nodes visited so far
+    pub fn get(&self) -> usize {
+        self.threads
+            .iter()
+            .map(|count| count.0.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+//which of the search's pruning and extension techniques to use, all of them by default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchConfig {
+    //search all but the first move with a null window, and again in full if one does better than the first
+    pub pvs: bool,
+    //let the side to move pass and search less deeply, since if that is still too good the position can be pruned
+    pub null_move: bool,
+    //search quiet moves late in the move ordering less deeply unless they turn out to be good
+    pub late_move_reductions: bool,
+    //search moves which give check one ply deeper
+    pub check_extensions: bool,
+    //how many plies past the main search quiescence may go on taking
+    pub quiescence_depth: usize,
+    //how far either side of the last iteration's score to look for the next one, widening when it falls outside
+    pub aspiration_window: Option<i64>,
+    //how many threads search the tree together
+    pub threads: usize,
+    //how many bytes the nodes kept in the tree between searches may take, after which new nodes are thrown away once searched
+    pub tree_memory: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            pvs: true,
+            null_move: true,
+            late_move_reductions: true,
+            check_extensions: true,
+            quiescence_depth: 8,
+            aspiration_window: Some(500),
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            tree_memory: 1 << 30,
+        }
+    }
+}
+
+impl SearchConfig {
+    //plain alpha-beta, for comparison
+    pub fn plain() -> Self {
+        Self {
+            pvs: false,
+            null_move: false,
+            late_move_reductions: false,
+            check_extensions: false,
+            quiescence_depth: 8,
+            aspiration_window: None,
+            ..Self::default()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct AlphaBetaMaximizingResult {
     score: Score,
@@ -52,6 +197,7 @@ pub struct MoveData {
     mv: Move,
     board: Option<BoardData>,
     approx_score: Option<AlphaBetaMinimizingResult>,
+    see: std::sync::OnceLock<Option<i64>>, //the static exchange evaluation of a capture, once it is first needed
 }
 
 impl MoveData {
@@ -60,18 +206,19 @@ impl MoveData {
             mv,
             board: None,
             approx_score: None,
+            see: std::sync::OnceLock::new(),
         }
     }
 
-    pub fn get_move(&self) -> &Move {
-        &self.mv
+    //the board is the position the move is made from
+    pub fn get_see(&self, board: &Board, params: &EvalParams) -> Option<i64> {
+        *self
+            .see
+            .get_or_init(|| board_data::see(board, params, &self.mv))
     }
 
-    fn get_board(&mut self, board: &mut Board) -> &mut BoardData {
-        if self.board.is_none() {
-            self.board = Some(BoardData::new(board));
-        }
-        self.board.as_mut().unwrap()
+    pub fn get_move(&self) -> &Move {
+        &self.mv
     }
 
     fn get_approx_score(&self) -> Score {
@@ -81,11 +228,15 @@ impl MoveData {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn alpha_beta(
         &mut self,
-        stop_check: &impl Fn() -> bool,
-        node_count: Arc<Mutex<usize>>,
+        stop_check: &(dyn Fn() -> bool + Sync),
+        node_count: &NodeCount,
         board: &mut Board,
+        params: &EvalParams,
+        ordering: &MoveOrdering,
+        config: &SearchConfig,
         depth: usize,
         max_depth: usize,
         max_quiesce_depth: usize,
@@ -93,20 +244,39 @@ impl MoveData {
         alpha: LowerBoundRef,
         beta: UpperBoundRef,
     ) -> Result<AlphaBetaMinimizingResult, ()> {
-        {
-            let mut node_count_value = node_count.lock().unwrap();
-            *node_count_value += 1;
-            if *node_count_value > max_node_count {
-                return Err(());
+        node_count.visit(max_node_count)?;
+
+        board.make_move(self.mv);
+        //once the tree is as big as it may get, new nodes are searched without being kept
+        let mut spare = None;
+        if self.board.is_none() {
+            let board_data = BoardData::with_params(board, params);
+            match node_count.keep(board_data.bytes()) {
+                true => self.board = Some(board_data),
+                false => spare = Some(board_data),
             }
         }
-
-        board.make_move(self.mv.clone());
-        let board_data = self.get_board(board);
+        let board_data = match &mut spare {
+            Some(board_data) => board_data,
+            None => self.board.as_mut().unwrap(),
+        };
+        board_data.set_visited(node_count.get_search());
+        //moves which give check are searched a ply deeper, though never into quiescence
+        let max_depth = match config.check_extensions
+            && board_data.is_check()
+            && depth <= max_depth
+            && max_depth + 1 < max_quiesce_depth
+        {
+            true => max_depth + 1,
+            false => max_depth,
+        };
         if let Ok(abres) = board_data.alpha_beta(
             stop_check,
             node_count,
             board,
+            params,
+            ordering,
+            config,
             depth,
             max_depth,
             max_quiesce_depth,
@@ -122,16 +292,92 @@ impl MoveData {
             Err(())
         }
     }
+
+    //search the nth move in the order they are tried at a node
+    //after the first move, check that a move beats the best so far with a null window before searching it properly
+    //and search late quiet moves a ply less deeply unless they turn out to beat it
+    #[allow(clippy::too_many_arguments)]
+    fn search_sibling(
+        &mut self,
+        stop_check: &(dyn Fn() -> bool + Sync),
+        node_count: &NodeCount,
+        board: &mut Board,
+        params: &EvalParams,
+        ordering: &MoveOrdering,
+        config: &SearchConfig,
+        depth: usize,
+        max_depth: usize,
+        max_quiesce_depth: usize,
+        max_node_count: usize,
+        alpha: &LowerBoundRef,
+        beta: &UpperBoundRef,
+        n: usize,
+        kind: u8,
+        in_check: bool,
+    ) -> Result<Score, ()> {
+        let mut search = |reduction: usize, beta: UpperBoundRef| {
+            self.alpha_beta(
+                stop_check,
+                node_count,
+                board,
+                params,
+                ordering,
+                config,
+                depth + 1,
+                max_depth - reduction,
+                max_quiesce_depth,
+                max_node_count,
+                alpha.clone(),
+                beta,
+            )
+            .map(|result| result.score)
+        };
+
+        let alpha_score = match alpha.get_bound() {
+            LowerBound::Finite(score) if n > 0 => Some(score),
+            _ => None,
+        };
+        let reduce = config.late_move_reductions
+            && n >= FULL_DEPTH_MOVES
+            && kind == ordering::QUIET
+            && !in_check
+            && max_depth - depth > 2;
+        let mut score = None;
+        if let Some(alpha_score) = alpha_score {
+            if reduce {
+                let reduced = search(1, UpperBoundRef::new_finite(alpha_score))?;
+                if reduced <= alpha_score {
+                    score = Some(reduced);
+                }
+            }
+            if score.is_none() && config.pvs {
+                let null_window = search(0, UpperBoundRef::new_finite(alpha_score))?;
+                if null_window <= alpha_score {
+                    score = Some(null_window);
+                }
+            }
+        }
+        let score = match score {
+            Some(score) => score,
+            None => search(0, beta.branch())?,
+        };
+        alpha.refine_bound(score);
+        Ok(score)
+    }
 }
 
 //alpha is the score we already know we can achive
 //beta is the score the opponent knows they can achive
 impl BoardData {
+    #[allow(clippy::too_many_arguments)]
     fn alpha_beta(
         &mut self,
-        stop_check: &impl Fn() -> bool,
-        node_count: Arc<Mutex<usize>>,
+        stop_check: &(dyn Fn() -> bool + Sync),
+        node_count: &NodeCount,
         board: &mut Board,
+        params: &EvalParams,
+        ordering: &MoveOrdering,
+        config: &SearchConfig,
         depth: usize,
         max_depth: usize,
         max_quiesce_depth: usize,
@@ -148,6 +394,9 @@ impl BoardData {
                 stop_check,
                 node_count,
                 board,
+                params,
+                ordering,
+                config,
                 depth,
                 max_depth,
                 max_quiesce_depth,
@@ -168,45 +417,190 @@ impl BoardData {
                 })
             }
             Score::Heuristic(_stand_pat) => {
-                macro_rules! get_score_and_beta_prune {
-                    ($move_data:expr) => {{
-                        let score = $move_data
-                            .alpha_beta(
-                                stop_check,
-                                node_count.clone(),
-                                board,
-                                depth + 1,
-                                max_depth,
-                                max_quiesce_depth,
-                                max_node_count,
-                                alpha.clone(),
-                                beta.branch(),
-                            )?
-                            .score;
-                        alpha.refine_bound(score);
-                        if !beta.get_bound().is_improvement(&score) {
-                            //beta prune
+                //if passing would still be too good for the opponent to allow then so would the best move
+                //not when in check, where passing is illegal, nor when only pawns and the king are left or in antichess
+                //since then passing may well be better than any move
+                let turn = board.get_turn();
+                if config.null_move
+                    && max_depth - depth > NULL_MOVE_REDUCTION
+                    && !self.is_check()
+                    && !board.last_was_null()
+                    && !board.signature.is_antichess()
+                    && board.get_pieces().iter().any(|(_sq, piece)| {
+                        piece.team == turn
+                            && !matches!(piece.kind, PieceKind::Pawn(..) | PieceKind::King)
+                    })
+                {
+                    if let UpperBound::Finite(beta_score @ Score::Heuristic(_)) = beta.get_bound() {
+                        board.make_null_move();
+                        let mut spare = None;
+                        if self.get_null_move().is_none() {
+                            let null_data = BoardData::with_params(board, params);
+                            match node_count
+                                .keep(std::mem::size_of::<BoardData>() + null_data.bytes())
+                            {
+                                true => *self.get_null_move_mut() = Some(Box::new(null_data)),
+                                false => spare = Some(null_data),
+                            }
+                        }
+                        let null_data = match &mut spare {
+                            Some(null_data) => null_data,
+                            None => self.get_null_move_mut().as_mut().unwrap(),
+                        };
+                        null_data.set_visited(node_count.get_search());
+                        let result = null_data.alpha_beta(
+                            stop_check,
+                            node_count,
+                            board,
+                            params,
+                            ordering,
+                            config,
+                            depth + 1,
+                            max_depth - NULL_MOVE_REDUCTION,
+                            max_quiesce_depth,
+                            max_node_count,
+                            LowerBoundRef::new_finite(-beta_score),
+                            UpperBoundRef::new_finite(-beta_score),
+                        );
+                        board.unmake_null_move();
+                        let score = -result?.score;
+                        if score > beta_score {
                             return Ok(AlphaBetaMaximizingResult {
-                                score: score,
+                                score,
                                 depth: max_depth as isize - depth as isize,
                                 exact: false,
                             });
                         }
-                        score
+                    }
+                }
+
+                //try the moves most likely to be good first, breaking ties by how they did last time
+                let best_move = self.get_best_move();
+                let mut order = self
+                    .get_moves_data()
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, move_data)| {
+                        let is_best = matches!(best_move, Some(best) if best.idx == idx);
+                        (
+                            ordering.key(board, params, depth, move_data, is_best),
+                            move_data.get_approx_score(),
+                            idx,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                order.sort_by(|a, b| b.cmp(a));
+                debug_assert!(!order.is_empty());
+
+                let order = order
+                    .into_iter()
+                    .map(|((kind, _value), _approx_score, idx)| (kind, idx))
+                    .collect::<Vec<_>>();
+                let in_check = self.is_check();
+                macro_rules! search {
+                    ($n:expr, $kind:expr, $move_data:expr, $stop_check:expr, $board:expr) => {
+                        $move_data.search_sibling(
+                            $stop_check,
+                            node_count,
+                            $board,
+                            params,
+                            ordering,
+                            config,
+                            depth,
+                            max_depth,
+                            max_quiesce_depth,
+                            max_node_count,
+                            &alpha,
+                            &beta,
+                            $n,
+                            $kind,
+                            in_check,
+                        )
+                    };
+                }
+                macro_rules! beta_prune {
+                    ($idx:expr, $score:expr) => {{
+                        ordering.cutoff(
+                            depth,
+                            self.get_moves_data()[$idx].get_move(),
+                            max_depth - depth,
+                        );
+                        self.set_best_move(Some(MoveIdx { idx: $idx }));
+                        return Ok(AlphaBetaMaximizingResult {
+                            score: $score,
+                            depth: max_depth as isize - depth as isize,
+                            exact: false,
+                        });
                     }};
                 }
 
-                let mut moves = self.get_moves_data_mut().iter_mut().collect::<Vec<_>>();
-                moves.sort_by_key(|mv| mv.get_approx_score());
-                debug_assert!(!moves.is_empty());
-                let first_move_data = moves.pop().unwrap();
-                let mut bestscore = get_score_and_beta_prune!(first_move_data);
-                for move_data in moves.into_iter().rev() {
-                    let score = get_score_and_beta_prune!(move_data);
-                    if score > bestscore {
-                        bestscore = score;
+                //young brothers wait: once the first move has been searched, and so given a good bound to the rest,
+                //the others are searched at once on as many threads as there are, each with its own copy of the board
+                //when one of them prunes the node, the others are told to stop
+                let parallel = config.threads > 1 && max_depth - depth >= PARALLEL_DEPTH;
+                let mut bestscore = None;
+                let mut results = vec![];
+                for (n, (kind, idx)) in order.iter().cloned().enumerate() {
+                    if parallel && n == 1 {
+                        break;
+                    }
+                    let move_data = &mut self.get_moves_data_mut()[idx];
+                    let score = search!(n, kind, move_data, stop_check, board)?;
+                    results.push((idx, score));
+                    if !beta.get_bound().is_improvement(&score) {
+                        beta_prune!(idx, score);
                     }
                 }
+                if parallel && order.len() > 1 {
+                    use rayon::prelude::*;
+
+                    let abort = AtomicBool::new(false);
+                    let child_stop_check = || abort.load(Ordering::Relaxed) || stop_check();
+                    let mut moves_data = self
+                        .get_moves_data_mut()
+                        .iter_mut()
+                        .map(Some)
+                        .collect::<Vec<_>>();
+                    let younger = order[1..]
+                        .iter()
+                        .enumerate()
+                        .map(|(n, (kind, idx))| {
+                            (n + 1, *kind, *idx, moves_data[*idx].take().unwrap())
+                        })
+                        .collect::<Vec<_>>();
+                    let younger_results = younger
+                        .into_par_iter()
+                        .map(|(n, kind, idx, move_data)| {
+                            let mut board = board.clone();
+                            let score = search!(n, kind, move_data, &child_stop_check, &mut board);
+                            if let Ok(score) = score {
+                                if !beta.get_bound().is_improvement(&score) {
+                                    abort.store(true, Ordering::Relaxed);
+                                }
+                            }
+                            (idx, score)
+                        })
+                        .collect::<Vec<_>>();
+                    //the cutoff is what matters if there was one, since the moves which were stopped for it do not
+                    if let Some((idx, score)) = younger_results
+                        .iter()
+                        .filter_map(|(idx, score)| score.ok().map(|score| (*idx, score)))
+                        .filter(|(_idx, score)| !beta.get_bound().is_improvement(score))
+                        .max_by_key(|(_idx, score)| *score)
+                    {
+                        beta_prune!(idx, score);
+                    }
+                    for (idx, score) in younger_results {
+                        results.push((idx, score?));
+                    }
+                }
+                for (idx, score) in results {
+                    if bestscore.is_none_or(|bestscore| score > bestscore) {
+                        bestscore = Some(score);
+                        self.set_best_move(Some(MoveIdx { idx }));
+                    }
+                }
+                let bestscore = bestscore.unwrap();
                 Ok(AlphaBetaMaximizingResult {
                     score: bestscore,
                     depth: max_depth as isize - depth as isize,
@@ -216,11 +610,15 @@ impl BoardData {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn quiescence(
         &mut self,
-        stop_check: &impl Fn() -> bool,
-        node_count: Arc<Mutex<usize>>,
+        stop_check: &(dyn Fn() -> bool + Sync),
+        node_count: &NodeCount,
         board: &mut Board,
+        params: &EvalParams,
+        ordering: &MoveOrdering,
+        config: &SearchConfig,
         depth: usize,
         max_depth: usize,
         max_quiesce_depth: usize,
@@ -232,13 +630,7 @@ impl BoardData {
             return Err(());
         }
 
-        {
-            let mut node_count_value = node_count.lock().unwrap();
-            *node_count_value += 1;
-            if *node_count_value > max_node_count {
-                return Err(());
-            }
-        }
+        node_count.visit(max_node_count)?;
 
         let eval = self.get_evaluation();
         if depth >= max_quiesce_depth {
@@ -263,8 +655,11 @@ impl BoardData {
                         let score = $move_data
                             .alpha_beta(
                                 stop_check,
-                                node_count.clone(),
+                                node_count,
                                 board,
+                                params,
+                                ordering,
+                                config,
                                 depth + 1,
                                 max_depth,
                                 max_quiesce_depth,
@@ -277,7 +672,7 @@ impl BoardData {
                         if !beta.get_bound().is_improvement(&score) {
                             //beta prune
                             return Ok(AlphaBetaMaximizingResult {
-                                score: score,
+                                score,
                                 depth: max_depth as isize - depth as isize,
                                 exact: false,
                             });
@@ -286,8 +681,22 @@ impl BoardData {
                     }};
                 }
 
-                let mut bestscore = Score::Heuristic(stand_pat);
-                if !beta.get_bound().is_improvement(&bestscore) {
+                //in antichess a capture must be made when there is one, so the side to move cannot stand pat
+                let must_capture = board.signature.is_antichess()
+                    && self.get_moves_data().iter().any(|move_data| {
+                        matches!(
+                            move_data.mv,
+                            Move::Standard {
+                                victim: Some(_),
+                                ..
+                            }
+                        )
+                    });
+                let mut bestscore = match must_capture {
+                    true => Score::Lost(0),
+                    false => Score::Heuristic(stand_pat),
+                };
+                if !must_capture && !beta.get_bound().is_improvement(&bestscore) {
                     //beta prune the stand_pat
                     return Ok(AlphaBetaMaximizingResult {
                         score: bestscore,
@@ -304,8 +713,8 @@ impl BoardData {
                             victim: victim_opt, ..
                         } => match victim_opt {
                             Some(_) => {
-                                //delta prune
-                                true
+                                //captures which lose material once the exchange plays out are not worth looking at
+                                move_data.get_see(board, params).is_none_or(|see| see >= 0)
                                 // alpha.get_bound().is_improvement(&Score::Heuristic(
                                 //     stand_pat + victim.kind.worth().unwrap() * 1000 + 200, //delta prune
                                 // ))
@@ -314,9 +723,11 @@ impl BoardData {
                         },
                         Move::Castle { .. } => false,
                         Move::EnCroissant { .. } => false,
+                        Move::Drop { .. } => false,
                     })
                     .collect::<Vec<_>>();
-                moves.sort_by_key(|mv| mv.get_approx_score());
+                //the best exchanges first, then those already searched most promisingly
+                moves.sort_by_key(|mv| (mv.get_see(board, params), mv.get_approx_score()));
                 for move_data in moves.into_iter().rev() {
                     let score = get_score_and_beta_prune!(move_data);
                     if score > bestscore {
@@ -336,26 +747,93 @@ impl BoardData {
 #[derive(Debug)]
 pub struct BoardTree {
     board: Board,
+    params: EvalParams,
+    config: SearchConfig,
+    ordering: MoveOrdering,
     root: Box<BoardData>,
+    root_order: Vec<MoveIdx>, //the root moves from best to worst by the last search, to search them in that order next time
+    pool: rayon::ThreadPool,
+    nodes: Arc<NodeCount>, //every node searched since the tree was made
 }
 
 impl BoardTree {
-    pub fn new(mut board: Board) -> Self {
-        let root = BoardData::new(&mut board);
-        let tree = BoardTree {
+    pub fn new(mut board: Board, params: EvalParams, config: SearchConfig) -> Self {
+        let root = BoardData::with_params(&mut board, &params);
+        let mut tree = BoardTree {
+            ordering: MoveOrdering::new(&board.signature),
             board,
+            params,
+            config: config.clone(),
             root: root.into(),
+            root_order: vec![],
+            pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(config.threads.max(1))
+                .stack_size(SEARCH_STACK_SIZE)
+                .build()
+                .unwrap(),
+            nodes: Arc::new(NodeCount::new(config.threads, config.tree_memory)),
         };
+        tree.trim();
         tree
     }
 
+    //keep the tree within its memory budget, leaving half of it for the next search to fill
+    //by forgetting first the nodes the last search did not reach and then the deepest of the rest
+    fn trim(&mut self) {
+        let target = self.config.tree_memory / 2;
+        if self.root.tree_bytes() > target {
+            self.root.forget_cold(self.nodes.get_search());
+            let mut bytes = vec![];
+            self.root.bytes_by_depth(0, &mut bytes);
+            if bytes.iter().sum::<usize>() > target {
+                let mut total = 0;
+                let depths = bytes
+                    .iter()
+                    .take_while(|bytes| {
+                        total += *bytes;
+                        total <= target
+                    })
+                    .count();
+                self.root.forget_below(depths.max(1));
+            }
+        }
+        self.nodes.set_tree_bytes(self.root.tree_bytes());
+    }
+
+    //how much memory the nodes kept in the tree take
+    pub fn tree_bytes(&self) -> usize {
+        self.nodes.get_tree_bytes()
+    }
+
+    //the move the syzygy tables choose from the root, if they have it, which wins where the search cannot see how
+    fn syzygy_move(&mut self) -> Option<(MoveIdx, Score)> {
+        let syzygy = self.board.signature.get_syzygy()?.clone();
+        let moves = self
+            .root
+            .get_moves()
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        let (idx, score) = syzygy.choose_move(&mut self.board, &moves)?;
+        Some((MoveIdx { idx }, score))
+    }
+
     fn best_move_at_depth(
         &mut self,
         max_depth: usize,
         max_quiesce_depth: usize,
         max_node_count: usize,
-        stop_flag: Arc<Mutex<bool>>,
+        alpha: LowerBoundRef,
+        beta: UpperBoundRef,
+        stop_flag: Arc<AtomicBool>,
     ) -> Result<Option<(MoveIdx, Score, usize)>, ()> {
+        self.trim();
+        let search = self.nodes.next_search();
+        self.root.set_visited(search);
+        self.ordering.new_search(max_depth);
+        let params = &self.params;
+        let config = &self.config;
+        let ordering = &self.ordering;
         let mut moves = self
             .root
             .get_moves_data_mut()
@@ -363,56 +841,77 @@ impl BoardTree {
             .enumerate()
             .map(|(idx, mv)| (MoveIdx { idx }, mv, self.board.clone()))
             .collect::<Vec<_>>();
-        moves.sort_by_key(|(_mv_idx, mv, _board)| mv.get_approx_score());
+        //best first, in the order the last search left them or else by their approximate scores
+        match self.root_order.len() == moves.len() {
+            true => {
+                let rank = self
+                    .root_order
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, move_idx)| (move_idx.idx, rank))
+                    .collect::<HashMap<_, _>>();
+                moves.sort_by_key(|(move_idx, _mv, _board)| rank[&move_idx.idx]);
+            }
+            false => moves
+                .sort_by_key(|(_move_idx, mv, _board)| std::cmp::Reverse(mv.get_approx_score())),
+        }
         let n = moves.len();
         if n == 0 {
             Ok(None)
         } else {
-            let alpha = LowerBoundRef::new_inf();
-            let beta = UpperBoundRef::new_inf();
-            let node_count = Arc::new(Mutex::new(0));
-
-            use rayon::prelude::*;
-
-            let results = moves
-                .into_iter()
-                .rev()
-                .enumerate()
-                .par_bridge() //so that moves start processing in order - to help with alpha-beta pruning
-                .into_par_iter()
-                .map(|(idx, (move_idx, move_data, mut board))| {
-                    let stop_check = || *stop_flag.lock().unwrap();
-                    match move_data.alpha_beta(
+            //the limit is on the nodes of this search alone
+            let node_count = &*self.nodes;
+            let nodes_before = node_count.get();
+            let max_node_count = nodes_before.saturating_add(max_node_count);
+            let stop_check = || stop_flag.load(Ordering::Relaxed);
+            let search = |(move_idx, move_data, mut board): (MoveIdx, &mut MoveData, Board)| {
+                let score = move_data
+                    .alpha_beta(
                         &stop_check,
-                        node_count.clone(),
+                        node_count,
                         &mut board,
+                        params,
+                        ordering,
+                        config,
                         0,
                         max_depth,
                         max_quiesce_depth,
                         max_node_count,
                         alpha.clone(),
                         beta.branch(),
-                    ) {
-                        Ok(score) => {
-                            let score = score.score;
-                            println!("  {:?}/{:?}", idx + 1, n);
-                            alpha.refine_bound(score);
-                            Ok((move_idx, score))
-                        }
-                        Err(()) => Err(()),
-                    }
-                })
-                .collect::<Vec<_>>();
+                    )?
+                    .score;
+                alpha.refine_bound(score);
+                Ok((move_idx, score))
+            };
+
+            //the first move alone, to find a bound for the others, which are then searched together
+            use rayon::prelude::*;
+
+            let results = self.pool.install(|| {
+                let mut moves = moves.into_iter();
+                let first: Result<_, ()> = search(moves.next().unwrap());
+                let rest = moves
+                    .collect::<Vec<_>>()
+                    .into_par_iter()
+                    .map(search)
+                    .collect::<Vec<_>>();
+                std::iter::once(first).chain(rest).collect::<Vec<_>>()
+            });
 
             if results.iter().any(|result| result.is_err()) {
                 Err(())
             } else {
-                let scores = results
+                let mut scores = results
                     .into_iter()
                     .map(|result| result.unwrap())
                     .collect::<Vec<_>>();
-                if let Some(best) = scores.into_iter().max_by_key(|(_move_idx, score)| *score) {
-                    Ok(Some((best.0, best.1, *node_count.lock().unwrap())))
+                //stable, so that moves which tie stay in the order they were searched
+                scores.sort_by_key(|(_move_idx, score)| std::cmp::Reverse(*score));
+                self.root_order = scores.iter().map(|(move_idx, _score)| *move_idx).collect();
+                if let Some(best) = scores.first() {
+                    self.root.set_best_move(Some(best.0));
+                    Ok(Some((best.0, best.1, node_count.get() - nodes_before)))
                 } else {
                     Ok(None)
                 }
@@ -422,20 +921,26 @@ impl BoardTree {
 
     pub fn make_move(&mut self, m: MoveIdx) -> Box<dyn BigData> {
         let md = self.root.get_move_mut(m);
-        self.board.make_move(md.mv.clone());
+        self.board.make_move(md.mv);
 
-        let mut alt_root = Box::new(match &md.board {
-            Some(board) => board.clone(),
-            None => md.get_board(&mut self.board).clone(),
+        //the subtree is taken rather than copied, and the rest of the old tree is handed back to be dropped
+        let mut alt_root = Box::new(match md.board.take() {
+            Some(board) => board,
+            None => BoardData::with_params(&mut self.board, &self.params),
         });
         std::mem::swap(&mut self.root, &mut alt_root);
+        self.root_order = vec![];
+        self.trim();
         alt_root
     }
 
+    #[allow(clippy::result_unit_err)]
     pub fn unmake_move(&mut self) -> Result<(), ()> {
         match self.board.unmake_move() {
             Ok(()) => {
-                self.root = BoardData::new(&mut self.board).into();
+                self.root = BoardData::with_params(&mut self.board, &self.params).into();
+                self.root_order = vec![];
+                self.trim();
                 Ok(())
             }
             Err(()) => Err(()),
@@ -445,36 +950,158 @@ impl BoardTree {
 
 pub trait BigData {}
 
-impl BoardData {}
+impl BoardData {
+    //the memory taken by the node's moves and every node kept below it
+    fn tree_bytes(&self) -> usize {
+        self.bytes()
+            + self
+                .get_moves_data()
+                .iter()
+                .filter_map(|move_data| move_data.board.as_ref())
+                .map(|board_data| board_data.tree_bytes())
+                .sum::<usize>()
+            + self.get_null_move().map_or(0, |null_data| {
+                std::mem::size_of::<BoardData>() + null_data.tree_bytes()
+            })
+    }
+
+    //add up the memory kept at each depth, the node's own moves being at the given one
+    fn bytes_by_depth(&self, depth: usize, bytes: &mut Vec<usize>) {
+        if bytes.len() <= depth {
+            bytes.push(0);
+        }
+        bytes[depth] += self.bytes();
+        for move_data in self.get_moves_data() {
+            if let Some(board_data) = &move_data.board {
+                board_data.bytes_by_depth(depth + 1, bytes);
+            }
+        }
+        if let Some(null_data) = self.get_null_move() {
+            null_data.bytes_by_depth(depth + 1, bytes);
+            bytes[depth + 1] += std::mem::size_of::<BoardData>();
+        }
+    }
+
+    //drop the nodes below this one which the given search did not reach
+    //what the search found out about their moves is kept in the moves themselves
+    fn forget_cold(&mut self, search: usize) {
+        for move_data in self.get_moves_data_mut() {
+            if let Some(board_data) = &mut move_data.board {
+                match board_data.get_visited() < search {
+                    true => move_data.board = None,
+                    false => board_data.forget_cold(search),
+                }
+            }
+        }
+        let null_move = self.get_null_move_mut();
+        if let Some(null_data) = null_move {
+            match null_data.get_visited() < search {
+                true => *null_move = None,
+                false => null_data.forget_cold(search),
+            }
+        }
+    }
+
+    //drop the nodes more than the given number of plies below this one
+    fn forget_below(&mut self, depth: usize) {
+        for move_data in self.get_moves_data_mut() {
+            if let Some(board_data) = &mut move_data.board {
+                match depth {
+                    0 | 1 => move_data.board = None,
+                    _ => board_data.forget_below(depth - 1),
+                }
+            }
+        }
+        let null_move = self.get_null_move_mut();
+        if let Some(null_data) = null_move {
+            match depth {
+                0 | 1 => *null_move = None,
+                _ => null_data.forget_below(depth - 1),
+            }
+        }
+    }
+}
 
 impl BigData for BoardData {}
 
 #[derive(Debug)]
 pub struct AiOn {
-    stop_flag: Arc<Mutex<bool>>,
+    stop_flag: Arc<AtomicBool>,
     best_move: Arc<Mutex<Option<MoveIdx>>>,
+    depth: Arc<Mutex<usize>>, //the deepest search completed so far
+    nodes: Arc<NodeCount>,
     handler: JoinHandle<(BoardTree, Option<MoveIdx>)>,
 }
 
 impl AiOn {
     fn think(
-        stop_flag: Arc<Mutex<bool>>,
+        stop_flag: Arc<AtomicBool>,
         best_move: Arc<Mutex<Option<MoveIdx>>>,
+        depth_done: Arc<Mutex<usize>>,
         mut tree: BoardTree,
     ) -> (BoardTree, Option<MoveIdx>) {
+        if let Some((syzygy_move, _)) = tree.syzygy_move() {
+            *best_move.lock().unwrap() = Some(syzygy_move);
+            return (tree, Some(syzygy_move));
+        }
         let mut depth = 1;
+        let mut last_score = None;
         println!("Search started");
         loop {
-            match tree.best_move_at_depth(depth - 1, depth * 3 - 1, 1000000, stop_flag.clone()) {
+            let max_depth = depth - 1;
+            let max_quiesce_depth = max_depth + tree.config.quiescence_depth;
+            //search a window around the last score, which is cheaper if the score lands in it
+            //and widen the window until it does
+            let mut width = tree.config.aspiration_window;
+            let result = loop {
+                let window = match (last_score, width) {
+                    (Some(Score::Heuristic(score)), Some(width)) => Some((
+                        Score::Heuristic(score - width),
+                        Score::Heuristic(score + width),
+                    )),
+                    _ => None,
+                };
+                let (alpha, beta) = match window {
+                    Some((low, high)) => (
+                        LowerBoundRef::new_finite(low),
+                        UpperBoundRef::new_finite(high),
+                    ),
+                    None => (LowerBoundRef::new_inf(), UpperBoundRef::new_inf()),
+                };
+                let result = tree.best_move_at_depth(
+                    max_depth,
+                    max_quiesce_depth,
+                    1000000,
+                    alpha,
+                    beta,
+                    stop_flag.clone(),
+                );
+                match (&result, window) {
+                    (Ok(Some((_mv, score, _node_count))), Some((low, high)))
+                        if *score <= low || *score > high =>
+                    {
+                        width = width
+                            .map(|width| width * 4)
+                            .filter(|width| *width <= MAX_ASPIRATION_WINDOW);
+                    }
+                    _ => break result,
+                }
+            };
+            match result {
                 Ok(None) => {
                     println!("No moves");
                     break;
                 }
                 Ok(Some((best_move_answer, score, node_count))) => {
+                    last_score = Some(score);
                     *best_move.lock().unwrap() = Some(best_move_answer);
+                    *depth_done.lock().unwrap() = depth;
                     println!(
-                        "Done at depth = {:?} with score = {:?} and {:?} boards checked",
-                        depth, score, node_count
+                        "Done at depth = {:?} with score = {:?} and {:?} boards checked, keeping {:?} KiB of tree",
+                        depth,
+                        score,
+                        node_count,
+                        tree.tree_bytes() >> 10
                     );
                 }
                 Err(()) => {
@@ -491,8 +1118,27 @@ impl AiOn {
         *self.best_move.lock().unwrap()
     }
 
+    pub fn current_depth(&self) -> usize {
+        *self.depth.lock().unwrap()
+    }
+
+    //how many nodes the tree has searched, on every thread and counting searches which were stopped
+    pub fn nodes_searched(&self) -> usize {
+        self.nodes.get()
+    }
+
+    //how much memory the nodes kept in the tree take, which the search keeps within its budget
+    pub fn tree_bytes(&self) -> usize {
+        self.nodes.get_tree_bytes()
+    }
+
+    //whether the search has ended by itself, having run out of moves or nodes
+    pub fn is_finished(&self) -> bool {
+        self.handler.is_finished()
+    }
+
     pub fn finish(self) -> (AiOff, Option<MoveIdx>) {
-        *self.stop_flag.lock().unwrap() = true;
+        self.stop_flag.store(true, Ordering::Relaxed);
         let (tree, best_move) = self.handler.join().unwrap();
         (AiOff { tree }, best_move)
     }
@@ -505,8 +1151,16 @@ pub struct AiOff {
 
 impl AiOff {
     pub fn new(board: Board) -> Self {
+        Self::with_params(board, EvalParams::default())
+    }
+
+    pub fn with_params(board: Board, params: EvalParams) -> Self {
+        Self::with_config(board, params, SearchConfig::default())
+    }
+
+    pub fn with_config(board: Board, params: EvalParams, config: SearchConfig) -> Self {
         Self {
-            tree: BoardTree::new(board),
+            tree: BoardTree::new(board, params, config),
         }
     }
 
@@ -518,24 +1172,283 @@ impl AiOff {
         self.tree.root.get_moves()
     }
 
+    pub fn tree_bytes(&self) -> usize {
+        self.tree.tree_bytes()
+    }
+
     pub fn make_move(&mut self, m: MoveIdx) -> Box<dyn BigData> {
         self.tree.make_move(m)
     }
 
+    #[allow(clippy::result_unit_err)]
     pub fn unmake_move(&mut self) -> Result<(), ()> {
         self.tree.unmake_move()
     }
 
     pub fn start(self) -> AiOn {
-        let stop_flag = Arc::new(Mutex::new(false));
+        let stop_flag = Arc::new(AtomicBool::new(false));
         let best_move = Arc::new(Mutex::new(None));
+        let depth = Arc::new(Mutex::new(0));
+        let nodes = self.tree.nodes.clone();
 
         AiOn {
             stop_flag: stop_flag.clone(),
             best_move: best_move.clone(),
+            depth: depth.clone(),
+            nodes,
             handler: std::thread::spawn(move || {
-                AiOn::think(stop_flag.clone(), best_move.clone(), self.tree)
+                AiOn::think(
+                    stop_flag.clone(),
+                    best_move.clone(),
+                    depth.clone(),
+                    self.tree,
+                )
             }),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::fen::from_fen;
+
+    #[test]
+    fn test_search_config() {
+        //mate in one with Rh8 and in two with Kb6 then Rh8
+        for (fen, max_depth) in [
+            ("k7/8/1K6/8/8/8/8/7R w - - 0 1", 0),
+            ("k7/8/2K5/8/8/8/8/7R w - - 0 1", 2),
+        ] {
+            let scores = [SearchConfig::plain(), SearchConfig::default()].map(|config| {
+                let mut tree =
+                    BoardTree::new(from_fen(fen).unwrap(), EvalParams::default(), config);
+                let (_mv, score, _nodes) = tree
+                    .best_move_at_depth(
+                        max_depth,
+                        max_depth + 4,
+                        usize::MAX,
+                        LowerBoundRef::new_inf(),
+                        UpperBoundRef::new_inf(),
+                        Arc::new(AtomicBool::new(false)),
+                    )
+                    .unwrap()
+                    .unwrap();
+                score
+            });
+            assert!(matches!(scores[0], Score::Won(_)), "{:?}", scores);
+            assert_eq!(scores[0], scores[1]);
+        }
+    }
+
+    #[test]
+    fn test_aspiration() {
+        let mut tree = BoardTree::new(
+            from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap(),
+            EvalParams::default(),
+            SearchConfig::default(),
+        );
+        let mut search = |low: Score, high: Score| {
+            tree.best_move_at_depth(
+                0,
+                4,
+                usize::MAX,
+                LowerBoundRef::new_finite(low),
+                UpperBoundRef::new_finite(high),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .unwrap()
+            .unwrap()
+        };
+        //a window which is too low fails high, and one containing the mate finds it
+        let (_mv, score, _nodes) = search(Score::Heuristic(-100), Score::Heuristic(100));
+        assert!(score > Score::Heuristic(100));
+        let (mv, score, _nodes) = search(Score::Heuristic(-100), Score::Won(0));
+        assert!(matches!(score, Score::Won(_)));
+
+        //the best move is searched first next time
+        assert_eq!(tree.root_order[0].idx, mv.idx);
+        assert_eq!(tree.root.get_best_move().unwrap().idx, mv.idx);
+    }
+
+    #[test]
+    fn test_antichess_quiescence() {
+        //either pawn takes on d4 and the queen must take the other back, losing itself and black's last piece
+        //quiescence has to follow that capture although it gives the queen away
+        let pawn = PieceKind::Pawn(EnCroissantable::No);
+        let board = Board::new(
+            Team::White,
+            crate::classical::create_signature().with_antichess(),
+            HashMap::from([(Square { idx: 18 }, pawn), (Square { idx: 20 }, pawn)]),
+            HashMap::from([
+                (Square { idx: 27 }, pawn),
+                (Square { idx: 59 }, PieceKind::Queen),
+            ]),
+        );
+        let mut tree = BoardTree::new(board, EvalParams::default(), SearchConfig::default());
+        let (_mv, score, _nodes) = tree
+            .best_move_at_depth(
+                0,
+                4,
+                usize::MAX,
+                LowerBoundRef::new_inf(),
+                UpperBoundRef::new_inf(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .unwrap()
+            .unwrap();
+        assert!(matches!(score, Score::Lost(_)), "{:?}", score);
+    }
+
+    #[test]
+    fn test_threads() {
+        //mate in two with Kb6 then Rh8, found alike by one thread and by several sharing the nodes far enough from the leaves
+        for config in [SearchConfig::plain(), SearchConfig::default()] {
+            let scores = [1, 4].map(|threads| {
+                let config = SearchConfig {
+                    threads,
+                    ..config.clone()
+                };
+                let mut tree = BoardTree::new(
+                    from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap(),
+                    EvalParams::default(),
+                    config,
+                );
+                let (_mv, score, _nodes) = tree
+                    .best_move_at_depth(
+                        3,
+                        5,
+                        usize::MAX,
+                        LowerBoundRef::new_inf(),
+                        UpperBoundRef::new_inf(),
+                        Arc::new(AtomicBool::new(false)),
+                    )
+                    .unwrap()
+                    .unwrap();
+                score
+            });
+            assert!(matches!(scores[0], Score::Won(_)), "{:?}", scores);
+            assert_eq!(scores[0], scores[1]);
+        }
+    }
+
+    #[test]
+    fn test_tree_memory() {
+        //mate in two with Kb6 then Rh8, found alike whether the tree may keep every node or only a few
+        let budget = 1 << 16;
+        let node = 64 * std::mem::size_of::<MoveData>();
+        let [(unlimited, unlimited_bytes), (limited, limited_bytes)] =
+            [usize::MAX, budget].map(|tree_memory| {
+                let mut tree = BoardTree::new(
+                    from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap(),
+                    EvalParams::default(),
+                    SearchConfig {
+                        threads: 1,
+                        tree_memory,
+                        ..SearchConfig::default()
+                    },
+                );
+                let mut score = None;
+                for max_depth in 0..4 {
+                    let (_mv, depth_score, _nodes) = tree
+                        .best_move_at_depth(
+                            max_depth,
+                            max_depth + 2,
+                            usize::MAX,
+                            LowerBoundRef::new_inf(),
+                            UpperBoundRef::new_inf(),
+                            Arc::new(AtomicBool::new(false)),
+                        )
+                        .unwrap()
+                        .unwrap();
+                    score = Some(depth_score);
+                    //nodes stop being kept once the budget is reached, so it is passed by a node at most
+                    assert_eq!(tree.tree_bytes(), tree.root.tree_bytes());
+                    assert!(tree.tree_bytes() < tree_memory.saturating_add(node));
+                }
+                let bytes = tree.root.tree_bytes();
+                //and between searches it is trimmed to leave half the budget free
+                tree.trim();
+                assert!(tree.root.tree_bytes() <= tree_memory / 2);
+                (score.unwrap(), bytes)
+            });
+        assert!(matches!(unlimited, Score::Won(_)), "{:?}", unlimited);
+        assert_eq!(unlimited, limited);
+        assert!(unlimited_bytes > budget);
+        assert!(limited_bytes < budget + node);
+    }
+
+    #[test]
+    fn test_cached_checks() {
+        let sq = |x: usize, y: usize| Square { idx: x + 8 * y };
+        let board = Board::new(
+            Team::White,
+            crate::classical::create_signature().with_rules(rules::ThreeCheck { checks: 3 }),
+            HashMap::from([(sq(3, 0), PieceKind::Queen), (sq(4, 0), PieceKind::King)]),
+            HashMap::from([(sq(4, 7), PieceKind::King)]),
+        );
+        let mut tree = BoardTree::new(
+            board,
+            EvalParams::default(),
+            SearchConfig {
+                threads: 1,
+                ..SearchConfig::default()
+            },
+        );
+        //Qh5+ Kd8 Qh4+ Kc8 Qg4+, each move taken from the nodes the search kept
+        for (from, to) in [
+            (sq(3, 0), sq(7, 4)),
+            (sq(4, 7), sq(3, 7)),
+            (sq(7, 4), sq(7, 3)),
+            (sq(3, 7), sq(2, 7)),
+            (sq(7, 3), sq(6, 3)),
+        ] {
+            tree.best_move_at_depth(
+                0,
+                2,
+                usize::MAX,
+                LowerBoundRef::new_inf(),
+                UpperBoundRef::new_inf(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .unwrap();
+            let idx = tree
+                .root
+                .get_moves()
+                .iter()
+                .position(|m| {
+                    matches!(m, Move::Standard { from_sq, to_sq, .. } if *from_sq == from && *to_sq == to)
+                })
+                .unwrap();
+            assert!(tree.root.get_move(MoveIdx { idx }).board.is_some());
+            tree.make_move(MoveIdx { idx });
+        }
+        assert_eq!(tree.board.count_checks(Team::White), 3);
+        assert!(tree.root.is_terminal());
+        assert_eq!(tree.root.get_evaluation(), Score::Lost(5));
+    }
+
+    #[test]
+    fn test_node_count() {
+        use rayon::prelude::*;
+
+        //every thread's nodes are counted exactly, though the limit only sees them a batch at a time
+        let nodes = NodeCount::new(4, usize::MAX);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            (0..10000)
+                .into_par_iter()
+                .for_each(|_| nodes.visit(usize::MAX).unwrap())
+        });
+        assert_eq!(nodes.get(), 10000);
+
+        let nodes = NodeCount::new(1, usize::MAX);
+        for _ in 1..NODE_COUNT_BATCH {
+            assert!(nodes.visit(NODE_COUNT_BATCH / 2).is_ok());
+        }
+        assert!(nodes.visit(NODE_COUNT_BATCH / 2).is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use super::*;
+
+//piece movement written in Betza's funny notation, e.g. `mRcpR` for the xiangqi cannon
+//
+//atoms
+//  W  one step orthogonally         F  one step diagonally
+//  R  any number of W steps         B  any number of F steps
+//  Q  R and B                       K  W and F
+//  N  the knight's leap
+//a W or F may be followed by a range, so that W3 rides up to three squares, and WW or FF ride without limit
+//R, B and Q may be limited in the same way, e.g. R2
+//
+//modifiers, written before the atom they apply to
+//  m  move only, never capture      c  capture only
+//  n  lame: a knight is blocked by a piece on the orthogonal square it passes
+//  j  jumping: a knight may only leap over a piece on that square
+//  p  hopper: ride up to a screen and continue beyond it, landing on any square until the next piece
+//  g  grasshopper: ride up to a hurdle and land on the square just beyond it
+//
+//the atoms are evaluated against the tables of the Signature, so flat and diagonal are whatever the board
+//says they are and the pieces work on any topology
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Geometry {
+    Flat,
+    Diag,
+    Knight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Path {
+    Normal,
+    Lame,
+    Jumping,
+    Hopper,
+    Grasshopper,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub geometry: Geometry,
+    pub range: Option<usize>, //None for an unlimited rider
+    pub can_move: bool,
+    pub can_capture: bool,
+    pub path: Path,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movement {
+    pub rules: Vec<Rule>,
+}
+
+//what a square along one of a rule's paths means for the piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reach {
+    Land,  //the piece could end its move here, by capture if occupied
+    Watch, //the piece's movement depends on whether this square is occupied
+}
+
+pub fn parse(text: &str) -> Result<Movement, String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return Err(String::from("empty movement"));
+    }
+    let mut rules = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        //modifiers
+        let (mut m, mut c) = (false, false);
+        let mut path = Path::Normal;
+        while i < chars.len() && chars[i].is_ascii_lowercase() {
+            let modifier = chars[i];
+            let new_path = match modifier {
+                'm' => {
+                    m = true;
+                    None
+                }
+                'c' => {
+                    c = true;
+                    None
+                }
+                'n' => Some(Path::Lame),
+                'j' => Some(Path::Jumping),
+                'p' => Some(Path::Hopper),
+                'g' => Some(Path::Grasshopper),
+                other => return Err(format!("unsupported modifier '{}'", other)),
+            };
+            if let Some(new_path) = new_path {
+                if path != Path::Normal {
+                    return Err(format!(
+                        "only one of n, j, p and g may be given but found '{}'",
+                        modifier
+                    ));
+                }
+                path = new_path;
+            }
+            i += 1;
+        }
+
+        //atom
+        let atom = match chars.get(i) {
+            Some(atom) => *atom,
+            None => return Err(String::from("modifiers must be followed by an atom")),
+        };
+        i += 1;
+        let doubled = matches!(atom, 'W' | 'F') && chars.get(i) == Some(&atom);
+        if doubled {
+            i += 1;
+        }
+        let digits: String = chars[i..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        i += digits.len();
+        let range = if digits.is_empty() {
+            None
+        } else {
+            match digits.parse::<usize>() {
+                Ok(range) if range > 0 => Some(range),
+                _ => return Err(format!("invalid range '{}'", digits)),
+            }
+        };
+
+        let (geometries, range): (Vec<Geometry>, Option<usize>) = match (atom, doubled, range) {
+            ('W', false, None) => (vec![Geometry::Flat], Some(1)),
+            ('K', _, None) => (vec![Geometry::Flat, Geometry::Diag], Some(1)),
+            ('F', false, None) => (vec![Geometry::Diag], Some(1)),
+            ('W', _, range) | ('R', _, range) => (vec![Geometry::Flat], range),
+            ('F', _, range) | ('B', _, range) => (vec![Geometry::Diag], range),
+            ('Q', _, range) => (vec![Geometry::Flat, Geometry::Diag], range),
+            ('N', _, None) => (vec![Geometry::Knight], Some(1)),
+            ('N', _, Some(_)) => return Err(String::from("knights cannot ride")),
+            ('K', _, Some(_)) => return Err(String::from("use W and F to give K a range")),
+            (other, _, _) => return Err(format!("unsupported atom '{}'", other)),
+        };
+
+        let knight = geometries == vec![Geometry::Knight];
+        match path {
+            Path::Lame | Path::Jumping if !knight => {
+                return Err(format!("n and j only apply to N but found '{}'", atom));
+            }
+            Path::Hopper | Path::Grasshopper if knight => {
+                return Err(String::from("p and g do not apply to N"));
+            }
+            _ => {}
+        }
+
+        for geometry in geometries {
+            rules.push(Rule {
+                geometry,
+                range,
+                can_move: m || !c,
+                can_capture: c || !m,
+                path,
+            });
+        }
+    }
+    Ok(Movement { rules })
+}
+
+impl Movement {
+    //a rough value on the same scale as PieceKind, where a rook is 10
+    pub fn worth(&self) -> i64 {
+        let mut worth = 0;
+        for rule in &self.rules {
+            let mut rule_worth = match (rule.geometry, rule.range) {
+                (Geometry::Flat, None) => 10,
+                (Geometry::Flat, Some(range)) => (3 * range as i64).min(10),
+                (Geometry::Diag, None) => 6,
+                (Geometry::Diag, Some(range)) => (3 * range as i64).min(6),
+                (Geometry::Knight, _) => 6,
+            };
+            if rule.path != Path::Normal {
+                rule_worth /= 2;
+            }
+            if !(rule.can_move && rule.can_capture) {
+                rule_worth /= 2;
+            }
+            worth += rule_worth;
+        }
+        worth.max(1)
+    }
+
+    //every square the piece on from_sq depends on or could land on, rule by rule
+    //the buffer is the caller's, so that it is filled again rather than allocated for every piece
+    pub fn reach(&self, board: &Board, from_sq: Square, reach: &mut Vec<(usize, Square, Reach)>) {
+        reach.clear();
+        for (rule_idx, rule) in self.rules.iter().enumerate() {
+            let start = reach.len();
+            rule.reach(board, from_sq, |sq, r| {
+                if !reach[start..].contains(&(rule_idx, sq, r)) {
+                    reach.push((rule_idx, sq, r));
+                }
+            });
+        }
+    }
+}
+
+impl Rule {
+    //each square may be pushed more than once, as when two leaps pass over the same square
+    pub fn reach(&self, board: &Board, from_sq: Square, mut push: impl FnMut(Square, Reach)) {
+        let signature = board.get_signature();
+        let slides = match self.geometry {
+            Geometry::Flat => signature.get_flat_slides(from_sq),
+            Geometry::Diag => signature.get_diag_slides(from_sq),
+            Geometry::Knight => {
+                let flat_nbs = signature.get_flat_slides(from_sq);
+                for to_sq in signature.get_knight_moves(from_sq) {
+                    //the orthogonal squares the leap passes over on its way
+                    let passes = || {
+                        signature
+                            .get_diag_slides(*to_sq)
+                            .iter()
+                            .filter_map(|slide| slide.first())
+                            .filter(|sq| flat_nbs.iter().any(|slide| slide.first() == Some(sq)))
+                    };
+                    let open = match self.path {
+                        Path::Lame => passes().any(|sq| board.get_square(*sq).is_none()),
+                        Path::Jumping => passes().any(|sq| board.get_square(*sq).is_some()),
+                        _ => true,
+                    };
+                    if self.path != Path::Normal {
+                        for sq in passes() {
+                            push(*sq, Reach::Watch);
+                        }
+                    }
+                    if open {
+                        push(*to_sq, Reach::Land);
+                    }
+                }
+                return;
+            }
+        };
+
+        for slide in slides {
+            let len = match self.range {
+                Some(range) => range.min(slide.len()),
+                None => slide.len(),
+            };
+            let mut screened = false;
+            for sq in &slide[..len] {
+                let occupied = board.get_square(*sq).is_some();
+                match (self.path, screened) {
+                    (Path::Hopper | Path::Grasshopper, false) => {
+                        push(*sq, Reach::Watch);
+                        screened = occupied;
+                    }
+                    (Path::Grasshopper, true) => {
+                        push(*sq, Reach::Land);
+                        break;
+                    }
+                    _ => {
+                        push(*sq, Reach::Land);
+                        if occupied {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    //whether the piece on from_sq could capture on target_sq
+    pub fn attacks(&self, board: &Board, from_sq: Square, target_sq: Square) -> bool {
+        let mut attacks = false;
+        if self.can_capture {
+            self.reach(board, from_sq, |sq, r| {
+                attacks |= sq == target_sq && r == Reach::Land
+            });
+        }
+        attacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let rule = |geometry, range, can_move, can_capture, path| Rule {
+            geometry,
+            range,
+            can_move,
+            can_capture,
+            path,
+        };
+        assert_eq!(
+            parse("mRcpR").unwrap().rules,
+            vec![
+                rule(Geometry::Flat, None, true, false, Path::Normal),
+                rule(Geometry::Flat, None, false, true, Path::Hopper),
+            ]
+        );
+        assert_eq!(
+            parse("WW").unwrap().rules,
+            vec![rule(Geometry::Flat, None, true, true, Path::Normal)]
+        );
+        assert_eq!(
+            parse("F2nN").unwrap().rules,
+            vec![
+                rule(Geometry::Diag, Some(2), true, true, Path::Normal),
+                rule(Geometry::Knight, Some(1), true, true, Path::Lame),
+            ]
+        );
+        assert_eq!(parse("K").unwrap().rules.len(), 2);
+        assert_eq!(parse("gQ").unwrap().rules.len(), 2);
+        for bad in ["", "X", "m", "nR", "pN", "N2", "npR", "fR", "R0"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    //replace pieces of the board by custom pieces with the given movement
+    fn customise(board: &Board, replacements: Vec<(PieceKind, &str)>) -> Board {
+        let mut signature = board.get_signature().clone();
+        let mut kinds = HashMap::new();
+        for (kind, notation) in replacements {
+            let custom = signature.add_custom_piece(String::new(), parse(notation).unwrap());
+            kinds.insert(kind, custom);
+        }
+        let mut white_pieces = HashMap::new();
+        let mut black_pieces = HashMap::new();
+        for (sq, piece) in board.get_pieces() {
+            let kind = *kinds.get(&piece.kind).unwrap_or(&piece.kind);
+            match piece.team {
+                Team::White => white_pieces.insert(sq, kind),
+                Team::Black => black_pieces.insert(sq, kind),
+            };
+        }
+        Board::new(board.get_turn(), signature, white_pieces, black_pieces)
+    }
+
+    fn perft(board: &mut Board, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        let moves: Vec<Move> = board_data::BoardData::new(board)
+            .get_moves()
+            .into_iter()
+            .cloned()
+            .collect();
+        let mut count = 0;
+        for m in moves {
+            board.make_move(m);
+            count += perft(board, depth - 1);
+            board.unmake_move().unwrap();
+        }
+        count
+    }
+
+    #[test]
+    fn test_custom_pieces() {
+        //the usual pieces written in Betza move exactly like the built in ones
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            let mut board = crate::classical::fen::from_fen(fen).unwrap();
+            let mut custom = customise(
+                &board,
+                vec![
+                    (PieceKind::Rook, "R"),
+                    (PieceKind::Knight, "N"),
+                    (PieceKind::Bishop, "B"),
+                    (PieceKind::Queen, "Q"),
+                ],
+            );
+            assert_eq!(perft(&mut custom, 2), perft(&mut board, 2));
+        }
+
+        //a xiangqi cannon on e2 checks the king on e8 over the pawn on e5
+        let cannon = |fen: &str| {
+            let board = crate::classical::fen::from_fen(fen).unwrap();
+            customise(&board, vec![(PieceKind::Rook, "mRcpR")])
+        };
+        let mut board = cannon("4k3/8/8/4p3/8/8/4R3/K7 b - - 0 1");
+        let data = board_data::BoardData::new(&mut board);
+        assert!(data.is_check());
+        //e5-e4 leaves the screen in place and e7 is still attacked
+        assert_eq!(data.get_moves().len(), 4);
+        let mut board = cannon("4k3/8/8/8/8/8/4R3/K7 b - - 0 1");
+        assert!(!board_data::BoardData::new(&mut board).is_check());
+    }
+}
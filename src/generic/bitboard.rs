@@ -0,0 +1,381 @@
+use super::signature::Signature;
+use super::*;
+
+//on the usual flat 8x8 board a set of squares fits in the bits of a u64, square idx = x + 8y being bit idx
+//this gives a faster way to find what attacks a square, used whenever the signature is laid out that way
+
+//the eight directions a slide may go in, the first four flat and the last four diagonal
+//N, E, NE and NW go up in idx so their nearest blocker is the lowest bit, the others the highest
+const DIRECTIONS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 0),
+    (0, -1),
+    (-1, 0),
+    (1, 1),
+    (-1, 1),
+    (1, -1),
+    (-1, -1),
+];
+const UPWARDS: [bool; 8] = [true, true, false, false, true, true, false, false];
+
+fn ray(sq: usize, (dx, dy): (i8, i8)) -> Vec<Square> {
+    let (mut x, mut y) = ((sq % 8) as i8, (sq / 8) as i8);
+    let mut ray = vec![];
+    loop {
+        x += dx;
+        y += dy;
+        if !(0..8).contains(&x) || !(0..8).contains(&y) {
+            return ray;
+        }
+        ray.push(Square {
+            idx: (x + 8 * y) as usize,
+        });
+    }
+}
+
+fn bits(squares: &[Square]) -> u64 {
+    squares.iter().fold(0, |bits, sq| bits | 1 << sq.idx)
+}
+
+//the squares of a bitboard, lowest first
+pub fn squares(mut bits: u64) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || match bits {
+        0 => None,
+        _ => {
+            let idx = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            Some(Square { idx })
+        }
+    })
+}
+
+//what each piece attacks from each square
+//leapers and pawns are read from the signature, so variants which change them, such as horde, keep working
+#[derive(Debug)]
+pub struct Tables {
+    rays: [[u64; 64]; 8],
+    knight: [u64; 64],
+    king: [u64; 64],
+    //the squares a pawn of each team could take from, indexed by the square taken on
+    pawn_attackers: [[u64; 64]; 2],
+}
+
+impl Tables {
+    //only for signatures whose slides are exactly the rays of the flat 8x8 board
+    pub fn new(signature: &Signature) -> Option<Self> {
+        if signature.num() != 64 {
+            return None;
+        }
+        let mut tables = Self {
+            rays: [[0; 64]; 8],
+            knight: [0; 64],
+            king: [0; 64],
+            pawn_attackers: [[0; 64]; 2],
+        };
+        for sq in (0..64).map(|idx| Square { idx }) {
+            let rays = DIRECTIONS.map(|direction| ray(sq.idx, direction));
+            let same = |slides: &Vec<Vec<Square>>, rays: &[Vec<Square>]| {
+                let mut rays = rays
+                    .iter()
+                    .filter(|ray| !ray.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let mut slides = slides.clone();
+                rays.sort();
+                slides.sort();
+                rays == slides
+            };
+            if !same(signature.get_flat_slides(sq), &rays[0..4])
+                || !same(signature.get_diag_slides(sq), &rays[4..8])
+            {
+                return None;
+            }
+            for (direction, ray) in rays.iter().enumerate() {
+                tables.rays[direction][sq.idx] = bits(ray);
+            }
+            tables.knight[sq.idx] = bits(signature.get_knight_moves(sq));
+            tables.king[sq.idx] = bits(signature.get_king_moves(sq));
+            for (team_idx, team) in [Team::White, Team::Black].into_iter().enumerate() {
+                for to_sq in signature.get_pawn_takes(sq, team) {
+                    tables.pawn_attackers[team_idx][to_sq.idx] |= 1 << sq.idx;
+                }
+            }
+        }
+        Some(tables)
+    }
+
+    //the squares a slide along the direction reaches, up to and including the first occupied one
+    fn slide(&self, direction: usize, sq: Square, occupied: u64) -> u64 {
+        let ray = self.rays[direction][sq.idx];
+        let blockers = ray & occupied;
+        if blockers == 0 {
+            return ray;
+        }
+        let first = match UPWARDS[direction] {
+            true => blockers.trailing_zeros(),
+            false => 63 - blockers.leading_zeros(),
+        };
+        ray ^ self.rays[direction][first as usize]
+    }
+
+    pub fn flat_slides(&self, sq: Square, occupied: u64) -> u64 {
+        (0..4).fold(0, |bits, direction| {
+            bits | self.slide(direction, sq, occupied)
+        })
+    }
+
+    pub fn diag_slides(&self, sq: Square, occupied: u64) -> u64 {
+        (4..8).fold(0, |bits, direction| {
+            bits | self.slide(direction, sq, occupied)
+        })
+    }
+}
+
+const PAWN: usize = 0;
+const KNIGHT: usize = 1;
+const BISHOP: usize = 2;
+const ROOK: usize = 3;
+const QUEEN: usize = 4;
+const ARCHBISHOP: usize = 5;
+const CHANCELLOR: usize = 6;
+const KING: usize = 7;
+
+fn team_idx(team: Team) -> usize {
+    match team {
+        Team::White => 0,
+        Team::Black => 1,
+    }
+}
+
+fn kind_idx(kind: PieceKind) -> Option<usize> {
+    match kind {
+        PieceKind::Pawn(..) => Some(PAWN),
+        PieceKind::Knight => Some(KNIGHT),
+        PieceKind::Bishop => Some(BISHOP),
+        PieceKind::Rook => Some(ROOK),
+        PieceKind::Queen => Some(QUEEN),
+        PieceKind::Archbishop => Some(ARCHBISHOP),
+        PieceKind::Chancellor => Some(CHANCELLOR),
+        PieceKind::King => Some(KING),
+        PieceKind::Grasshopper | PieceKind::Custom(..) => None,
+    }
+}
+
+//where the pieces are, kept by the board alongside its maps of them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bitboards {
+    teams: [u64; 2],
+    kinds: [u64; 8],
+    others: u64, //pieces whose attacks are not worked out here, such as grasshoppers and custom pieces
+}
+
+impl Bitboards {
+    pub fn new(pieces: &[(Square, Piece)]) -> Self {
+        let mut bitboards = Self::default();
+        for (sq, piece) in pieces {
+            bitboards.put(*sq, *piece);
+        }
+        bitboards
+    }
+
+    pub fn put(&mut self, sq: Square, piece: Piece) {
+        let bit = 1 << sq.idx;
+        self.teams[team_idx(piece.team)] |= bit;
+        match kind_idx(piece.kind) {
+            Some(kind) => self.kinds[kind] |= bit,
+            None => self.others |= bit,
+        }
+    }
+
+    pub fn remove(&mut self, sq: Square, piece: Piece) {
+        let bit = !(1 << sq.idx);
+        self.teams[team_idx(piece.team)] &= bit;
+        match kind_idx(piece.kind) {
+            Some(kind) => self.kinds[kind] &= bit,
+            None => self.others &= bit,
+        }
+    }
+
+    pub fn occupied(&self) -> u64 {
+        self.teams[0] | self.teams[1]
+    }
+
+    pub fn team_at(&self, sq: Square) -> Option<Team> {
+        let bit = 1 << sq.idx;
+        if self.teams[0] & bit != 0 {
+            Some(Team::White)
+        } else if self.teams[1] & bit != 0 {
+            Some(Team::Black)
+        } else {
+            None
+        }
+    }
+
+    //the squares of the team's pieces which attack the square, once the pieces on the taken squares are gone
+    //and the squares which are occupied are those given
+    //unknown if the team has pieces whose attacks are not worked out here
+    fn attackers_with(
+        &self,
+        tables: &Tables,
+        sq: Square,
+        team: Team,
+        occupied: u64,
+        taken: u64,
+    ) -> Option<u64> {
+        let own = self.teams[team_idx(team)] & !taken;
+        if self.others & own != 0 {
+            return None;
+        }
+        let kinds = |kinds: &[usize]| kinds.iter().fold(0, |bits, kind| bits | self.kinds[*kind]);
+        let attackers = tables.pawn_attackers[team_idx(team)][sq.idx] & self.kinds[PAWN]
+            | tables.knight[sq.idx] & kinds(&[KNIGHT, ARCHBISHOP, CHANCELLOR])
+            | tables.king[sq.idx] & self.kinds[KING]
+            | tables.diag_slides(sq, occupied) & kinds(&[BISHOP, QUEEN, ARCHBISHOP])
+            | tables.flat_slides(sq, occupied) & kinds(&[ROOK, QUEEN, CHANCELLOR]);
+        Some(attackers & own)
+    }
+
+    //the squares of the team's pieces which attack the square
+    //unknown if the team has pieces whose attacks are not worked out here
+    pub fn attackers(&self, tables: &Tables, sq: Square, team: Team) -> Option<u64> {
+        self.attackers_with(tables, sq, team, self.occupied(), 0)
+    }
+}
+
+//the squares a move empties, fills and takes a piece from, ignoring pockets and explosions
+fn squares_changed(m: &Move) -> (u64, u64, u64) {
+    let bit = |sq: &Square| 1u64 << sq.idx;
+    match m {
+        Move::Standard {
+            victim,
+            from_sq,
+            to_sq,
+            ..
+        } => (
+            bit(from_sq),
+            bit(to_sq),
+            match victim {
+                Some(_) => bit(to_sq),
+                None => 0,
+            },
+        ),
+        Move::Castle {
+            king_from,
+            king_to,
+            rook_from,
+            rook_to,
+            ..
+        } => (
+            bit(king_from) | bit(rook_from),
+            bit(king_to) | bit(rook_to),
+            0,
+        ),
+        Move::EnCroissant {
+            pawn_from,
+            pawn_to,
+            victim_sq,
+            ..
+        } => (
+            bit(pawn_from) | bit(victim_sq),
+            bit(pawn_to),
+            bit(victim_sq),
+        ),
+        Move::Drop { to_sq, .. } => (0, bit(to_sq), 0),
+    }
+}
+
+//whether a move would leave the mover's king attacked, found from the squares the move changes
+//unknown if the board has no bitboards, or the enemy has pieces whose attacks are not worked out by them
+pub fn is_illegal(board: &Board, is_check: bool, m: &Move) -> Option<bool> {
+    let tables = board.signature.get_bitboard_tables()?;
+    let bitboards = board.get_bitboards()?;
+    let turn = board.get_turn();
+    let king_sq = match m {
+        Move::Standard {
+            from_piece, to_sq, ..
+        } if from_piece.kind == PieceKind::King => *to_sq,
+        Move::Castle {
+            king_piece,
+            castle,
+            king_to,
+            ..
+        } if king_piece.kind == PieceKind::King => {
+            if is_check {
+                return Some(true);
+            }
+            for sq in &board.signature.get_castle(turn, *castle).not_chcked {
+                if bitboards.attackers(tables, *sq, turn.flip())? != 0 {
+                    return Some(true);
+                }
+            }
+            *king_to
+        }
+        _ => board.get_king_square(turn)?,
+    };
+    let (emptied, filled, taken) = squares_changed(m);
+    let occupied = bitboards.occupied() & !emptied | filled;
+    bitboards
+        .attackers_with(tables, king_sq, turn.flip(), occupied, taken)
+        .map(|attackers| attackers != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::fen::from_fen;
+    use crate::classical::ClassicalGameType;
+    use crate::generic::board_data::perft;
+
+    #[test]
+    fn test_bitboards() {
+        //only the flat board is laid out as a bitboard
+        assert!(ClassicalGameType::Classical
+            .create_game()
+            .get_bitboards()
+            .is_some());
+        assert!(ClassicalGameType::Horde
+            .create_game()
+            .get_bitboards()
+            .is_some());
+        for game in [ClassicalGameType::Cylinder, ClassicalGameType::Torus] {
+            assert!(game.create_game().get_bitboards().is_none());
+        }
+
+        //a rook on d4 sees along its rank and file as far as the first piece each way
+        let board = from_fen("4k3/3p4/8/8/1P1R2n1/8/8/4K3 w - - 0 1").unwrap();
+        let tables = board.signature.get_bitboard_tables().unwrap();
+        let d4 = Square { idx: 27 };
+        let seen = squares(tables.flat_slides(d4, board.get_bitboards().unwrap().occupied()))
+            .map(|sq| sq.idx)
+            .collect::<Vec<_>>();
+        assert_eq!(seen, vec![3, 11, 19, 25, 26, 28, 29, 30, 35, 43, 51]);
+    }
+
+    #[test]
+    fn test_perft() {
+        //known counts, and the same counts without bitboards
+        for (fen, depth, count) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                3,
+                8902,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                2,
+                2039,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3, 2812),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                2,
+                264,
+            ),
+        ] {
+            let mut board = from_fen(fen).unwrap();
+            assert_eq!(perft(&mut board, depth), count, "{}", fen);
+            board.bitboards = None;
+            assert_eq!(perft(&mut board, depth), count, "{} without bitboards", fen);
+        }
+    }
+}
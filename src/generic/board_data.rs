@@ -1,42 +1,52 @@
 use super::ai::*;
 use super::score::*;
+use super::vision::*;
 use super::*;
+use std::cell::RefCell;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum GrasshopperVisionKind {
-    Slide,
-    Hurdle,
-    Land,
+#[derive(Debug, Clone, Default)]
+struct PseudoMoves {
+    white_pseudomoves: Vec<Move>,
+    black_pseudomoves: Vec<Move>,
+    //scratch space used while generating, kept to save allocating it again
+    pieces: Vec<(Square, Piece)>,
+    crossants: Vec<(Square, Square)>, //where an en crossant capture lands and the pawn it takes
+    landed: Vec<usize>, //the squares already reached by the current piece are those marked with the stamp
+    stamp: usize,
+    reach: Vec<(usize, Square, betza::Reach)>, //where a custom piece reaches
 }
 
-#[derive(Debug, Clone)]
-enum Vision {
-    Teleport {
-        piece: Piece,
-        from: Square,
-        to: Square,
-    },
-    Slide {
-        piece: Piece,
-        from: Square,
-        slide: Vec<Square>,
-        slide_idx: usize,
-    },
-    Grasshopper {
-        piece: Piece,
-        from: Square,
-        slide: Vec<Square>,
-        slide_idx: usize,
-        kind: GrasshopperVisionKind,
-    },
+thread_local! {
+    //pseudomoves which are no longer needed, whose buffers are filled again by the next node on the thread
+    static SPARE_PSEUDOMOVES: RefCell<Vec<PseudoMoves>> = const { RefCell::new(vec![]) };
 }
 
-#[derive(Debug, Clone)]
-struct PseudoMoves {
-    white_pseudomoves: Vec<Move>,
-    black_pseudomoves: Vec<Move>,
-    white_vision: Vec<Vec<Vision>>,
-    black_vision: Vec<Vec<Vision>>,
+//pseudomoves whose buffers go back to the spares of the thread once they have been used
+struct PooledPseudoMoves(PseudoMoves);
+
+impl PooledPseudoMoves {
+    fn new(board: &Board) -> Self {
+        let mut pseudomoves = SPARE_PSEUDOMOVES
+            .with(|spare| spare.borrow_mut().pop())
+            .unwrap_or_default();
+        pseudomoves.generate(board);
+        Self(pseudomoves)
+    }
+}
+
+impl std::ops::Deref for PooledPseudoMoves {
+    type Target = PseudoMoves;
+
+    fn deref(&self) -> &PseudoMoves {
+        &self.0
+    }
+}
+
+impl Drop for PooledPseudoMoves {
+    fn drop(&mut self) {
+        let pseudomoves = std::mem::take(&mut self.0);
+        let _ = SPARE_PSEUDOMOVES.try_with(|spare| spare.borrow_mut().push(pseudomoves));
+    }
 }
 
 impl PseudoMoves {
@@ -47,30 +57,25 @@ impl PseudoMoves {
         }
     }
 
-    fn get_vision(&self, team: Team, square: Square) -> &Vec<Vision> {
-        match team {
-            Team::White => &self.white_vision[square.idx],
-            Team::Black => &self.black_vision[square.idx],
-        }
-    }
-
-    fn new(board: &Board) -> Self {
-        let mut white_vision: Vec<Vec<Vision>> =
-            (0..board.signature.num()).map(|_i| vec![]).collect();
-        let mut black_vision: Vec<Vec<Vision>> =
-            (0..board.signature.num()).map(|_i| vec![]).collect();
-
-        let mut white_pseudomoves = vec![];
-        let mut black_pseudomoves = vec![];
+    fn generate(&mut self, board: &Board) {
+        let num = board.signature.num();
+        let PseudoMoves {
+            white_pseudomoves,
+            black_pseudomoves,
+            pieces,
+            crossants,
+            landed,
+            stamp,
+            reach,
+        } = self;
+        white_pseudomoves.clear();
+        black_pseudomoves.clear();
+        //stamps only go up, so whatever was marked on an earlier board is never current
+        landed.resize(num, 0);
 
-        macro_rules! add_vision {
-            ($s:expr, $v:expr, $t:expr) => {
-                match $t {
-                    Team::White => white_vision[$s.idx].push($v),
-                    Team::Black => black_vision[$s.idx].push($v),
-                }
-            };
-        }
+        //in antichess kings are ordinary pieces which may be captured
+        let capturable =
+            |piece: Piece| piece.kind != PieceKind::King || board.signature.is_antichess();
 
         macro_rules! add_move {
             ($m:expr, $t:expr) => {
@@ -130,279 +135,31 @@ impl PseudoMoves {
             };
         }
 
-        macro_rules! add_teleports {
-            ($teles:expr, $piece:expr, $from_sq:expr) => {{
-                for to_sq in $teles {
-                    match board.get_square(*to_sq) {
-                        None => {
-                            add_vision!(
-                                to_sq,
-                                Vision::Teleport {
-                                    piece: *$piece,
-                                    from: *$from_sq,
-                                    to: *to_sq,
-                                },
-                                $piece.team
-                            );
-                            add_move!(
-                                Move::Standard {
-                                    from_piece: *$piece,
-                                    to_piece: $piece.moved(),
-                                    victim: None,
-                                    from_sq: *$from_sq,
-                                    to_sq: *to_sq,
-                                },
-                                $piece.team
-                            );
-                        }
-                        Some(blocking) => {
-                            if blocking.team != $piece.team {
-                                add_vision!(
-                                    to_sq,
-                                    Vision::Teleport {
-                                        piece: *$piece,
-                                        from: *$from_sq,
-                                        to: *to_sq,
-                                    },
-                                    $piece.team
-                                );
-                                if blocking.kind != PieceKind::King {
-                                    add_move!(
-                                        Move::Standard {
-                                            from_piece: *$piece,
-                                            to_piece: $piece.moved(),
-                                            victim: Some(blocking),
-                                            from_sq: *$from_sq,
-                                            to_sq: *to_sq,
-                                        },
-                                        $piece.team
-                                    );
-                                }
-                            } else {
-                                add_vision!(
-                                    to_sq,
-                                    Vision::Teleport {
-                                        piece: *$piece,
-                                        from: *$from_sq,
-                                        to: *to_sq,
-                                    },
-                                    $piece.team
-                                );
-                            }
-                        }
-                    }
-                }
-            }};
-        }
-
-        macro_rules! add_slides {
-            ($all_slides:expr, $piece:expr, $from_sq:expr) => {{
-                let mut done = HashSet::new();
-                for slides in $all_slides {
-                    for slide in slides {
-                        let mut slide_idx = 0;
-                        while slide_idx < slide.len() {
-                            let to_sq = slide[slide_idx];
-                            if !done.contains(&to_sq) {
-                                done.insert(to_sq);
-                                match board.get_square(to_sq) {
-                                    None => {
-                                        add_vision!(
-                                            to_sq,
-                                            Vision::Slide {
-                                                piece: $piece,
-                                                from: $from_sq,
-                                                slide: slide.clone(),
-                                                slide_idx: slide_idx,
-                                            },
-                                            $piece.team
-                                        );
-                                        add_move!(
-                                            Move::Standard {
-                                                from_piece: $piece,
-                                                to_piece: $piece.moved(),
-                                                victim: None,
-                                                from_sq: $from_sq,
-                                                to_sq: to_sq,
-                                            },
-                                            $piece.team
-                                        );
-                                    }
-                                    Some(blocking) => {
-                                        if blocking.team != $piece.team {
-                                            add_vision!(
-                                                to_sq,
-                                                Vision::Slide {
-                                                    piece: $piece,
-                                                    from: $from_sq,
-                                                    slide: slide,
-                                                    slide_idx: slide_idx,
-                                                },
-                                                $piece.team
-                                            );
-                                            if blocking.kind != PieceKind::King {
-                                                add_move!(
-                                                    Move::Standard {
-                                                        from_piece: $piece,
-                                                        to_piece: $piece.moved(),
-                                                        victim: Some(blocking),
-                                                        from_sq: $from_sq,
-                                                        to_sq: to_sq,
-                                                    },
-                                                    $piece.team
-                                                );
-                                            }
-                                        } else {
-                                            add_vision!(
-                                                to_sq,
-                                                Vision::Slide {
-                                                    piece: $piece,
-                                                    from: $from_sq,
-                                                    slide: slide,
-                                                    slide_idx: slide_idx,
-                                                },
-                                                $piece.team
-                                            );
-                                        }
-
-                                        break;
-                                    }
-                                }
-                            };
-                            slide_idx += 1;
-                        }
-                    }
-                }
-            }};
-        }
-
-        macro_rules! add_grasshopper_slides {
-            ($all_slides:expr, $piece:expr, $from_sq:expr) => {{
-                let mut done = HashSet::new();
-                for slides in $all_slides {
-                    for slide in slides {
-                        let mut slide_idx = 0;
-                        while slide_idx < slide.len() {
-                            let jump_sq = slide[slide_idx];
-                            if !done.contains(&jump_sq) {
-                                done.insert(jump_sq);
-                                match board.get_square(jump_sq) {
-                                    None => {
-                                        add_vision!(
-                                            jump_sq,
-                                            Vision::Grasshopper {
-                                                piece: $piece,
-                                                from: $from_sq,
-                                                slide: slide.clone(),
-                                                slide_idx: slide_idx,
-                                                kind: GrasshopperVisionKind::Slide
-                                            },
-                                            $piece.team
-                                        );
-                                    }
-                                    Some(hurdle) => {
-                                        add_vision!(
-                                            jump_sq,
-                                            Vision::Grasshopper {
-                                                piece: $piece,
-                                                from: $from_sq,
-                                                slide: slide.clone(),
-                                                slide_idx: slide_idx,
-                                                kind: GrasshopperVisionKind::Hurdle
-                                            },
-                                            $piece.team
-                                        );
-                                        let land_slide_idx = slide_idx + 1;
-                                        if land_slide_idx < slide.len() {
-                                            let land_sq = slide[land_slide_idx];
-                                            match board.get_square(land_sq) {
-                                                None => {
-                                                    add_move!(
-                                                        Move::Standard {
-                                                            from_piece: $piece,
-                                                            to_piece: $piece.moved(),
-                                                            victim: None,
-                                                            from_sq: $from_sq,
-                                                            to_sq: land_sq,
-                                                        },
-                                                        $piece.team
-                                                    );
-                                                    add_vision!(
-                                                        land_sq,
-                                                        Vision::Grasshopper {
-                                                            piece: $piece,
-                                                            from: $from_sq,
-                                                            slide: slide,
-                                                            slide_idx: land_slide_idx,
-                                                            kind: GrasshopperVisionKind::Land
-                                                        },
-                                                        $piece.team
-                                                    );
-                                                }
-                                                Some(land_piece) => {
-                                                    if land_piece.team != $piece.team {
-                                                        if land_piece.kind != PieceKind::King {
-                                                            add_move!(
-                                                                Move::Standard {
-                                                                    from_piece: $piece,
-                                                                    to_piece: $piece.moved(),
-                                                                    victim: Some(land_piece),
-                                                                    from_sq: $from_sq,
-                                                                    to_sq: land_sq,
-                                                                },
-                                                                $piece.team
-                                                            );
-                                                        }
-                                                    }
-                                                    add_vision!(
-                                                        land_sq,
-                                                        Vision::Grasshopper {
-                                                            piece: $piece,
-                                                            from: $from_sq,
-                                                            slide: slide,
-                                                            slide_idx: land_slide_idx,
-                                                            kind: GrasshopperVisionKind::Land
-                                                        },
-                                                        $piece.team
-                                                    );
-                                                }
-                                            }
-                                        }
-                                        break;
-                                    }
-                                }
-                            };
-                            slide_idx += 1;
-                        }
-                    }
-                }
-            }};
-        }
-
         //sort the pieces so that two equal boards produce moves in the same order
-        let mut all_pieces: Vec<_> = board
-            .white_pieces
-            .iter()
-            .chain(board.black_pieces.iter())
-            .collect();
-        all_pieces.sort_by_key(|(s, _p)| s.idx);
-
-        let mut crossant_map = HashMap::new();
-        for (sq, piece) in &all_pieces {
-            match piece {
-                Piece {
-                    kind: PieceKind::Pawn(EnCroissantable::Yes { move_num, take_sq }),
-                    ..
-                } => {
-                    if *move_num == board.get_move_num() {
-                        crossant_map.insert(take_sq, *sq);
-                    }
+        pieces.clear();
+        pieces.extend(
+            board
+                .white_pieces
+                .iter()
+                .chain(board.black_pieces.iter())
+                .map(|(s, p)| (*s, *p)),
+        );
+        pieces.sort_unstable_by_key(|(s, _p)| s.idx);
+
+        crossants.clear();
+        for (sq, piece) in pieces.iter() {
+            if let Piece {
+                kind: PieceKind::Pawn(EnCroissantable::Yes { move_num, take_sq }),
+                ..
+            } = piece
+            {
+                if *move_num == board.get_move_num() {
+                    crossants.push((*take_sq, *sq));
                 }
-                _ => {}
             }
         }
 
-        for (from_sq, piece) in all_pieces {
+        for (from_sq, piece) in pieces.iter() {
             match piece.kind {
                 PieceKind::Pawn(..) => {
                     //pawn movement
@@ -421,62 +178,97 @@ impl PseudoMoves {
                     for to_sq in board.signature.get_pawn_takes(*from_sq, piece.team) {
                         match board.get_square(*to_sq) {
                             None => {
-                                for (take_sq, victim_sq) in &crossant_map {
-                                    if *take_sq == to_sq {
+                                for (take_sq, victim_sq) in crossants.iter() {
+                                    if take_sq == to_sq {
                                         //en crossant
-                                        let victim = board.get_square(**victim_sq).unwrap();
+                                        let victim = board.get_square(*victim_sq).unwrap();
                                         if victim.team != piece.team {
                                             add_move!(
                                                 Move::EnCroissant {
                                                     pawn: *piece,
                                                     pawn_from: *from_sq,
                                                     pawn_to: *to_sq,
-                                                    victim: victim,
-                                                    victim_sq: **victim_sq
+                                                    victim,
+                                                    victim_sq: *victim_sq
                                                 },
                                                 board.get_turn()
                                             );
                                         }
                                     }
                                 }
-
-                                add_vision!(
-                                    to_sq,
-                                    Vision::Teleport {
-                                        piece: *piece,
-                                        from: *from_sq,
-                                        to: *to_sq,
-                                    },
-                                    piece.team
-                                );
                             }
                             Some(diag) => {
-                                if diag.team != piece.team {
-                                    add_vision!(
-                                        to_sq,
-                                        Vision::Teleport {
-                                            piece: *piece,
-                                            from: *from_sq,
-                                            to: *to_sq,
-                                        },
-                                        piece.team
+                                if diag.team != piece.team && capturable(diag) {
+                                    add_pawn_move!(
+                                        *piece,
+                                        Some(diag),
+                                        *from_sq,
+                                        *to_sq,
+                                        None::<&Square>
                                     );
-                                    if diag.kind != PieceKind::King {
-                                        add_pawn_move!(
-                                            *piece,
-                                            Some(diag),
-                                            *from_sq,
-                                            *to_sq,
-                                            None::<&Square>
-                                        );
-                                    }
-                                } else {
-                                    add_vision!(
+                                }
+                            }
+                        }
+                    }
+                }
+                PieceKind::Custom(idx) => {
+                    let movement = &board.signature.get_custom_piece(idx).movement;
+                    *stamp += 1;
+                    movement.reach(board, *from_sq, reach);
+                    for (rule_idx, to_sq, reach) in reach.iter().copied() {
+                        let rule = &movement.rules[rule_idx];
+                        if reach == betza::Reach::Land {
+                            let victim = board.get_square(to_sq);
+                            let allowed = match victim {
+                                None => rule.can_move,
+                                Some(victim) => {
+                                    rule.can_capture
+                                        && victim.team != piece.team
+                                        && capturable(victim)
+                                }
+                            };
+                            if allowed && landed[to_sq.idx] != *stamp {
+                                landed[to_sq.idx] = *stamp;
+                                add_move!(
+                                    Move::Standard {
+                                        from_piece: *piece,
+                                        to_piece: piece.moved(),
+                                        victim,
+                                        from_sq: *from_sq,
                                         to_sq,
-                                        Vision::Teleport {
-                                            piece: *piece,
-                                            from: *from_sq,
-                                            to: *to_sq,
+                                    },
+                                    piece.team
+                                );
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    //every other piece moves to wherever it attacks, which the board already knows
+                    for (to_sq, vision) in board.vision.get_seen_from(*from_sq) {
+                        if !vision.attacks() {
+                            continue;
+                        }
+                        match board.get_square(*to_sq) {
+                            None => add_move!(
+                                Move::Standard {
+                                    from_piece: *piece,
+                                    to_piece: piece.moved(),
+                                    victim: None,
+                                    from_sq: *from_sq,
+                                    to_sq: *to_sq,
+                                },
+                                piece.team
+                            ),
+                            Some(victim) => {
+                                if victim.team != piece.team && capturable(victim) {
+                                    add_move!(
+                                        Move::Standard {
+                                            from_piece: *piece,
+                                            to_piece: piece.moved(),
+                                            victim: Some(victim),
+                                            from_sq: *from_sq,
+                                            to_sq: *to_sq,
                                         },
                                         piece.team
                                     );
@@ -485,71 +277,71 @@ impl PseudoMoves {
                         }
                     }
                 }
-                PieceKind::Grasshopper => {
-                    add_grasshopper_slides!(
-                        vec![
-                            board.signature.get_flat_slides(*from_sq).clone(),
-                            board.signature.get_diag_slides(*from_sq).clone()
-                        ],
-                        *piece,
-                        *from_sq
-                    );
-                }
-                PieceKind::Rook => {
-                    add_slides!(
-                        vec![board.signature.get_flat_slides(*from_sq).clone()],
-                        *piece,
-                        *from_sq
-                    );
-                }
-                PieceKind::Bishop => {
-                    add_slides!(
-                        vec![board.signature.get_diag_slides(*from_sq).clone()],
-                        *piece,
-                        *from_sq
-                    );
-                }
-                PieceKind::Queen => {
-                    add_slides!(
-                        vec![
-                            board.signature.get_flat_slides(*from_sq).clone(),
-                            board.signature.get_diag_slides(*from_sq).clone()
-                        ],
-                        *piece,
-                        *from_sq
-                    );
-                }
-                PieceKind::Knight => {
-                    add_teleports!(board.signature.get_knight_moves(*from_sq), piece, from_sq);
-                }
-                PieceKind::King => {
-                    add_teleports!(board.signature.get_king_moves(*from_sq), piece, from_sq);
+            }
+        }
+
+        for team in [Team::White, Team::Black] {
+            for (castle, castle_signature) in
+                board.signature.get_team_castles(team).iter().enumerate()
+            {
+                if let (Some(king_piece), Some(rook_piece)) = (
+                    board.get_square(castle_signature.king_from),
+                    board.get_square(castle_signature.rook_from),
+                ) {
+                    //note that king_piece may not actually be the king
+                    if !king_piece.moved && !rook_piece.moved {
+                        //the destinations may be where the king and rook start, as in chess960
+                        let vacant = |sq: &Square| {
+                            board.get_square(*sq).is_none()
+                                || *sq == castle_signature.king_from
+                                || *sq == castle_signature.rook_from
+                        };
+                        if castle_signature.not_occupied.iter().all(vacant)
+                            && vacant(&castle_signature.king_to)
+                            && vacant(&castle_signature.rook_to)
+                        {
+                            add_move!(
+                                Move::Castle {
+                                    king_from: castle_signature.king_from,
+                                    castle,
+                                    king_to: castle_signature.king_to,
+                                    king_piece,
+                                    rook_from: castle_signature.rook_from,
+                                    rook_to: castle_signature.rook_to,
+                                    rook_piece
+                                },
+                                team
+                            );
+                        }
+                    }
                 }
             }
         }
 
-        for (team, castle_signature) in board.signature.get_castles() {
-            if let (Some(king_piece), Some(rook_piece)) = (
-                board.get_square(castle_signature.king_from),
-                board.get_square(castle_signature.rook_from),
-            ) {
-                //note that king_piece may not actually be the king
-                if !king_piece.moved && !rook_piece.moved {
-                    if castle_signature
-                        .not_occupied
-                        .iter()
-                        .chain(vec![&castle_signature.king_to, &castle_signature.rook_to])
-                        .all(|sq| board.get_square(*sq).is_none())
-                    {
+        if board.signature.has_drops() {
+            for team in [Team::White, Team::Black] {
+                for kind in board.get_pocket(team).keys() {
+                    for idx in 0..board.signature.num() {
+                        let sq = Square { idx };
+                        if board.get_square(sq).is_some() {
+                            continue;
+                        }
+                        //pawns may not be dropped where they would promote or could never move
+                        if let PieceKind::Pawn(..) = kind {
+                            if board.signature.get_pawn_moves(sq, team).is_empty()
+                                || board.signature.get_pawn_promotions(sq, team).is_some()
+                            {
+                                continue;
+                            }
+                        }
                         add_move!(
-                            Move::Castle {
-                                king_from: castle_signature.king_from,
-                                king_through: castle_signature.not_chcked.clone(),
-                                king_to: castle_signature.king_to,
-                                king_piece: king_piece,
-                                rook_from: castle_signature.rook_from,
-                                rook_to: castle_signature.rook_to,
-                                rook_piece: rook_piece
+                            Move::Drop {
+                                piece: Piece {
+                                    kind: *kind,
+                                    team,
+                                    moved: true,
+                                },
+                                to_sq: sq,
                             },
                             team
                         );
@@ -557,14 +349,397 @@ impl PseudoMoves {
                 }
             }
         }
+    }
+}
 
-        Self {
-            white_pseudomoves,
-            black_pseudomoves,
-            white_vision,
-            black_vision,
+//in atomic chess kings are blown up rather than captured, so legality is found by making the move
+//a move may not blow up its own king, and once the enemy king is gone nothing else matters
+//touching kings cannot check each other since neither can capture without exploding
+fn is_illegal_atomic(board: &mut Board, is_check: bool, pseudo_move: &Move) -> bool {
+    let turn = board.get_turn();
+    if let Move::Castle {
+        castle, king_piece, ..
+    } = pseudo_move
+    {
+        if king_piece.kind == PieceKind::King {
+            if is_check {
+                return true;
+            }
+            for through in &board.signature.get_castle(turn, *castle).not_chcked {
+                if board
+                    .get_vision(turn.flip(), *through)
+                    .iter()
+                    .any(|vis| vis.attacks())
+                {
+                    return true;
+                }
+            }
         }
     }
+
+    board.make_move(*pseudo_move);
+    let illegal = if !board.has_king(turn) {
+        true
+    } else if !board.has_king(turn.flip()) || board.kings_touch() {
+        false
+    } else {
+        board
+            .get_vision(turn.flip(), board.get_king_square(turn).unwrap())
+            .iter()
+            .any(|vis| vis.attacks())
+    };
+    board.unmake_move().unwrap();
+    illegal
+}
+
+//in antichess captures are compulsory, kings are ordinary pieces and there is no castling
+fn antichess_moves(board: &Board, pseudomoves: &PseudoMoves) -> Vec<Move> {
+    let is_capture = |m: &&Move| {
+        matches!(
+            m,
+            Move::Standard {
+                victim: Some(_),
+                ..
+            } | Move::EnCroissant { .. }
+        )
+    };
+    let candidates: Vec<&Move> = pseudomoves
+        .get_pseudomoves(board.get_turn())
+        .iter()
+        .filter(|m| !matches!(m, Move::Castle { .. }))
+        .collect();
+    let must_capture = candidates.iter().any(is_capture);
+    candidates
+        .into_iter()
+        .filter(|m| !must_capture || is_capture(m))
+        .cloned()
+        .collect()
+}
+
+//a position in the tablebases is scored exactly, though its moves are kept to choose between
+fn tablebase_score(board: &mut Board) -> Option<Score> {
+    if let Some(score) = board
+        .signature
+        .get_tablebases()
+        .and_then(|tablebases| tablebases.probe(board))
+    {
+        return Some(score);
+    }
+    let syzygy = board.signature.get_syzygy()?.clone();
+    syzygy.probe(board)
+}
+
+//the aim is to run out of moves, so every piece is a liability and being made to capture is a nuisance
+//mobility is worth something since pieces which can get out of the way are easier to give up later
+fn antichess_evaluation(
+    board: &Board,
+    params: &evaluation::EvalParams,
+    pseudomoves: &PseudoMoves,
+    moves: &[Move],
+) -> Score {
+    if moves.is_empty() {
+        return Score::Won(board.get_move_num());
+    }
+    let turn = board.get_turn();
+    let mut score = 0;
+    for (_sq, piece) in board.get_pieces() {
+        score += match piece.team == turn {
+            true => -params.antichess_piece,
+            false => params.antichess_piece,
+        };
+    }
+    score += params.antichess_mobility
+        * (pseudomoves.get_pseudomoves(turn).len() as i64
+            - pseudomoves.get_pseudomoves(turn.flip()).len() as i64);
+    if moves.iter().any(|m| {
+        matches!(
+            m,
+            Move::Standard {
+                victim: Some(_),
+                ..
+            } | Move::EnCroissant { .. }
+        )
+    }) {
+        score -= params.antichess_capture;
+    }
+    Score::Heuristic(score)
+}
+
+//the middlegame danger to each king, positive in favour of white
+//pawns of its own team around the king shelter it, enemy pawns advancing on it and pieces attacking the squares next to it threaten it
+fn king_safety(board: &Board, params: &evaluation::EvalParams) -> i64 {
+    let mut score = 0;
+    for team in [Team::White, Team::Black] {
+        let king_sq = match board.get_king_square(team) {
+            Some(sq) if board.has_king(team) => sq,
+            _ => continue,
+        };
+        let king_moves = board.signature.get_king_moves(king_sq);
+        let in_zone = |sq: &Square| *sq == king_sq || king_moves.contains(sq);
+
+        let mut safety = 0;
+        let mut n = 0;
+        for (sq, piece) in board.white_pieces.iter().chain(&board.black_pieces) {
+            if let PieceKind::Pawn(..) = piece.kind {
+                if piece.team == team {
+                    if in_zone(sq) {
+                        safety += params.shelter;
+                    }
+                } else if board
+                    .signature
+                    .get_pawn_moves(*sq, piece.team)
+                    .iter()
+                    .any(|(to, double)| in_zone(to) || double.iter().any(in_zone))
+                    || board
+                        .signature
+                        .get_pawn_takes(*sq, piece.team)
+                        .iter()
+                        .any(in_zone)
+                {
+                    safety -= params.storm;
+                }
+            } else if piece.team != team
+                && board
+                    .vision
+                    .get_seen_from(*sq)
+                    .iter()
+                    .any(|(to, vision)| vision.attacks() && in_zone(to))
+            {
+                //each piece attacking the zone counts once, however much of it the piece attacks
+                n += 1;
+            }
+        }
+        //a lone attacker is rarely dangerous but several together are
+        safety -= params.king_attack * n * n;
+
+        score += match team {
+            Team::White => safety,
+            Team::Black => -safety,
+        };
+    }
+    score
+}
+
+//a piece which could take on a square once everything between it and the square is gone
+//the path of a slider or grasshopper is the start of its slide, up to the square
+#[derive(Debug, Clone, Copy)]
+enum Attacker {
+    Direct {
+        piece: Piece,
+        from: Square,
+    },
+    Slide {
+        piece: Piece,
+        from: Square,
+        slide: SlideRef,
+        len: usize,
+    },
+    Hop {
+        piece: Piece,
+        from: Square,
+        slide: SlideRef,
+        len: usize,
+    }, //the last square of the path is the hurdle
+}
+
+impl Attacker {
+    fn piece(&self) -> &Piece {
+        match self {
+            Attacker::Direct { piece, .. } => piece,
+            Attacker::Slide { piece, .. } => piece,
+            Attacker::Hop { piece, .. } => piece,
+        }
+    }
+
+    fn from(&self) -> Square {
+        match self {
+            Attacker::Direct { from, .. } => *from,
+            Attacker::Slide { from, .. } => *from,
+            Attacker::Hop { from, .. } => *from,
+        }
+    }
+
+    //whether the piece can take now that the pieces on the removed squares have gone
+    fn can_take(&self, board: &Board, removed: &[Square]) -> bool {
+        let occupied = |sq: &Square| board.get_square(*sq).is_some() && !removed.contains(sq);
+        !removed.contains(&self.from())
+            && match self {
+                Attacker::Direct { .. } => true,
+                Attacker::Slide {
+                    from, slide, len, ..
+                } => !slide.get(board, *from)[..*len].iter().any(occupied),
+                Attacker::Hop {
+                    from, slide, len, ..
+                } => match slide.get(board, *from)[..*len].split_last() {
+                    Some((hurdle, rest)) => occupied(hurdle) && !rest.iter().any(occupied),
+                    None => false,
+                },
+            }
+    }
+}
+
+thread_local! {
+    //the attackers, gains and squares taken from of an exchange, filled again by the next one worked out on the thread
+    static SEE_BUFFERS: RefCell<(Vec<Attacker>, Vec<i64>, Vec<Square>)> =
+        const { RefCell::new((vec![], vec![], vec![])) };
+}
+
+//every piece of either team which attacks the square, or would once the pieces in its way were taken
+//slides run both ways, so a slider or grasshopper whose slide crosses the square stands on one of the square's own
+//slides, and is found from what it sees there however many pieces are in between
+fn find_attackers(board: &Board, sq: Square, attackers: &mut Vec<Attacker>) {
+    attackers.clear();
+    for vision in board
+        .get_vision(Team::White, sq)
+        .iter()
+        .chain(board.get_vision(Team::Black, sq))
+    {
+        match vision {
+            Vision::Teleport { piece, from, .. } => attackers.push(Attacker::Direct {
+                piece: *piece,
+                from: *from,
+            }),
+            Vision::Custom { piece, from, .. } if vision.attacks() => {
+                attackers.push(Attacker::Direct {
+                    piece: *piece,
+                    from: *from,
+                })
+            }
+            _ => {}
+        }
+    }
+    let signature = &board.signature;
+    for line in signature
+        .get_flat_slides(sq)
+        .iter()
+        .chain(signature.get_diag_slides(sq))
+    {
+        for from in line {
+            if *from == sq || board.get_square(*from).is_none() {
+                continue;
+            }
+            for (_to, vision) in board.vision.get_seen_from(*from) {
+                let (piece, slide, hop) = match vision {
+                    Vision::Slide { piece, slide, .. } => (piece, slide, false),
+                    Vision::Grasshopper { piece, slide, .. } => (piece, slide, true),
+                    _ => continue,
+                };
+                let squares = slide.get(board, *from);
+                let len = match squares.iter().position(|s| *s == sq) {
+                    Some(len) => len,
+                    None => continue,
+                };
+                //a piece on several of the square's slides, or seeing several squares of one, is only added once
+                let known = attackers.iter().any(|attacker| match attacker {
+                    Attacker::Slide { from: f, slide, .. }
+                    | Attacker::Hop { from: f, slide, .. } => {
+                        f == from && slide.get(board, *f)[0] == squares[0]
+                    }
+                    Attacker::Direct { .. } => false,
+                });
+                if !known {
+                    attackers.push(match hop {
+                        false => Attacker::Slide {
+                            piece: *piece,
+                            from: *from,
+                            slide: *slide,
+                            len,
+                        },
+                        true => Attacker::Hop {
+                            piece: *piece,
+                            from: *from,
+                            slide: *slide,
+                            len,
+                        },
+                    });
+                }
+            }
+        }
+    }
+}
+
+//the material won by a capture if both sides then keep taking on the square with their cheapest piece
+//while it pays, in the same units as the evaluation
+//a king only takes when nothing can take it back
+//explosions take more than the piece on the square, so exchanges do not work out the usual way in atomic chess
+//and in antichess captures are compulsory and losing material is the aim, so an exchange says nothing about a capture
+pub fn see(board: &Board, params: &evaluation::EvalParams, mv: &Move) -> Option<i64> {
+    let (from_piece, to_piece, victim, from_sq, to_sq) = match mv {
+        Move::Standard {
+            from_piece,
+            to_piece,
+            victim: Some(victim),
+            from_sq,
+            to_sq,
+        } if !board.signature.is_atomic() && !board.signature.is_antichess() => {
+            (from_piece, to_piece, victim, from_sq, to_sq)
+        }
+        _ => return None,
+    };
+    SEE_BUFFERS.with_borrow_mut(|(attackers, gains, removed)| {
+        find_attackers(board, *to_sq, attackers);
+        let value = |piece: &Piece| match params.get_worth(&board.signature, piece.kind) {
+            Some(worth) => worth * 1000,
+            None => 1_000_000_000,
+        };
+        let cheapest = |team: Team, removed: &[Square]| {
+            attackers
+                .iter()
+                .filter(|attacker| {
+                    attacker.piece().team == team && attacker.can_take(board, removed)
+                })
+                .min_by_key(|attacker| value(attacker.piece()))
+                .copied()
+        };
+
+        gains.clear();
+        gains.push(value(victim) + value(to_piece) - value(from_piece));
+        removed.clear();
+        removed.push(*from_sq);
+        let mut occupant = *to_piece;
+        let mut team = from_piece.team.flip();
+        while let Some(attacker) = cheapest(team, removed) {
+            removed.push(attacker.from());
+            if attacker.piece().kind == PieceKind::King && cheapest(team.flip(), removed).is_some()
+            {
+                break;
+            }
+            gains.push(value(&occupant) - gains.last().unwrap());
+            occupant = *attacker.piece();
+            team = team.flip();
+        }
+        //either side may stop taking whenever continuing would lose
+        while gains.len() > 1 {
+            let gain = gains.pop().unwrap();
+            let last = gains.last_mut().unwrap();
+            *last = -std::cmp::max(-*last, gain);
+        }
+        Some(gains[0])
+    })
+}
+
+//the number of sequences of legal moves of the given length, to check move generation against known counts
+pub fn perft(board: &mut Board, depth: usize) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = BoardData::new(board)
+        .get_moves()
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    if depth == 1 {
+        return moves.len();
+    }
+    moves
+        .into_iter()
+        .map(|m| {
+            board.make_move(m);
+            let count = perft(board, depth - 1);
+            board.unmake_move().unwrap();
+            count
+        })
+        .sum()
 }
 
 #[derive(Debug, Clone)]
@@ -573,203 +748,136 @@ pub struct BoardData {
     is_check: bool,
     evaluation: Score, //TODO: rename to evalutation
     moves: Vec<MoveData>,
+    best_move: Option<MoveIdx>, //found by the last search through here, to be tried first next time
+    null_move: Option<Box<BoardData>>, //the position after passing, for null move pruning
+    visited: usize, //the last search of the tree to reach the node, to tell which parts of the tree have gone cold
 }
 
 impl BoardData {
     pub fn new(board: &mut Board) -> Self {
-        let turn = board.get_turn();
+        Self::with_params(board, &evaluation::DEFAULT_PARAMS)
+    }
 
-        let pseudomoves = PseudoMoves::new(board);
-        let checkers = pseudomoves.get_vision(turn.flip(), board.get_king_square(turn));
-        let is_check = !checkers.is_empty();
+    pub fn with_params(board: &mut Board, params: &evaluation::EvalParams) -> Self {
+        let mut data = Self::by_rules(board, params);
+        if let Score::Heuristic(_) = data.evaluation {
+            if let Some(score) = tablebase_score(board) {
+                data.evaluation = score;
+            }
+        }
+        data
+    }
 
-        let is_illegal = |board: &mut Board, pseudo_move: &Move| -> bool {
-            //compile a list of things which might be checking the king after the move is made
-            let mut hot_squares = vec![];
-            match pseudo_move {
-                Move::Standard {
-                    from_piece,
-                    to_piece,
-                    victim,
-                    from_sq,
-                    to_sq,
-                } => {
-                    if from_piece.kind == PieceKind::King {
-                        debug_assert!(to_piece.kind == PieceKind::King);
-                        hot_squares.push(*from_sq);
-                        hot_squares.push(*to_sq);
-                    } else {
-                        hot_squares.push(board.get_king_square(turn));
-                        hot_squares.push(*from_sq);
-                        hot_squares.push(*to_sq);
-                    }
-                }
-                Move::Castle {
-                    king_from,
-                    king_through,
-                    king_to,
-                    king_piece,
-                    rook_from,
-                    rook_to,
-                    rook_piece,
-                } => {
-                    if king_piece.kind == PieceKind::King {
-                        if is_check {
-                            return true; //can't castle while in check
-                        }
-                        for through in king_through {
-                            //can't castle through check
-                            for vis in pseudomoves.get_vision(turn.flip(), through.clone()) {
-                                match vis {
-                                    Vision::Teleport { .. } => {
-                                        return true;
-                                    }
-                                    Vision::Slide { .. } => {
-                                        return true;
-                                    }
-                                    Vision::Grasshopper {
-                                        piece,
-                                        from,
-                                        slide,
-                                        slide_idx,
-                                        kind,
-                                    } => {
-                                        if kind == &GrasshopperVisionKind::Land {
-                                            return true;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+    //the node as the rules and the evaluation make it, without looking the position up in any tablebases
+    pub fn by_rules(board: &mut Board, params: &evaluation::EvalParams) -> Self {
+        let turn = board.get_turn();
+        let atomic = board.signature.is_atomic();
 
-                    hot_squares.push(*king_from);
-                    for sq in king_through {
-                        hot_squares.push(*sq);
-                    }
-                    hot_squares.push(*king_to);
-                    hot_squares.push(*rook_from);
-                    hot_squares.push(*rook_to);
-                }
-                Move::EnCroissant {
-                    pawn,
-                    pawn_from,
-                    pawn_to,
-                    victim,
-                    victim_sq,
-                } => {
-                    hot_squares.push(*pawn_from);
-                    hot_squares.push(*pawn_to);
-                    hot_squares.push(*victim_sq);
-                }
-            }
+        //the game is lost once the king has been blown up
+        if atomic && !board.has_king(turn) {
+            return Self {
+                moves: vec![],
+                is_check: false,
+                evaluation: Score::Lost(board.get_move_num()),
+                best_move: None,
+                null_move: None,
+                visited: 0,
+            };
+        }
 
-            let mut hot_vis = vec![];
-            for hot_sq in hot_squares {
-                for vis in pseudomoves.get_vision(turn.flip(), hot_sq) {
-                    hot_vis.push(vis);
-                }
-            }
+        let pseudomoves = PooledPseudoMoves::new(board);
 
-            board.make_move(pseudo_move.clone());
-            let new_king_sq = board.get_king_square(turn);
+        //antichess is won by running out of moves, whether by losing every piece or being stalemated
+        if board.signature.is_antichess() {
+            let moves = antichess_moves(board, &pseudomoves);
+            let evaluation = antichess_evaluation(board, params, &pseudomoves, &moves);
+            return Self {
+                moves: moves.into_iter().map(MoveData::new).collect(),
+                is_check: false,
+                evaluation,
+                best_move: None,
+                null_move: None,
+                visited: 0,
+            };
+        }
 
-            let mut is_illegal = false;
+        //a side without a king is never in check, and so has no illegal moves
+        let king_sq = board.get_king_square(turn);
+        let is_check = board.is_in_check();
 
-            //go through each possible check and see if it is actually a checker
-            'IS_ILLEGAL: for possible_checker in hot_vis {
-                match possible_checker {
-                    Vision::Teleport { piece, from, to } => match board.get_square(*from) {
-                        Some(after_piece) => {
-                            if after_piece.team == piece.team && *to == new_king_sq {
-                                is_illegal = true;
-                                break 'IS_ILLEGAL;
-                            }
-                        }
-                        None => {}
-                    },
-                    Vision::Slide {
-                        piece,
-                        from,
-                        slide,
-                        slide_idx,
-                    } => match board.get_square(*from) {
-                        Some(after_piece) => {
-                            if after_piece.team == piece.team {
-                                for sq in slide {
-                                    match board.get_square(*sq) {
-                                        Some(slide_piece) => {
-                                            if *sq == new_king_sq {
-                                                is_illegal = true;
-                                                break 'IS_ILLEGAL;
-                                            }
-                                            break;
-                                        }
-                                        None => {}
-                                    }
-                                }
-                            }
-                        }
-                        None => {}
-                    },
-                    Vision::Grasshopper {
-                        piece,
-                        from,
-                        slide,
-                        slide_idx,
-                        kind,
-                    } => match board.get_square(*from) {
-                        Some(after_piece) => {
-                            if after_piece.team == piece.team {
-                                for idx in 0..(slide.len() - 1) {
-                                    let slide_sq = slide[idx];
-                                    match board.get_square(slide_sq) {
-                                        Some(hurdle_piece) => {
-                                            let land_sq = slide[idx + 1];
-                                            if land_sq == new_king_sq {
-                                                is_illegal = true;
-                                                break 'IS_ILLEGAL;
-                                            }
-                                            break;
-                                        }
-                                        None => {}
-                                    }
-                                }
-                            }
+        for rules in board.signature.get_rules() {
+            if let Some(evaluation) = rules.terminal(board, is_check) {
+                return Self {
+                    moves: vec![],
+                    is_check,
+                    evaluation,
+                    best_move: None,
+                    null_move: None,
+                    visited: 0,
+                };
+            }
+        }
+
+        let is_illegal = |board: &mut Board, pseudo_move: &Move| -> bool {
+            if king_sq.is_none() {
+                return false;
+            }
+            if let Some(is_illegal) = bitboard::is_illegal(board, is_check, pseudo_move) {
+                return is_illegal;
+            }
+            if let Move::Castle {
+                castle, king_piece, ..
+            } = pseudo_move
+            {
+                if king_piece.kind == PieceKind::King {
+                    if is_check {
+                        return true; //can't castle while in check
+                    }
+                    for through in &board.signature.get_castle(turn, *castle).not_chcked {
+                        //can't castle through check
+                        if board
+                            .get_vision(turn.flip(), *through)
+                            .iter()
+                            .any(|vis| vis.attacks())
+                        {
+                            return true;
                         }
-                        None => {}
-                    },
+                    }
                 }
             }
 
+            //the vision of the board follows the move, so whether the king is left attacked can be read off it
+            board.make_move(*pseudo_move);
+            let is_illegal = board
+                .get_vision(turn.flip(), board.get_king_square(turn).unwrap())
+                .iter()
+                .any(|vis| vis.attacks());
+
             board.unmake_move().unwrap();
             is_illegal
         };
 
-        let mut moves: Vec<Move> = vec![];
+        //the node keeps its legal moves, so they are its only allocation which is not reused
+        let mut moves = Vec::with_capacity(pseudomoves.get_pseudomoves(turn).len());
         for pseudo_move in pseudomoves.get_pseudomoves(turn) {
             //compute whether pseudo_move is legal is not
-            let illegal = is_illegal(board, pseudo_move);
+            let illegal = match atomic {
+                true => is_illegal_atomic(board, is_check, pseudo_move),
+                false => is_illegal(board, pseudo_move),
+            };
 
-            if cfg!(debug_assertions) {
+            if cfg!(debug_assertions) && !atomic && king_sq.is_some() {
                 match pseudo_move {
                     Move::Castle { .. } => {}
                     _ => {
                         //check that the fast check calculation is valid
                         let mut test_board = board.clone();
-                        test_board.make_move(pseudo_move.clone());
-                        let test_board_pseudomoves = PseudoMoves::new(&test_board);
-                        let king_square = test_board.get_king_square(turn);
-                        let test_illegal = test_board_pseudomoves
-                            .get_vision(turn.flip(), king_square)
-                            .into_iter()
-                            .any(|vis| match vis {
-                                Vision::Teleport { .. } => true,
-                                Vision::Slide { .. } => true,
-                                Vision::Grasshopper { kind, .. } => {
-                                    *kind == GrasshopperVisionKind::Land
-                                }
-                            });
+                        test_board.make_move(*pseudo_move);
+                        let king_square = test_board.get_king_square(turn).unwrap();
+                        let test_illegal = vision::VisionMap::new(&test_board)
+                            .get(turn.flip(), king_square)
+                            .iter()
+                            .any(|vis| vis.attacks());
                         test_board.unmake_move().unwrap();
                         if test_illegal != illegal {
                             println!("NUM = {:?}", board.get_move_num());
@@ -782,7 +890,7 @@ impl BoardData {
             }
 
             if !illegal {
-                moves.push(pseudo_move.clone());
+                moves.push(MoveData::new(*pseudo_move));
             }
         }
 
@@ -804,40 +912,57 @@ impl BoardData {
                 }
             } else {
                 let mut score = 0;
+                let tables = board.signature.get_piece_square_tables();
+                let (mut mg, mut eg, mut material) = (0, 0, 0);
                 for (sq, piece) in board.white_pieces.iter().chain(board.black_pieces.iter()) {
                     if piece.kind != PieceKind::King {
-                        score += signed_score!(piece.team, piece.kind.worth().unwrap() * 1000);
+                        let worth = params.get_worth(&board.signature, piece.kind).unwrap();
+                        score += signed_score!(piece.team, worth * 1000);
+                        if !matches!(piece.kind, PieceKind::Pawn(..)) {
+                            material += worth;
+                        }
                     }
+                    let (piece_mg, piece_eg) = tables.value(piece, *sq);
+                    mg += signed_score!(piece.team, piece_mg * params.pst);
+                    eg += signed_score!(piece.team, piece_eg * params.pst);
                     if let PieceKind::Pawn(_) = piece.kind {
-                        match board.signature.get_pawn_promotion_distance(*sq, piece.team) {
-                            Some(1) => {
-                                score += signed_score!(piece.team, 2500);
-                            }
-                            Some(2) => {
-                                score += signed_score!(piece.team, 300);
-                            }
-                            Some(3) => {
-                                score += signed_score!(piece.team, 25);
-                            }
-                            Some(4) => {
-                                score += signed_score!(piece.team, 20);
-                            }
-                            Some(5) => {
-                                score += signed_score!(piece.team, 1);
-                            }
-                            Some(_) => {}
-                            None => {}
+                        if let Some(distance) =
+                            board.signature.get_pawn_promotion_distance(*sq, piece.team)
+                        {
+                            score +=
+                                signed_score!(piece.team, params.get_promotion_bonus(distance));
                         }
                     }
                 }
-                for (sq_idx, visions, team) in pseudomoves
-                    .white_vision
+                //taper from the middlegame to the endgame values as the pieces come off
+                let phase = match params.phase {
+                    phase if phase > 0 => material.clamp(0, phase) * 256 / phase,
+                    _ => 0,
+                };
+                let (pawn_mg, pawn_eg) = pawn_structure::evaluate(board, params);
+                mg += pawn_mg + king_safety(board, params);
+                eg += pawn_eg;
+                score += (mg * phase + eg * (256 - phase)) / 256;
+                for team in [Team::White, Team::Black] {
+                    for (kind, count) in board.get_pocket(team) {
+                        score += signed_score!(
+                            team,
+                            params.get_worth(&board.signature, *kind).unwrap()
+                                * 1000
+                                * *count as i64
+                        );
+                    }
+                }
+                for (sq_idx, visions, team) in board
+                    .vision
+                    .get_all(Team::White)
                     .iter()
                     .enumerate()
                     .map(|(i, v)| (i, v, Team::White))
                     .chain(
-                        pseudomoves
-                            .black_vision
+                        board
+                            .vision
+                            .get_all(Team::Black)
                             .iter()
                             .enumerate()
                             .map(|(i, v)| (i, v, Team::Black)),
@@ -845,34 +970,36 @@ impl BoardData {
                 {
                     let sq = Square { idx: sq_idx };
                     for vision in visions {
-                        let from_piece = match &vision {
-                            Vision::Teleport { piece, .. } => piece,
-                            Vision::Slide { piece, .. } => piece,
-                            Vision::Grasshopper { piece, .. } => piece,
-                        };
+                        let from_piece = vision.piece();
                         if from_piece.kind != PieceKind::King {
-                            let from_worth = from_piece.kind.worth().unwrap();
+                            let from_worth =
+                                params.get_worth(&board.signature, from_piece.kind).unwrap();
                             match board.get_square(sq) {
                                 Some(to_piece) => {
                                     if to_piece.kind != PieceKind::King {
-                                        let _to_worth = to_piece.kind.worth();
                                         if to_piece.team == from_piece.team {
                                             //defend
                                         } else {
                                             //attack
-                                            score += signed_score!(team, (10 - from_worth) * 3);
+                                            score += signed_score!(
+                                                team,
+                                                (params.vision - from_worth) * params.attack
+                                            );
                                         }
                                     }
                                 }
                                 None => {
                                     //visible
 
-                                    score += signed_score!(team, 10 - from_worth);
+                                    score += signed_score!(team, params.vision - from_worth);
                                 }
                             }
                         }
                     }
                 }
+                for rules in board.signature.get_rules() {
+                    score += rules.evaluate(board);
+                }
                 Score::Heuristic(match board.get_turn() {
                     Team::White => score,
                     Team::Black => -score,
@@ -881,9 +1008,12 @@ impl BoardData {
         };
 
         Self {
-            moves: moves.into_iter().map(|m| MoveData::new(m)).collect(),
-            is_check: is_check,
+            moves,
+            is_check,
             evaluation: score,
+            best_move: None,
+            null_move: None,
+            visited: 0,
         }
     }
 
@@ -899,6 +1029,36 @@ impl BoardData {
         self.evaluation
     }
 
+    pub fn get_best_move(&self) -> Option<MoveIdx> {
+        self.best_move
+    }
+
+    pub fn set_best_move(&mut self, best_move: Option<MoveIdx>) {
+        self.best_move = best_move;
+    }
+
+    pub fn get_visited(&self) -> usize {
+        self.visited
+    }
+
+    pub fn set_visited(&mut self, search: usize) {
+        self.visited = search;
+    }
+
+    //the position after passing, if it is kept
+    pub fn get_null_move(&self) -> Option<&BoardData> {
+        self.null_move.as_deref()
+    }
+
+    pub fn get_null_move_mut(&mut self) -> &mut Option<Box<BoardData>> {
+        &mut self.null_move
+    }
+
+    //the memory the node's own moves take, not counting the nodes kept below them
+    pub fn bytes(&self) -> usize {
+        self.moves.capacity() * std::mem::size_of::<MoveData>()
+    }
+
     pub fn get_moves_data(&self) -> &Vec<MoveData> {
         &self.moves
     }
@@ -910,7 +1070,7 @@ impl BoardData {
     pub fn get_moves(&self) -> Vec<&Move> {
         self.moves
             .iter()
-            .map(|move_data| (move_data.get_move()))
+            .map(|move_data| move_data.get_move())
             .collect()
     }
 
@@ -922,3 +1082,98 @@ impl BoardData {
         &mut self.moves[move_idx.idx]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::fen::from_fen;
+    use crate::generic::evaluation::{EvalParams, DEFAULT_PARAMS};
+
+    #[test]
+    fn test_see() {
+        let see = |fen, from: (u8, u8), to: (u8, u8)| {
+            let mut board = from_fen(fen).unwrap();
+            let sq = |(x, y): (u8, u8)| Square {
+                idx: (x + 8 * y) as usize,
+            };
+            BoardData::new(&mut board)
+                .get_moves_data()
+                .iter()
+                .find(|move_data| {
+                    matches!(move_data.get_move(), Move::Standard { from_sq, to_sq, .. } if *from_sq == sq(from) && *to_sq == sq(to))
+                })
+                .unwrap()
+                .get_see(&board, &DEFAULT_PARAMS)
+        };
+
+        //a rook taking a pawn defended by a pawn
+        assert_eq!(
+            see("4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1", (3, 0), (3, 4)),
+            Some(2000 - 10000)
+        );
+        //the rook behind comes through to take back, so the first rook is not lost for nothing
+        assert_eq!(
+            see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", (3, 1), (3, 4)),
+            Some(2000)
+        );
+        //a grasshopper hops over its own pawn onto a rook
+        assert_eq!(
+            see("4k3/8/4p3/3r4/3P4/3G4/8/4K3 w - - 0 1", (3, 2), (3, 4)),
+            Some(10000 - 1000)
+        );
+        //moves which are not captures have no exchange
+        assert_eq!(see("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", (3, 0), (3, 4)), None);
+    }
+
+    #[test]
+    fn test_reused_buffers() {
+        fn moves(board: &mut Board) -> Vec<Move> {
+            BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .copied()
+                .collect()
+        }
+        //a new thread has no spare buffers of its own
+        let fresh = |board: &Board| {
+            let mut board = board.clone();
+            std::thread::spawn(move || moves(&mut board))
+                .join()
+                .unwrap()
+        };
+
+        //buffers left by a bigger board, or by one with more pieces, give the same moves as new ones
+        for mut board in [
+            crate::hexagonal::HexGameType::Glinski.create_game(),
+            from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap(),
+            crate::hexagonal::HexGameType::Glinski.create_game(),
+            from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap(),
+        ] {
+            assert_eq!(moves(&mut board), fresh(&board));
+        }
+    }
+
+    #[test]
+    fn test_king_safety() {
+        let safety = |fen, params| {
+            let board = from_fen(fen).unwrap();
+            king_safety(&board, &params)
+        };
+        let only = |shelter, storm, king_attack| EvalParams {
+            shelter,
+            storm,
+            king_attack,
+            ..DEFAULT_PARAMS
+        };
+
+        //white has castled behind three pawns
+        let fen = "4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1";
+        assert_eq!(safety(fen, only(1, 0, 0)), 3);
+        //black pawns coming up on the king
+        let fen = "4k3/8/8/8/8/6pp/5PPP/6K1 w - - 0 1";
+        assert_eq!(safety(fen, only(0, 1, 0)), -2);
+        //a queen and a knight both bearing on the squares around the king
+        let fen = "4k3/8/8/8/8/5n2/5PPP/3q2K1 w - - 0 1";
+        assert_eq!(safety(fen, only(0, 0, 1)), -4);
+    }
+}
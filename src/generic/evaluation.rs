@@ -0,0 +1,212 @@
+use super::signature::Signature;
+use super::*;
+
+//the weights used by the heuristic evaluation in BoardData
+//they may be loaded from a file of `key = value` lines, with `#` starting a comment
+//
+//  pawn = 2                   material values, in units which are multiplied by 1000
+//  knight = 6                 likewise grasshopper rook bishop queen archbishop chancellor
+//  promotion = 2500 300 25    bonus for pawns 1, 2, 3, ... moves from promoting, up to 5 values
+//  vision = 10                each square seen is worth this less the worth of the piece seeing it
+//  attack = 3                 and seeing an enemy piece is worth that many times as much
+//  pst = 10                   each point of a piece-square table is worth this much
+//  phase = 124                non-pawn material at and above which the middlegame tables apply fully
+//  shelter = 100              each pawn next to its own king, in the middlegame
+//  storm = 80                 each enemy pawn about to move or take next to a king, in the middlegame
+//  king_attack = 50           times the square of the number of pieces attacking next to a king
+//  doubled = 200              each pawn with another of its team in front of it
+//  isolated = 200             each pawn no other pawn of its team could ever defend
+//  backward = 150             each pawn which cannot advance safely nor be defended
+//  passed = 300               each pawn no enemy pawn can stop, twice this in the endgame
+//  antichess_piece = 1000     in antichess, each piece left to the side to move counts this against it
+//  antichess_mobility = 10    in antichess, each pseudomove more than the opponent has
+//  antichess_capture = 200    in antichess, against the side to move when it is forced to capture
+//
+//keys which are not given keep their default values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    pub pawn: i64,
+    pub grasshopper: i64,
+    pub rook: i64,
+    pub knight: i64,
+    pub bishop: i64,
+    pub queen: i64,
+    pub archbishop: i64,
+    pub chancellor: i64,
+    pub promotion: [i64; 5],
+    pub vision: i64,
+    pub attack: i64,
+    pub pst: i64,
+    pub phase: i64,
+    pub shelter: i64,
+    pub storm: i64,
+    pub king_attack: i64,
+    pub doubled: i64,
+    pub isolated: i64,
+    pub backward: i64,
+    pub passed: i64,
+    pub antichess_piece: i64,
+    pub antichess_mobility: i64,
+    pub antichess_capture: i64,
+}
+
+pub const DEFAULT_PARAMS: EvalParams = EvalParams {
+    pawn: 2,
+    grasshopper: 1,
+    rook: 10,
+    knight: 6,
+    bishop: 6,
+    queen: 18,
+    archbishop: 15,
+    chancellor: 17,
+    promotion: [2500, 300, 25, 20, 1],
+    vision: 10,
+    attack: 3,
+    pst: 10,
+    phase: 124,
+    shelter: 100,
+    storm: 80,
+    king_attack: 50,
+    doubled: 200,
+    isolated: 200,
+    backward: 150,
+    passed: 300,
+    antichess_piece: 1000,
+    antichess_mobility: 10,
+    antichess_capture: 200,
+};
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS
+    }
+}
+
+impl EvalParams {
+    //the material value of a piece, or None for the king
+    //custom pieces have no entry and are valued from their movement
+    pub fn get_worth(&self, signature: &Signature, kind: PieceKind) -> Option<i64> {
+        match kind {
+            PieceKind::Pawn(..) => Some(self.pawn),
+            PieceKind::Grasshopper => Some(self.grasshopper),
+            PieceKind::Rook => Some(self.rook),
+            PieceKind::Knight => Some(self.knight),
+            PieceKind::Bishop => Some(self.bishop),
+            PieceKind::Queen => Some(self.queen),
+            PieceKind::Archbishop => Some(self.archbishop),
+            PieceKind::Chancellor => Some(self.chancellor),
+            PieceKind::King => None,
+            PieceKind::Custom(idx) => Some(signature.get_custom_piece(idx).movement.worth()),
+        }
+    }
+
+    //the bonus for a pawn the given number of moves from promoting
+    pub fn get_promotion_bonus(&self, distance: usize) -> i64 {
+        match distance {
+            1..=5 => self.promotion[distance - 1],
+            _ => 0,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut params = DEFAULT_PARAMS;
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", i + 1, message);
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    return Err(error(format!(
+                        "expected `key = value` but found '{}'",
+                        line
+                    )))
+                }
+            };
+            let mut numbers = vec![];
+            for word in value.split_whitespace() {
+                match word.parse::<i64>() {
+                    Ok(n) => numbers.push(n),
+                    Err(_) => return Err(error(format!("expected a number but found '{}'", word))),
+                }
+            }
+            if key == "promotion" {
+                if numbers.is_empty() || numbers.len() > 5 {
+                    return Err(error(String::from("expected from 1 to 5 numbers")));
+                }
+                params.promotion = [0; 5];
+                params.promotion[..numbers.len()].copy_from_slice(&numbers);
+                continue;
+            }
+            let n = match numbers[..] {
+                [n] => n,
+                _ => return Err(error(format!("{}: expected one number", key))),
+            };
+            match key {
+                "pawn" => params.pawn = n,
+                "grasshopper" => params.grasshopper = n,
+                "rook" => params.rook = n,
+                "knight" => params.knight = n,
+                "bishop" => params.bishop = n,
+                "queen" => params.queen = n,
+                "archbishop" => params.archbishop = n,
+                "chancellor" => params.chancellor = n,
+                "vision" => params.vision = n,
+                "attack" => params.attack = n,
+                "pst" => params.pst = n,
+                "phase" => params.phase = n,
+                "shelter" => params.shelter = n,
+                "storm" => params.storm = n,
+                "king_attack" => params.king_attack = n,
+                "doubled" => params.doubled = n,
+                "isolated" => params.isolated = n,
+                "backward" => params.backward = n,
+                "passed" => params.passed = n,
+                "antichess_piece" => params.antichess_piece = n,
+                "antichess_mobility" => params.antichess_mobility = n,
+                "antichess_capture" => params.antichess_capture = n,
+                _ => return Err(error(format!("unknown key '{}'", key))),
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) => Err(format!("could not read {}: {}", path.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(EvalParams::parse("# nothing\n").unwrap(), DEFAULT_PARAMS);
+        let params = EvalParams::parse("queen = 20\npromotion = 900 100\nvision = 8").unwrap();
+        assert_eq!(params.queen, 20);
+        assert_eq!(params.rook, DEFAULT_PARAMS.rook);
+        assert_eq!(params.promotion, [900, 100, 0, 0, 0]);
+        assert_eq!(params.get_promotion_bonus(2), 100);
+        assert_eq!(params.get_promotion_bonus(6), 0);
+        assert_eq!(params.vision, 8);
+        let params = EvalParams::parse("antichess_capture = 50").unwrap();
+        assert_eq!(params.antichess_capture, 50);
+        assert_eq!(params.antichess_piece, DEFAULT_PARAMS.antichess_piece);
+
+        assert!(EvalParams::parse("queen 20").is_err());
+        assert!(EvalParams::parse("queen = lots").is_err());
+        assert!(EvalParams::parse("queen = 1 2").is_err());
+        assert!(EvalParams::parse("king = 100").is_err());
+        assert!(EvalParams::parse("promotion = 1 2 3 4 5 6").is_err());
+    }
+}
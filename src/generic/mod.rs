@@ -1,9 +1,19 @@
 pub mod ai;
+pub mod betza;
+pub mod bitboard;
 pub mod board_data;
+pub mod evaluation;
+pub mod ordering;
+pub mod pawn_structure;
+pub mod piece_square;
+pub mod rules;
 pub mod score;
 pub mod signature;
+pub mod syzygy;
+pub mod tablebase;
+pub mod vision;
 
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Square {
@@ -11,7 +21,7 @@ pub struct Square {
 }
 impl PartialOrd for Square {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.idx.partial_cmp(&other.idx)
+        Some(self.cmp(other))
     }
 }
 impl Ord for Square {
@@ -35,13 +45,13 @@ impl Team {
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum EnCroissantable {
     No,
     Yes { move_num: usize, take_sq: Square },
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum PieceKind {
     Pawn(EnCroissantable),
     Grasshopper,
@@ -49,21 +59,10 @@ pub enum PieceKind {
     Knight,
     Bishop,
     Queen,
+    Archbishop, //bishop + knight
+    Chancellor, //rook + knight
     King,
-}
-
-impl PieceKind {
-    pub fn worth(&self) -> Option<i64> {
-        match self {
-            PieceKind::Pawn(..) => Some(2),
-            PieceKind::Grasshopper => Some(1),
-            PieceKind::Rook => Some(10),
-            PieceKind::Knight => Some(6),
-            PieceKind::Bishop => Some(6),
-            PieceKind::Queen => Some(18),
-            PieceKind::King => None,
-        }
-    }
+    Custom(usize), //index of a piece defined in the signature
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -83,7 +82,7 @@ impl Piece {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Move {
     Standard {
         from_piece: Piece,
@@ -94,7 +93,7 @@ pub enum Move {
     },
     Castle {
         king_from: Square,
-        king_through: Vec<Square>,
+        castle: usize, //which of the team's castles in the signature, whose squares the king passes through
         king_to: Square,
         king_piece: Piece,
         rook_from: Square,
@@ -109,6 +108,10 @@ pub enum Move {
         victim: Piece,
         victim_sq: Square,
     },
+    Drop {
+        piece: Piece,
+        to_sq: Square,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -123,14 +126,34 @@ pub struct Board {
     signature: signature::Signature,
     white_pieces: BTreeMap<Square, Piece>,
     black_pieces: BTreeMap<Square, Piece>,
-    white_king: Square,
-    black_king: Square,
+    white_king: Option<Square>, //either side may have no king, as white in horde
+    black_king: Option<Square>,
+    //for each move made, whether it gave check
+    checks: Vec<bool>,
+    //captured pieces which may be dropped back onto the board, in games with drops
+    white_pocket: BTreeMap<PieceKind, usize>,
+    black_pocket: BTreeMap<PieceKind, usize>,
+    //squares of pieces which have promoted, since they go back to the pocket as pawns when captured
+    promoted: BTreeSet<Square>,
+    //for each move made, whether it captured a promoted piece
+    promoted_victims: Vec<bool>,
+    //for each move made in atomic games, the pieces blown up by it
+    exploded: Vec<Vec<(Square, Piece)>>,
+    //the number of moves made when each null move still on the board was made
+    nulls: Vec<usize>,
+    //the pieces again as bitboards, when the signature is the flat 8x8 board
+    bitboards: Option<bitboard::Bitboards>,
+    //what every piece sees, kept up to date as pieces move
+    vision: vision::VisionMap,
 }
 impl PartialEq for Board {
     fn eq(&self, other: &Self) -> bool {
         self.turn == other.turn
             && self.white_pieces == other.white_pieces
             && self.black_pieces == other.black_pieces
+            && self.white_pocket == other.white_pocket
+            && self.black_pocket == other.black_pocket
+            && self.promoted == other.promoted
     }
 }
 impl Eq for Board {}
@@ -139,6 +162,9 @@ impl core::hash::Hash for Board {
         self.turn.hash(state);
         self.white_pieces.hash(state);
         self.black_pieces.hash(state);
+        self.white_pocket.hash(state);
+        self.black_pocket.hash(state);
+        self.promoted.hash(state);
     }
 }
 
@@ -184,17 +210,78 @@ impl Board {
             }
         }
 
-        let board = Self {
+        let bitboards = signature.get_bitboard_tables().map(|_tables| {
+            bitboard::Bitboards::new(
+                &white_pieces
+                    .iter()
+                    .chain(black_pieces.iter())
+                    .map(|(sq, piece)| (*sq, *piece))
+                    .collect::<Vec<_>>(),
+            )
+        });
+
+        let mut board = Self {
             turn,
             moves: vec![],
             signature,
             white_pieces,
             black_pieces,
-            white_king: white_king.unwrap(),
-            black_king: black_king.unwrap(),
+            white_king,
+            black_king,
+            checks: vec![],
+            white_pocket: BTreeMap::new(),
+            black_pocket: BTreeMap::new(),
+            promoted: BTreeSet::new(),
+            promoted_victims: vec![],
+            nulls: vec![],
+            exploded: vec![],
+            bitboards,
+            vision: vision::VisionMap::default(),
         };
+        board.vision = vision::VisionMap::new(&board);
 
-        return board;
+        board
+    }
+
+    //clear the board and put the pieces on it with the team to move, as if the game had started there
+    pub fn set_position(&mut self, turn: Team, pieces: &[(Square, Piece)]) {
+        for (sq, piece) in self.get_pieces() {
+            self.lift(sq, piece.team);
+        }
+        self.turn = turn;
+        self.moves.clear();
+        self.checks.clear();
+        self.white_pocket.clear();
+        self.black_pocket.clear();
+        self.promoted.clear();
+        self.promoted_victims.clear();
+        self.exploded.clear();
+        self.nulls.clear();
+        self.white_king = None;
+        self.black_king = None;
+        for (sq, piece) in pieces {
+            self.place(*sq, *piece);
+            if piece.kind == PieceKind::King {
+                match piece.team {
+                    Team::White => self.white_king = Some(*sq),
+                    Team::Black => self.black_king = Some(*sq),
+                }
+            }
+        }
+
+        if cfg!(debug_assertions) {
+            self.check();
+        }
+    }
+
+    //let the search look up positions with few pieces in the tablebases, which must be for this signature
+    pub fn set_tablebases(&mut self, tablebases: std::sync::Arc<tablebase::Tablebases>) {
+        self.signature = self.signature.clone().with_tablebases(tablebases);
+    }
+
+    //let the search look up positions with few pieces in the syzygy tables, which are only for classical chess
+    pub fn set_syzygy(&mut self, syzygy: std::sync::Arc<syzygy::Syzygy>) {
+        self.signature = self.signature.clone().with_syzygy(syzygy);
     }
 
     pub fn get_move_num(&self) -> usize {
@@ -202,19 +289,40 @@ impl Board {
     }
 
     pub fn get_square(&self, sq: Square) -> Option<Piece> {
+        //the bitboards say at once whose piece, if any, is there
+        if let Some(bitboards) = &self.bitboards {
+            return match bitboards.team_at(sq)? {
+                Team::White => self.white_pieces.get(&sq).copied(),
+                Team::Black => self.black_pieces.get(&sq).copied(),
+            };
+        }
         match self.white_pieces.get(&sq) {
             Some(piece) => Some(*piece),
-            None => match self.black_pieces.get(&sq) {
-                Some(piece) => Some(*piece),
-                None => None,
-            },
+            None => self.black_pieces.get(&sq).copied(),
         }
     }
 
+    pub fn get_bitboards(&self) -> Option<&bitboard::Bitboards> {
+        self.bitboards.as_ref()
+    }
+
+    //keep the pieces only in their maps from now on, for the bench to compare against
+    pub fn drop_bitboards(&mut self) {
+        self.bitboards = None;
+    }
+
+    pub fn get_vision(&self, team: Team, sq: Square) -> &Vec<vision::Vision> {
+        self.vision.get(team, sq)
+    }
+
     // pub fn generate_info(&self) -> score::BoardInfo {
     //     score::BoardInfo::new(self)
     // }
 
+    pub fn get_signature(&self) -> &signature::Signature {
+        &self.signature
+    }
+
     pub fn get_turn(&self) -> Team {
         self.turn
     }
@@ -222,36 +330,150 @@ impl Board {
     pub fn get_pieces(&self) -> Vec<(Square, Piece)> {
         let mut pieces = vec![];
         for (sq, piece) in &self.white_pieces {
-            pieces.push((*sq, piece.clone()));
+            pieces.push((*sq, *piece));
         }
         for (sq, piece) in &self.black_pieces {
-            pieces.push((*sq, piece.clone()));
+            pieces.push((*sq, *piece));
         }
         pieces
     }
 
-    fn get_king_square(&self, team: Team) -> Square {
+    pub fn get_pocket(&self, team: Team) -> &BTreeMap<PieceKind, usize> {
+        match team {
+            Team::White => &self.white_pocket,
+            Team::Black => &self.black_pocket,
+        }
+    }
+
+    //pawns go into the pocket without their en passant state
+    pub fn add_to_pocket(&mut self, team: Team, kind: PieceKind) {
+        let kind = match kind {
+            PieceKind::Pawn(..) => PieceKind::Pawn(EnCroissantable::No),
+            kind => kind,
+        };
+        let pocket = match team {
+            Team::White => &mut self.white_pocket,
+            Team::Black => &mut self.black_pocket,
+        };
+        *pocket.entry(kind).or_insert(0) += 1;
+    }
+
+    fn remove_from_pocket(&mut self, team: Team, kind: PieceKind) {
+        let kind = match kind {
+            PieceKind::Pawn(..) => PieceKind::Pawn(EnCroissantable::No),
+            kind => kind,
+        };
+        let pocket = match team {
+            Team::White => &mut self.white_pocket,
+            Team::Black => &mut self.black_pocket,
+        };
+        let count = pocket.get_mut(&kind).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            pocket.remove(&kind);
+        }
+    }
+
+    //only in atomic games can a king go missing, by being blown up
+    pub fn has_king(&self, team: Team) -> bool {
+        match self
+            .get_king_square(team)
+            .and_then(|sq| self.get_square(sq))
+        {
+            Some(piece) => piece.kind == PieceKind::King && piece.team == team,
+            None => false,
+        }
+    }
+
+    pub fn kings_touch(&self) -> bool {
+        match (self.white_king, self.black_king) {
+            (Some(white_king), Some(black_king)) => self
+                .signature
+                .get_king_moves(white_king)
+                .contains(&black_king),
+            _ => false,
+        }
+    }
+
+    //how many checks the team has given so far
+    pub fn count_checks(&self, team: Team) -> usize {
+        let num = self.checks.len();
+        (0..num)
+            .filter(|i| self.checks[*i])
+            .filter(|i| {
+                //the last move was made by the side not to move
+                let mover = match (num - 1 - i) % 2 {
+                    0 => self.turn.flip(),
+                    _ => self.turn,
+                };
+                mover == team
+            })
+            .count()
+    }
+
+    //whether the king of the side to move is attacked, as the vision of the board shows
+    //kings are not royal in antichess, and touching kings in atomic chess cannot take each other
+    pub fn is_in_check(&self) -> bool {
+        let turn = self.turn;
+        if self.signature.is_antichess() || !self.has_king(turn) {
+            return false;
+        }
+        let king_sq = self.get_king_square(turn).unwrap();
+        self.get_vision(turn.flip(), king_sq)
+            .iter()
+            .any(|vis| vis.attacks())
+            && !(self.signature.is_atomic() && self.kings_touch())
+    }
+
+    fn get_king_square(&self, team: Team) -> Option<Square> {
         match team {
             Team::White => self.white_king,
             Team::Black => self.black_king,
         }
     }
 
-    fn good_pieces(&mut self) -> &mut BTreeMap<Square, Piece> {
-        match self.turn {
-            Team::White => &mut self.white_pieces,
-            Team::Black => &mut self.black_pieces,
+    //every piece is put on and taken off the board through these, so that the bitboards and vision keep up
+    fn place(&mut self, sq: Square, piece: Piece) {
+        if let Some(bitboards) = &mut self.bitboards {
+            bitboards.put(sq, piece);
         }
+        match piece.team {
+            Team::White => self.white_pieces.insert(sq, piece),
+            Team::Black => self.black_pieces.insert(sq, piece),
+        };
+        self.update_vision(sq);
     }
 
-    fn bad_pieces(&mut self) -> &mut BTreeMap<Square, Piece> {
-        match self.turn {
-            Team::White => &mut self.black_pieces,
-            Team::Black => &mut self.white_pieces,
+    fn lift(&mut self, sq: Square, team: Team) -> Option<Piece> {
+        let piece = match team {
+            Team::White => self.white_pieces.remove(&sq),
+            Team::Black => self.black_pieces.remove(&sq),
+        };
+        if let (Some(bitboards), Some(piece)) = (&mut self.bitboards, piece) {
+            bitboards.remove(sq, piece);
+        }
+        if piece.is_some() {
+            self.update_vision(sq);
         }
+        piece
+    }
+
+    fn update_vision(&mut self, sq: Square) {
+        //the map is taken out while it looks at the board
+        let mut vision = std::mem::take(&mut self.vision);
+        vision.update(self, sq);
+        self.vision = vision;
     }
 
     fn check(&self) {
+        if let Some(bitboards) = &self.bitboards {
+            assert_eq!(*bitboards, bitboard::Bitboards::new(&self.get_pieces()));
+        }
+        let vision = vision::VisionMap::new(self);
+        for team in [Team::White, Team::Black] {
+            assert_eq!(self.vision.get_all(team), vision.get_all(team));
+        }
+
         let mut white_king = None;
         for (sq, piece) in &self.white_pieces {
             if piece.kind == PieceKind::King {
@@ -259,7 +481,14 @@ impl Board {
                 white_king = Some(sq);
             }
         }
-        assert_eq!(white_king.unwrap(), &self.white_king);
+        match white_king {
+            Some(sq) => assert_eq!(Some(*sq), self.white_king),
+            None => assert!(
+                self.white_king.is_none()
+                    || self.signature.is_atomic()
+                    || self.signature.is_antichess()
+            ),
+        }
 
         let mut black_king = None;
         for (sq, piece) in &self.black_pieces {
@@ -268,7 +497,14 @@ impl Board {
                 black_king = Some(sq);
             }
         }
-        assert_eq!(black_king.unwrap(), &self.black_king);
+        match black_king {
+            Some(sq) => assert_eq!(Some(*sq), self.black_king),
+            None => assert!(
+                self.black_king.is_none()
+                    || self.signature.is_atomic()
+                    || self.signature.is_antichess()
+            ),
+        }
     }
 
     pub fn make_move(&mut self, m: Move) {
@@ -283,29 +519,49 @@ impl Board {
                 debug_assert_eq!(from_piece.team, self.turn);
                 debug_assert_eq!(to_piece.team, self.turn);
                 debug_assert_eq!(self.get_square(*from_sq), Some(*from_piece));
-                self.good_pieces().remove(&from_sq);
+                self.lift(*from_sq, self.turn);
                 match victim_opt {
                     Some(victim) => {
                         debug_assert_eq!(self.get_square(*to_sq), Some(*victim));
                         debug_assert_ne!(victim.team, self.turn);
-                        self.bad_pieces().remove(&to_sq);
+                        self.lift(*to_sq, self.turn.flip());
                     }
                     None => {
                         debug_assert!(self.get_square(*to_sq).is_none());
                     }
                 }
-                self.good_pieces().insert(*to_sq, *to_piece);
+                self.place(*to_sq, *to_piece);
                 if from_piece.kind == PieceKind::King {
                     debug_assert!(to_piece.kind == PieceKind::King);
                     match from_piece.team {
-                        Team::White => self.white_king = *to_sq,
-                        Team::Black => self.black_king = *to_sq,
+                        Team::White => self.white_king = Some(*to_sq),
+                        Team::Black => self.black_king = Some(*to_sq),
                     }
                 }
+                if self.signature.has_drops() {
+                    let mover_promoted = self.promoted.remove(from_sq);
+                    let victim_promoted = self.promoted.remove(to_sq);
+                    if mover_promoted
+                        || (matches!(from_piece.kind, PieceKind::Pawn(..))
+                            && !matches!(to_piece.kind, PieceKind::Pawn(..)))
+                    {
+                        self.promoted.insert(*to_sq);
+                    }
+                    if let Some(victim) = victim_opt {
+                        self.add_to_pocket(
+                            self.turn,
+                            match victim_promoted {
+                                true => PieceKind::Pawn(EnCroissantable::No),
+                                false => victim.kind,
+                            },
+                        );
+                    }
+                    self.promoted_victims.push(victim_promoted);
+                }
             }
             Move::Castle {
                 king_from,
-                king_through,
+                castle,
                 king_to,
                 king_piece,
                 rook_from,
@@ -313,24 +569,26 @@ impl Board {
                 rook_piece,
             } => {
                 debug_assert_eq!(self.get_square(*king_from), Some(*king_piece));
-                for sq in king_through {
-                    debug_assert_eq!(self.get_square(*sq), None);
-                }
-                debug_assert_eq!(self.get_square(*king_to), None);
                 debug_assert_eq!(self.get_square(*rook_from), Some(*rook_piece));
-                debug_assert_eq!(self.get_square(*rook_to), None);
+                //in chess960 the king and rook may pass over or land on each others starting squares
+                let king_through = &self.signature.get_castle(self.turn, *castle).not_chcked;
+                for sq in king_through.iter().chain([king_to, rook_to]) {
+                    debug_assert!(
+                        self.get_square(*sq).is_none() || sq == king_from || sq == rook_from
+                    );
+                }
                 debug_assert_eq!(king_piece.team, self.turn);
                 debug_assert_eq!(rook_piece.team, self.turn);
 
-                self.good_pieces().remove(&king_from);
-                self.good_pieces().remove(&rook_from);
-                self.good_pieces().insert(*rook_to, rook_piece.moved());
-                self.good_pieces().insert(*king_to, king_piece.moved());
+                self.lift(*king_from, self.turn);
+                self.lift(*rook_from, self.turn);
+                self.place(*rook_to, rook_piece.moved());
+                self.place(*king_to, king_piece.moved());
 
                 if king_piece.kind == PieceKind::King {
                     match king_piece.team {
-                        Team::White => self.white_king = *king_to,
-                        Team::Black => self.black_king = *king_to,
+                        Team::White => self.white_king = Some(*king_to),
+                        Team::Black => self.black_king = Some(*king_to),
                     }
                 }
             }
@@ -344,24 +602,89 @@ impl Board {
                 debug_assert_eq!(self.get_square(*pawn_from), Some(*pawn));
                 debug_assert_eq!(self.get_square(*pawn_to), None);
                 debug_assert_eq!(self.get_square(*victim_sq), Some(*victim));
-                self.good_pieces().remove(&pawn_from);
-                self.good_pieces().insert(*pawn_to, pawn.moved());
-                self.bad_pieces().remove(victim_sq);
+                self.lift(*pawn_from, self.turn);
+                self.place(*pawn_to, pawn.moved());
+                self.lift(*victim_sq, self.turn.flip());
+                if self.signature.has_drops() {
+                    self.add_to_pocket(self.turn, victim.kind);
+                }
             }
+            Move::Drop { piece, to_sq } => {
+                debug_assert_eq!(piece.team, self.turn);
+                debug_assert!(self.get_square(*to_sq).is_none());
+                self.remove_from_pocket(self.turn, piece.kind);
+                self.place(*to_sq, *piece);
+            }
+        }
+
+        if self.signature.is_atomic() {
+            let centre = match &m {
+                Move::Standard {
+                    victim: Some(_),
+                    to_sq,
+                    ..
+                } => Some(*to_sq),
+                Move::EnCroissant { pawn_to, .. } => Some(*pawn_to),
+                _ => None,
+            };
+            //a capture destroys the capturing piece along with every piece but a pawn around it
+            let mut exploded = vec![];
+            if let Some(centre) = centre {
+                exploded.push((centre, self.lift(centre, self.turn).unwrap()));
+                for sq in self.signature.get_king_moves(centre).clone() {
+                    if let Some(piece) = self.get_square(sq) {
+                        if !matches!(piece.kind, PieceKind::Pawn(..)) {
+                            self.lift(sq, piece.team);
+                            exploded.push((sq, piece));
+                        }
+                    }
+                }
+            }
+            self.exploded.push(exploded);
         }
 
         self.turn = self.turn.flip();
         self.moves.push(m);
+        //worked out here rather than by whoever looks at the position next, which may be a node kept from an earlier search
+        self.checks.push(self.is_in_check());
 
         if cfg!(debug_assertions) {
             self.check();
         }
     }
 
+    //pass the turn without moving, for null move pruning
+    //it must be taken back with unmake_null_move before any real move is taken back
+    pub fn make_null_move(&mut self) {
+        self.nulls.push(self.moves.len());
+        self.turn = self.turn.flip();
+        self.checks.push(false);
+    }
+
+    pub fn unmake_null_move(&mut self) {
+        debug_assert_eq!(self.nulls.last(), Some(&self.moves.len()));
+        self.nulls.pop();
+        self.turn = self.turn.flip();
+        self.checks.pop();
+    }
+
+    //whether the last thing done to the board was a null move
+    pub fn last_was_null(&self) -> bool {
+        self.nulls.last() == Some(&self.moves.len())
+    }
+
+    #[allow(clippy::result_unit_err)]
     pub fn unmake_move(&mut self) -> Result<(), ()> {
         match self.moves.pop() {
             Some(m) => {
                 self.turn = self.turn.flip();
+                self.checks.pop();
+
+                if self.signature.is_atomic() {
+                    for (sq, piece) in self.exploded.pop().unwrap() {
+                        self.place(sq, piece);
+                    }
+                }
 
                 match m {
                     Move::Standard {
@@ -374,26 +697,43 @@ impl Board {
                         debug_assert_eq!(from_piece.team, self.turn);
                         debug_assert_eq!(to_piece.team, self.turn);
                         debug_assert!(self.get_square(from_sq).is_none());
-                        self.good_pieces().remove(&to_sq);
-                        match victim_opt {
-                            Some(victim) => {
-                                debug_assert_ne!(victim.team, self.turn);
-                                self.bad_pieces().insert(to_sq, victim);
+                        if self.signature.has_drops() {
+                            let victim_promoted = self.promoted_victims.pop().unwrap();
+                            if self.promoted.remove(&to_sq)
+                                && !matches!(from_piece.kind, PieceKind::Pawn(..))
+                            {
+                                self.promoted.insert(from_sq);
                             }
-                            None => {}
+                            if let Some(victim) = victim_opt {
+                                if victim_promoted {
+                                    self.promoted.insert(to_sq);
+                                }
+                                self.remove_from_pocket(
+                                    self.turn,
+                                    match victim_promoted {
+                                        true => PieceKind::Pawn(EnCroissantable::No),
+                                        false => victim.kind,
+                                    },
+                                );
+                            }
+                        }
+                        self.lift(to_sq, self.turn);
+                        if let Some(victim) = victim_opt {
+                            debug_assert_ne!(victim.team, self.turn);
+                            self.place(to_sq, victim);
                         }
-                        self.good_pieces().insert(from_sq, from_piece);
+                        self.place(from_sq, from_piece);
                         if from_piece.kind == PieceKind::King {
                             debug_assert!(to_piece.kind == PieceKind::King);
                             match from_piece.team {
-                                Team::White => self.white_king = from_sq,
-                                Team::Black => self.black_king = from_sq,
+                                Team::White => self.white_king = Some(from_sq),
+                                Team::Black => self.black_king = Some(from_sq),
                             }
                         }
                     }
                     Move::Castle {
                         king_from,
-                        king_through: _,
+                        castle: _,
                         king_to,
                         king_piece,
                         rook_from,
@@ -401,22 +741,24 @@ impl Board {
                         rook_piece,
                     } => {
                         debug_assert_eq!(self.get_square(king_to), Some(king_piece.moved()));
-                        debug_assert_eq!(self.get_square(king_from), None);
-
                         debug_assert_eq!(self.get_square(rook_to), Some(rook_piece.moved()));
-                        debug_assert_eq!(self.get_square(rook_from), None);
+                        for sq in [king_from, rook_from] {
+                            debug_assert!(
+                                self.get_square(sq).is_none() || sq == king_to || sq == rook_to
+                            );
+                        }
                         debug_assert_eq!(king_piece.team, self.turn);
                         debug_assert_eq!(rook_piece.team, self.turn);
 
-                        self.good_pieces().remove(&rook_to);
-                        self.good_pieces().remove(&king_to);
-                        self.good_pieces().insert(rook_from, rook_piece);
-                        self.good_pieces().insert(king_from, king_piece);
+                        self.lift(rook_to, self.turn);
+                        self.lift(king_to, self.turn);
+                        self.place(rook_from, rook_piece);
+                        self.place(king_from, king_piece);
 
                         if king_piece.kind == PieceKind::King {
                             match king_piece.team {
-                                Team::White => self.white_king = king_from,
-                                Team::Black => self.black_king = king_from,
+                                Team::White => self.white_king = Some(king_from),
+                                Team::Black => self.black_king = Some(king_from),
                             }
                         }
                     }
@@ -430,9 +772,17 @@ impl Board {
                         debug_assert_eq!(self.get_square(pawn_from), None);
                         debug_assert_eq!(self.get_square(pawn_to), Some(pawn.moved()));
                         debug_assert_eq!(self.get_square(victim_sq), None);
-                        self.good_pieces().remove(&pawn_to);
-                        self.good_pieces().insert(pawn_from, pawn);
-                        self.bad_pieces().insert(victim_sq, victim);
+                        self.lift(pawn_to, self.turn);
+                        self.place(pawn_from, pawn);
+                        self.place(victim_sq, victim);
+                        if self.signature.has_drops() {
+                            self.remove_from_pocket(self.turn, victim.kind);
+                        }
+                    }
+                    Move::Drop { piece, to_sq } => {
+                        debug_assert_eq!(self.get_square(to_sq), Some(piece));
+                        self.lift(to_sq, self.turn);
+                        self.add_to_pocket(self.turn, piece.kind);
                     }
                 }
 
@@ -0,0 +1,228 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+use super::ai::MoveData;
+use super::evaluation::EvalParams;
+use super::signature::Signature;
+use super::*;
+
+//what is remembered between the nodes of a search to try the best moves first
+//it is shared by every thread searching the tree, so the history is atomic and each ply's killers have their own lock
+//
+//moves are tried in this order
+//  the best move found at the node by the previous search
+//  captures which do not lose material, most valuable victim first and then least valuable attacker
+//  promotions
+//  killers, quiet moves which caused a cutoff at the same ply elsewhere in the tree
+//  other quiet moves, by how often moving that piece to that square has caused a cutoff
+//  captures which lose material
+//the first part of the sort key of each kind of move
+pub const BEST: u8 = 6;
+pub const GOOD_CAPTURE: u8 = 5;
+pub const PROMOTION: u8 = 4;
+pub const KILLER: u8 = 3;
+pub const QUIET: u8 = 2;
+pub const BAD_CAPTURE: u8 = 1;
+
+#[derive(Debug)]
+pub struct MoveOrdering {
+    num: usize,
+    kinds: usize,
+    history: Vec<AtomicI64>,
+    killers: Vec<Mutex<[Option<Move>; 2]>>,
+}
+
+impl MoveOrdering {
+    pub fn new(signature: &Signature) -> Self {
+        let num = signature.num();
+        let kinds = 9 + signature.num_custom_pieces();
+        Self {
+            num,
+            kinds,
+            history: (0..2 * kinds * num).map(|_| AtomicI64::new(0)).collect(),
+            killers: vec![],
+        }
+    }
+
+    //forget the killers of the last search, which were for other plies, and age the history
+    pub fn new_search(&mut self, max_ply: usize) {
+        self.killers = (0..=max_ply).map(|_| Mutex::new([None, None])).collect();
+        for entry in &self.history {
+            entry.store(entry.load(Ordering::Relaxed) / 2, Ordering::Relaxed);
+        }
+    }
+
+    fn history_idx(&self, piece: &Piece, to_sq: Square) -> usize {
+        let kind = match piece.kind {
+            PieceKind::Pawn(..) => 0,
+            PieceKind::Grasshopper => 1,
+            PieceKind::Rook => 2,
+            PieceKind::Knight => 3,
+            PieceKind::Bishop => 4,
+            PieceKind::Queen => 5,
+            PieceKind::Archbishop => 6,
+            PieceKind::Chancellor => 7,
+            PieceKind::King => 8,
+            PieceKind::Custom(idx) => 9 + idx,
+        };
+        let team = match piece.team {
+            Team::White => 0,
+            Team::Black => 1,
+        };
+        (team * self.kinds + kind) * self.num + to_sq.idx
+    }
+
+    //the piece which moves and where to, for moves which are neither captures nor promotions
+    fn quiet(mv: &Move) -> Option<(&Piece, Square)> {
+        match mv {
+            Move::Standard {
+                from_piece,
+                to_piece,
+                victim: None,
+                to_sq,
+                ..
+            } => match (from_piece.kind, to_piece.kind) {
+                (PieceKind::Pawn(..), PieceKind::Pawn(..)) => Some((from_piece, *to_sq)),
+                (PieceKind::Pawn(..), _) => None,
+                _ => Some((from_piece, *to_sq)),
+            },
+            Move::Castle {
+                king_piece,
+                king_to,
+                ..
+            } => Some((king_piece, *king_to)),
+            Move::Drop { piece, to_sq } => Some((piece, *to_sq)),
+            _ => None,
+        }
+    }
+
+    pub fn get_history(&self, mv: &Move) -> i64 {
+        match Self::quiet(mv) {
+            Some((piece, to_sq)) => {
+                self.history[self.history_idx(piece, to_sq)].load(Ordering::Relaxed)
+            }
+            None => 0,
+        }
+    }
+
+    //the slot of the move among the killers of the ply, if it is one
+    fn killer_slot(&self, ply: usize, mv: &Move) -> Option<usize> {
+        let killers = self.killers.get(ply)?.lock().unwrap();
+        killers
+            .iter()
+            .position(|killer| killer.as_ref() == Some(mv))
+    }
+
+    //remember a quiet move which caused a beta cutoff with the given depth left to search
+    pub fn cutoff(&self, ply: usize, mv: &Move, depth_left: usize) {
+        if let Some((piece, to_sq)) = Self::quiet(mv) {
+            let bonus = (depth_left * depth_left) as i64 + 1;
+            self.history[self.history_idx(piece, to_sq)].fetch_add(bonus, Ordering::Relaxed);
+            if let Some(killers) = self.killers.get(ply) {
+                let mut killers = killers.lock().unwrap();
+                if killers[0].as_ref() != Some(mv) {
+                    killers[1] = killers[0].take();
+                    killers[0] = Some(*mv);
+                }
+            }
+        }
+    }
+
+    //a key to sort moves by, greatest first
+    pub fn key(
+        &self,
+        board: &Board,
+        params: &EvalParams,
+        ply: usize,
+        move_data: &MoveData,
+        is_best: bool,
+    ) -> (u8, i64) {
+        let worth = |kind: PieceKind| params.get_worth(&board.signature, kind).unwrap_or(1000);
+        let mv = move_data.get_move();
+        if is_best {
+            return (BEST, 0);
+        }
+        match mv {
+            Move::Standard {
+                from_piece,
+                victim: Some(victim),
+                ..
+            } => match move_data.get_see(board, params) {
+                Some(see) if see < 0 => (BAD_CAPTURE, see),
+                _ => (
+                    GOOD_CAPTURE,
+                    100 * worth(victim.kind) - worth(from_piece.kind),
+                ),
+            },
+            Move::EnCroissant { pawn, victim, .. } => {
+                (GOOD_CAPTURE, 100 * worth(victim.kind) - worth(pawn.kind))
+            }
+            Move::Standard { to_piece, .. } if Self::quiet(mv).is_none() => {
+                (PROMOTION, worth(to_piece.kind))
+            }
+            _ => match self.killer_slot(ply, mv) {
+                Some(slot) => (KILLER, -(slot as i64)),
+                None => (QUIET, self.get_history(mv)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::fen::from_fen;
+    use crate::generic::board_data::BoardData;
+    use crate::generic::evaluation::DEFAULT_PARAMS;
+
+    #[test]
+    fn test_ordering() {
+        //the b7 pawn may promote, the queen may take an undefended bishop or a defended knight
+        let mut board = from_fen("3rk3/1P1p4/2n5/6b1/8/8/8/2Q1K3 w - - 0 1").unwrap();
+        let data = BoardData::new(&mut board);
+        let mut ordering = MoveOrdering::new(&board.signature);
+        ordering.new_search(4);
+        let find = |f: &dyn Fn(&Move) -> bool| {
+            data.get_moves_data()
+                .iter()
+                .find(|move_data| f(move_data.get_move()))
+                .unwrap()
+        };
+        let key = |ordering: &MoveOrdering, move_data| {
+            ordering.key(&board, &DEFAULT_PARAMS, 2, move_data, false)
+        };
+        let to =
+            |idx| move |mv: &Move| matches!(mv, Move::Standard { to_sq, .. } if to_sq.idx == idx);
+
+        let win = find(&to(38)); //Qxg5 is safe
+        let lose = find(&to(42)); //Qxc6 is taken back by the pawn
+        let promote = find(
+            &|mv| matches!(mv, Move::Standard { to_sq, to_piece, victim: None, .. } if to_sq.idx == 57 && to_piece.kind == PieceKind::Queen),
+        );
+        let quiet = find(&to(10)); //Qc2
+        let other = find(&to(18)); //Qc3
+
+        assert!(key(&ordering, win) > key(&ordering, promote));
+        assert!(key(&ordering, promote) > key(&ordering, quiet));
+        assert!(key(&ordering, quiet) > key(&ordering, lose));
+        assert_eq!(key(&ordering, quiet), key(&ordering, other));
+
+        //a quiet move which caused a cutoff becomes a killer at its ply and earns history everywhere
+        ordering.cutoff(2, quiet.get_move(), 3);
+        assert_eq!(key(&ordering, quiet), (KILLER, 0));
+        assert!(ordering.get_history(quiet.get_move()) > 0);
+        assert_eq!(
+            ordering.key(&board, &DEFAULT_PARAMS, 1, quiet, false),
+            (QUIET, 10)
+        );
+        assert!(key(&ordering, quiet) > key(&ordering, other));
+        assert_eq!(
+            ordering.key(&board, &DEFAULT_PARAMS, 1, other, true),
+            (BEST, 0)
+        );
+
+        //the history fades from one search to the next
+        ordering.new_search(4);
+        assert_eq!(ordering.get_history(quiet.get_move()), 5);
+    }
+}
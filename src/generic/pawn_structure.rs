@@ -0,0 +1,215 @@
+use super::evaluation::EvalParams;
+use super::signature::Signature;
+use super::*;
+use std::cell::RefCell;
+
+//there are no files on a general board, so the usual notions are phrased in terms of where pawns may go
+//  the span of a pawn is every square it may reach by moving forward, ignoring double steps
+//  its line is its span together with the span of a pawn of the other team on its square
+//  pawns are neighbours when one could take onto the other's line
+
+thread_local! {
+    //what was worked out about the pawns in the last evaluation on this thread, whose buffers are filled again
+    //rather than allocated for every position
+    static SPARE_PAWNS: RefCell<Vec<PawnInfo>> = const { RefCell::new(vec![]) };
+}
+
+//the span is added to the end of the buffer, going on from each square in it in turn
+fn span(signature: &Signature, team: Team, sq: Square, span: &mut Vec<Square>) {
+    let start = span.len();
+    let mut next = start;
+    let mut from = sq;
+    loop {
+        for (to, _double) in signature.get_pawn_moves(from, team) {
+            if *to != sq && !span[start..].contains(to) {
+                span.push(*to);
+            }
+        }
+        match span.get(next) {
+            Some(sq) => from = *sq,
+            None => return,
+        }
+        next += 1;
+    }
+}
+
+struct PawnInfo {
+    sq: Square,
+    span: Vec<Square>,
+    line: Vec<Square>, //kept only for its buffer
+    //squares a pawn of either team could take onto from the line, and so where neighbouring pawns stand
+    neighbours: HashSet<Square>,
+    //the squares this pawn takes on now or once it has advanced
+    reach: HashSet<Square>,
+}
+
+impl PawnInfo {
+    fn new() -> Self {
+        Self {
+            sq: Square { idx: 0 },
+            span: vec![],
+            line: vec![],
+            neighbours: HashSet::new(),
+            reach: HashSet::new(),
+        }
+    }
+
+    fn set(&mut self, signature: &Signature, team: Team, sq: Square) {
+        self.sq = sq;
+        self.span.clear();
+        span(signature, team, sq, &mut self.span);
+        self.line.clear();
+        self.line.push(sq);
+        self.line.extend_from_slice(&self.span);
+        span(signature, team.flip(), sq, &mut self.line);
+        self.neighbours.clear();
+        for x in &self.line {
+            self.neighbours.extend(
+                signature
+                    .get_pawn_takes(*x, Team::White)
+                    .iter()
+                    .chain(signature.get_pawn_takes(*x, Team::Black))
+                    .filter(|x| !self.line.contains(x)),
+            );
+        }
+        self.reach.clear();
+        for x in std::iter::once(&sq).chain(&self.span) {
+            self.reach
+                .extend(signature.get_pawn_takes(*x, team).iter().cloned());
+        }
+    }
+}
+
+//middlegame and endgame scores for the pawn formation, positive in favour of white
+pub fn evaluate(board: &Board, params: &EvalParams) -> (i64, i64) {
+    SPARE_PAWNS.with_borrow_mut(|pawns| {
+        //white's pawns and then black's
+        let signature = &board.signature;
+        let mut num = 0;
+        let mut num_white = 0;
+        for (sq, piece) in board.white_pieces.iter().chain(&board.black_pieces) {
+            if let PieceKind::Pawn(..) = piece.kind {
+                if num == pawns.len() {
+                    pawns.push(PawnInfo::new());
+                }
+                pawns[num].set(signature, piece.team, *sq);
+                num += 1;
+                if piece.team == Team::White {
+                    num_white = num;
+                }
+            }
+        }
+        let (white, black) = pawns[..num].split_at(num_white);
+        score(signature, params, white, black)
+    })
+}
+
+//the scores of the pawns of each team, found before
+fn score(
+    signature: &Signature,
+    params: &EvalParams,
+    white: &[PawnInfo],
+    black: &[PawnInfo],
+) -> (i64, i64) {
+    let (mut mg, mut eg) = (0, 0);
+    for (team, own, enemy) in [(Team::White, white, black), (Team::Black, black, white)] {
+        let sign = match team {
+            Team::White => 1,
+            Team::Black => -1,
+        };
+        for pawn in own.iter() {
+            let others = || own.iter().filter(|other| other.sq != pawn.sq);
+
+            //another of our pawns is in the way
+            if others().any(|other| pawn.span.contains(&other.sq)) {
+                mg -= sign * params.doubled;
+                eg -= sign * params.doubled;
+            }
+
+            //no enemy pawn stands in the way or could take it on the way to promoting
+            if enemy.iter().all(|other| {
+                other.sq != pawn.sq
+                    && !pawn.span.contains(&other.sq)
+                    && !other.span.contains(&pawn.sq)
+                    && !other.reach.contains(&pawn.sq)
+                    && !pawn.span.iter().any(|sq| other.reach.contains(sq))
+            }) {
+                mg += sign * params.passed;
+                eg += sign * params.passed * 2;
+            }
+
+            //no pawn of ours could ever defend it
+            let isolated = !others().any(|other| pawn.neighbours.contains(&other.sq));
+            if isolated {
+                mg -= sign * params.isolated;
+                eg -= sign * params.isolated;
+                continue;
+            }
+
+            //it cannot advance without being taken, and no pawn of ours could come to defend it
+            let stops = signature.get_pawn_moves(pawn.sq, team);
+            let attacked = |stop: &Square| {
+                enemy.iter().any(|other| {
+                    signature
+                        .get_pawn_takes(other.sq, team.flip())
+                        .contains(stop)
+                })
+            };
+            let supportable = |stop: &Square| others().any(|other| other.reach.contains(stop));
+            if !stops.is_empty()
+                && stops
+                    .iter()
+                    .all(|(stop, _double)| attacked(stop) && !supportable(stop))
+            {
+                mg -= sign * params.backward;
+                eg -= sign * params.backward;
+            }
+        }
+    }
+    (mg, eg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::fen::from_fen;
+    use crate::generic::evaluation::DEFAULT_PARAMS;
+
+    #[test]
+    fn test_pawn_structure() {
+        let only = |doubled, isolated, backward, passed| EvalParams {
+            doubled,
+            isolated,
+            backward,
+            passed,
+            ..DEFAULT_PARAMS
+        };
+        let evaluate = |fen, params| evaluate(&from_fen(fen).unwrap(), &params);
+
+        //two white pawns on the a file with nothing to stop them
+        let fen = "4k3/8/8/8/8/P7/P7/4K3 w - - 0 1";
+        assert_eq!(evaluate(fen, only(1, 0, 0, 0)), (-1, -1));
+        assert_eq!(evaluate(fen, only(0, 1, 0, 0)), (-2, -2));
+        assert_eq!(evaluate(fen, only(0, 0, 0, 1)), (2, 4));
+
+        //a pawn on the next file stops a passed pawn, and is stopped by it
+        assert_eq!(
+            evaluate("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1", only(0, 0, 0, 1)),
+            (1, 2)
+        );
+        assert_eq!(
+            evaluate("4k3/3p4/8/8/4P3/8/8/4K3 w - - 0 1", only(0, 0, 0, 1)),
+            (0, 0)
+        );
+
+        //d3 cannot advance past c5 and e4 is already past defending d4
+        assert_eq!(
+            evaluate("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1", only(0, 0, 1, 0)),
+            (-1, -1)
+        );
+        assert_eq!(
+            evaluate("4k3/8/8/2p5/4P3/3P4/4P3/4K3 w - - 0 1", only(0, 0, 1, 0)),
+            (0, 0)
+        );
+    }
+}
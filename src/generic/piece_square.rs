@@ -0,0 +1,171 @@
+use super::signature::Signature;
+use super::*;
+
+//bonuses for each square a kind of piece may stand on, in hundredths of a pawn
+//there are separate values for the middlegame and the endgame, and for each team so no board needs mirroring
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub white_mg: Vec<i64>,
+    pub white_eg: Vec<i64>,
+    pub black_mg: Vec<i64>,
+    pub black_eg: Vec<i64>,
+}
+
+impl Table {
+    fn zero(num: usize) -> Self {
+        Self {
+            white_mg: vec![0; num],
+            white_eg: vec![0; num],
+            black_mg: vec![0; num],
+            black_eg: vec![0; num],
+        }
+    }
+
+    //the same values for both teams, as for a board symmetric about every square
+    fn symmetric(mg: Vec<i64>, eg: Vec<i64>) -> Self {
+        Self {
+            white_mg: mg.clone(),
+            white_eg: eg.clone(),
+            black_mg: mg,
+            black_eg: eg,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PieceSquareTables {
+    tables: BTreeMap<PieceKind, Table>,
+    default: Table, //for custom pieces and anything else without its own table
+}
+
+//shift the values to average zero and scale them so the largest is the given amplitude
+fn spread(values: &[i64], amplitude: i64) -> Vec<i64> {
+    if values.is_empty() {
+        return vec![];
+    }
+    let mean = values.iter().sum::<i64>() / values.len() as i64;
+    let max_dev = values.iter().map(|v| (v - mean).abs()).max().unwrap();
+    match max_dev {
+        0 => vec![0; values.len()],
+        _ => values
+            .iter()
+            .map(|v| (v - mean) * amplitude / max_dev)
+            .collect(),
+    }
+}
+
+impl PieceSquareTables {
+    pub(crate) fn empty() -> Self {
+        Self {
+            tables: BTreeMap::new(),
+            default: Table::zero(0),
+        }
+    }
+
+    //tables for any board, from how many squares each piece reaches on it when empty
+    //pieces like to be where they see the most, except the king which hides in the middlegame
+    //pawns like to be close to promoting, much more so in the endgame
+    pub fn generate(signature: &Signature) -> Self {
+        let num = signature.num();
+        let squares = || (0..num).map(|idx| Square { idx });
+        let flat = squares()
+            .map(|sq| {
+                signature
+                    .get_flat_slides(sq)
+                    .iter()
+                    .map(|slide| slide.len() as i64)
+                    .sum::<i64>()
+            })
+            .collect::<Vec<_>>();
+        let diag = squares()
+            .map(|sq| {
+                signature
+                    .get_diag_slides(sq)
+                    .iter()
+                    .map(|slide| slide.len() as i64)
+                    .sum::<i64>()
+            })
+            .collect::<Vec<_>>();
+        let knight = squares()
+            .map(|sq| signature.get_knight_moves(sq).len() as i64)
+            .collect::<Vec<_>>();
+        let king = squares()
+            .map(|sq| signature.get_king_moves(sq).len() as i64)
+            .collect::<Vec<_>>();
+        let sum = |a: &[i64], b: &[i64]| a.iter().zip(b).map(|(x, y)| x + y).collect::<Vec<_>>();
+        let queen = sum(&flat, &diag);
+
+        let mobility = |reach: &[i64], mg: i64, eg: i64| {
+            Table::symmetric(spread(reach, mg), spread(reach, eg))
+        };
+
+        let mut tables = BTreeMap::new();
+        tables.insert(PieceKind::Rook, mobility(&flat, 10, 10));
+        tables.insert(PieceKind::Bishop, mobility(&diag, 20, 20));
+        tables.insert(PieceKind::Knight, mobility(&knight, 40, 30));
+        tables.insert(PieceKind::Queen, mobility(&queen, 10, 20));
+        tables.insert(PieceKind::Grasshopper, mobility(&queen, 10, 10));
+        tables.insert(
+            PieceKind::Archbishop,
+            mobility(&sum(&diag, &knight), 30, 30),
+        );
+        tables.insert(
+            PieceKind::Chancellor,
+            mobility(&sum(&flat, &knight), 20, 20),
+        );
+        tables.insert(
+            PieceKind::King,
+            Table::symmetric(spread(&king, -20), spread(&queen, 40)),
+        );
+
+        let mut pawn = Table::zero(num);
+        for (team, mg, eg) in [
+            (Team::White, &mut pawn.white_mg, &mut pawn.white_eg),
+            (Team::Black, &mut pawn.black_mg, &mut pawn.black_eg),
+        ] {
+            let distances = squares()
+                .map(|sq| signature.get_pawn_promotion_distance(sq, team))
+                .collect::<Vec<_>>();
+            let furthest = distances.iter().flatten().max().copied().unwrap_or(0) as i64;
+            for (idx, distance) in distances.into_iter().enumerate() {
+                if let Some(distance) = distance {
+                    let advance = (furthest - distance as i64).max(0);
+                    mg[idx] = 5 * advance;
+                    eg[idx] = 15 * advance;
+                }
+            }
+        }
+        tables.insert(PieceKind::Pawn(EnCroissantable::No), pawn);
+
+        Self {
+            tables,
+            default: mobility(&queen, 10, 10),
+        }
+    }
+
+    //replace the table for a kind of piece
+    pub fn set(&mut self, kind: PieceKind, table: Table) {
+        self.tables.insert(Self::key(kind), table);
+    }
+
+    pub fn get(&self, kind: PieceKind) -> &Table {
+        self.tables.get(&Self::key(kind)).unwrap_or(&self.default)
+    }
+
+    //the middlegame and endgame bonuses for a piece on a square
+    pub fn value(&self, piece: &Piece, sq: Square) -> (i64, i64) {
+        let table = self.get(piece.kind);
+        match piece.team {
+            Team::White => (table.white_mg[sq.idx], table.white_eg[sq.idx]),
+            Team::Black => (table.black_mg[sq.idx], table.black_eg[sq.idx]),
+        }
+    }
+
+    //pawns share a table whatever their en passant state
+    fn key(kind: PieceKind) -> PieceKind {
+        match kind {
+            PieceKind::Pawn(..) => PieceKind::Pawn(EnCroissantable::No),
+            kind => kind,
+        }
+    }
+}
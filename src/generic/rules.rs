@@ -0,0 +1,102 @@
+use super::score::Score;
+use super::*;
+
+//extra ways for a game to end, consulted by BoardData alongside checkmate and stalemate
+pub trait Rules: std::fmt::Debug + Send + Sync {
+    //the result for the side to move if the game is already over
+    fn terminal(&self, board: &Board, is_check: bool) -> Option<Score>;
+
+    //an adjustment to the heuristic evaluation, positive in favour of white
+    fn evaluate(&self, _board: &Board) -> i64 {
+        0
+    }
+
+    //whether the result depends on how the position came about and not only on where the pieces are
+    fn depends_on_history(&self) -> bool {
+        false
+    }
+}
+
+fn signed(team: Team, score: i64) -> i64 {
+    match team {
+        Team::White => score,
+        Team::Black => -score,
+    }
+}
+
+//a king which reaches the hill wins
+#[derive(Debug, Clone)]
+pub struct KingOfTheHill {
+    pub hill: Vec<Square>,
+}
+
+impl Rules for KingOfTheHill {
+    fn terminal(&self, board: &Board, _is_check: bool) -> Option<Score> {
+        let turn = board.get_turn();
+        match board.get_king_square(turn.flip()) {
+            Some(sq) if board.has_king(turn.flip()) && self.hill.contains(&sq) => {
+                Some(Score::Lost(board.get_move_num()))
+            }
+            _ => None,
+        }
+    }
+
+    //reward kings for being close to the hill
+    fn evaluate(&self, board: &Board) -> i64 {
+        let mut score = 0;
+        for team in [Team::White, Team::Black] {
+            if let Some(sq) = board.get_king_square(team) {
+                if self
+                    .hill
+                    .iter()
+                    .any(|hill_sq| board.signature.get_king_moves(*hill_sq).contains(&sq))
+                {
+                    score += signed(team, 1500);
+                }
+            }
+        }
+        score
+    }
+}
+
+//giving check a number of times wins
+#[derive(Debug, Clone)]
+pub struct ThreeCheck {
+    pub checks: usize,
+}
+
+impl Rules for ThreeCheck {
+    fn terminal(&self, board: &Board, _is_check: bool) -> Option<Score> {
+        match board.count_checks(board.get_turn().flip()) >= self.checks {
+            true => Some(Score::Lost(board.get_move_num())),
+            false => None,
+        }
+    }
+
+    fn evaluate(&self, board: &Board) -> i64 {
+        signed(Team::White, 2500 * board.count_checks(Team::White) as i64)
+            + signed(Team::Black, 2500 * board.count_checks(Team::Black) as i64)
+    }
+
+    fn depends_on_history(&self) -> bool {
+        true
+    }
+}
+
+//a side without a king, like the pawns in horde, loses once all its pieces are captured
+#[derive(Debug, Clone)]
+pub struct Horde {}
+
+impl Rules for Horde {
+    fn terminal(&self, board: &Board, _is_check: bool) -> Option<Score> {
+        let turn = board.get_turn();
+        match board
+            .get_pieces()
+            .iter()
+            .any(|(_sq, piece)| piece.team == turn)
+        {
+            true => None,
+            false => Some(Score::Lost(board.get_move_num())),
+        }
+    }
+}
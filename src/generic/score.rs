@@ -1,6 +1,9 @@
 use std::{
     ops::Neg,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -141,9 +144,65 @@ impl UpperBound {
     }
 }
 
+//an upper bound which many threads may read and tighten without locking
+//the bound is packed into one integer, the kind of bound in the low bits and its value in the rest
+#[derive(Debug)]
+struct AtomicBound(AtomicI64);
+
+impl AtomicBound {
+    fn new(bound: UpperBound) -> Self {
+        Self(AtomicI64::new(Self::pack(bound)))
+    }
+
+    fn pack(bound: UpperBound) -> i64 {
+        let (value, tag) = match bound {
+            UpperBound::Finite(Score::Heuristic(v)) => (v, 0),
+            UpperBound::Finite(Score::Lost(n)) => (n as i64, 1),
+            UpperBound::Finite(Score::Draw(n)) => (n as i64, 2),
+            UpperBound::Finite(Score::Won(n)) => (n as i64, 3),
+            UpperBound::PosInf => (0, 4),
+        };
+        (value << 3) | tag
+    }
+
+    fn unpack(packed: i64) -> UpperBound {
+        let value = packed >> 3;
+        match packed & 7 {
+            0 => UpperBound::Finite(Score::Heuristic(value)),
+            1 => UpperBound::Finite(Score::Lost(value as usize)),
+            2 => UpperBound::Finite(Score::Draw(value as usize)),
+            3 => UpperBound::Finite(Score::Won(value as usize)),
+            _ => UpperBound::PosInf,
+        }
+    }
+
+    fn load(&self) -> UpperBound {
+        Self::unpack(self.0.load(Ordering::Acquire))
+    }
+
+    //lower the bound to the score if that is lower, returning whether it was
+    fn refine(&self, score: Score) -> bool {
+        let mut current = self.0.load(Ordering::Acquire);
+        loop {
+            if !Self::unpack(current).is_improvement(&score) {
+                return false;
+            }
+            match self.0.compare_exchange_weak(
+                current,
+                Self::pack(UpperBound::Finite(score)),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LowerBoundRef {
-    values: Vec<Arc<Mutex<UpperBound>>>, //we are the maximum of these upper bounds
+    values: Vec<Arc<AtomicBound>>, //we are the maximum of these upper bounds
 }
 impl Neg for LowerBoundRef {
     type Output = UpperBoundRef;
@@ -157,37 +216,32 @@ impl Neg for LowerBoundRef {
 impl LowerBoundRef {
     pub fn new_inf() -> Self {
         Self {
-            values: vec![Arc::new(Mutex::new(UpperBound::PosInf))],
+            values: vec![Arc::new(AtomicBound::new(UpperBound::PosInf))],
+        }
+    }
+    pub fn new_finite(score: Score) -> Self {
+        Self {
+            values: vec![Arc::new(AtomicBound::new(UpperBound::Finite(-score)))],
         }
     }
     pub fn get_bound(&self) -> LowerBound {
-        -self
-            .values
-            .iter()
-            .map(|value| *value.lock().unwrap())
-            .min()
-            .unwrap()
+        -self.values.iter().map(|value| value.load()).min().unwrap()
     }
     pub fn refine_bound(&self, score: Score) -> bool {
-        let mut bound = self.values.last().unwrap().lock().unwrap();
-        let bound_neg = -*bound;
-        if bound_neg.is_improvement(&score) {
-            *bound = -LowerBound::Finite(score);
-            true
-        } else {
-            false
-        }
+        self.values.last().unwrap().refine(-score)
     }
     pub fn branch(&self) -> Self {
         let mut branch = self.clone();
-        branch.values.push(Arc::new(Mutex::new(UpperBound::PosInf)));
+        branch
+            .values
+            .push(Arc::new(AtomicBound::new(UpperBound::PosInf)));
         branch
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct UpperBoundRef {
-    values: Vec<Arc<Mutex<UpperBound>>>, //we are the minimum of these lower bounds represented as negative upper bounds
+    values: Vec<Arc<AtomicBound>>, //we are the minimum of these lower bounds represented as negative upper bounds
 }
 impl Neg for UpperBoundRef {
     type Output = LowerBoundRef;
@@ -201,28 +255,59 @@ impl Neg for UpperBoundRef {
 impl UpperBoundRef {
     pub fn new_inf() -> Self {
         Self {
-            values: vec![Arc::new(Mutex::new(UpperBound::PosInf))],
+            values: vec![Arc::new(AtomicBound::new(UpperBound::PosInf))],
+        }
+    }
+    pub fn new_finite(score: Score) -> Self {
+        Self {
+            values: vec![Arc::new(AtomicBound::new(UpperBound::Finite(score)))],
         }
     }
     pub fn get_bound(&self) -> UpperBound {
-        self.values
-            .iter()
-            .map(|value| *value.lock().unwrap())
-            .min()
-            .unwrap()
+        self.values.iter().map(|value| value.load()).min().unwrap()
     }
     pub fn refine_bound(&self, score: Score) -> bool {
-        let mut bound = self.values.last().unwrap().lock().unwrap();
-        if bound.is_improvement(&score) {
-            *bound = UpperBound::Finite(score);
-            true
-        } else {
-            false
-        }
+        self.values.last().unwrap().refine(score)
     }
     pub fn branch(&self) -> Self {
         let mut branch = self.clone();
-        branch.values.push(Arc::new(Mutex::new(UpperBound::PosInf)));
         branch
+            .values
+            .push(Arc::new(AtomicBound::new(UpperBound::PosInf)));
+        branch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_bound() {
+        for bound in [
+            UpperBound::Finite(Score::Heuristic(-123456789)),
+            UpperBound::Finite(Score::Heuristic(42)),
+            UpperBound::Finite(Score::Lost(7)),
+            UpperBound::Finite(Score::Draw(0)),
+            UpperBound::Finite(Score::Won(100)),
+            UpperBound::PosInf,
+        ] {
+            assert_eq!(AtomicBound::new(bound).load(), bound);
+        }
+
+        let bound = AtomicBound::new(UpperBound::PosInf);
+        assert!(bound.refine(Score::Heuristic(10)));
+        assert!(!bound.refine(Score::Heuristic(20)));
+        assert!(bound.refine(Score::Lost(3)));
+        assert_eq!(bound.load(), UpperBound::Finite(Score::Lost(3)));
+
+        //a lower bound is the greatest of the bounds along its chain
+        let alpha = LowerBoundRef::new_finite(Score::Heuristic(5));
+        let branch = alpha.branch();
+        assert_eq!(branch.get_bound(), LowerBound::Finite(Score::Heuristic(5)));
+        assert!(branch.refine_bound(Score::Heuristic(8)));
+        assert!(!branch.refine_bound(Score::Heuristic(6)));
+        assert_eq!(branch.get_bound(), LowerBound::Finite(Score::Heuristic(8)));
+        assert_eq!(alpha.get_bound(), LowerBound::Finite(Score::Heuristic(5)));
     }
 }
@@ -10,8 +10,16 @@ pub struct CastleSignature {
     pub not_occupied: Vec<Square>, //in-between places which must be empty
 }
 
+//a piece whose movement is given in Betza notation rather than built into the move generator
 #[derive(Debug, Clone)]
-pub struct Signature {
+pub struct CustomPiece {
+    pub name: String,
+    pub movement: betza::Movement,
+}
+
+//what a board is and how its pieces move, worked out once and shared by every board of the game
+#[derive(Debug, Clone)]
+struct SignatureData {
     num: usize,
     flat_slides: Vec<Vec<Vec<Square>>>,
     diag_slides: Vec<Vec<Vec<Square>>>,
@@ -28,63 +36,107 @@ pub struct Signature {
     black_pawn_promotion_distance: Vec<Option<usize>>,
     white_castles: Vec<CastleSignature>,
     black_castles: Vec<CastleSignature>,
+    custom_pieces: Vec<CustomPiece>,
+    drops: bool, //whether captured pieces go to the capturer's pocket to be dropped back on the board
+    atomic: bool, //whether captures explode, as in atomic chess
+    antichess: bool, //whether the rules are those of antichess, where the aim is to lose every piece
+    rules: Vec<std::sync::Arc<dyn rules::Rules>>,
+    piece_square: piece_square::PieceSquareTables,
+    bitboards: Option<std::sync::Arc<bitboard::Tables>>, //when the board is the flat 8x8 one
+    tablebases: Option<std::sync::Arc<tablebase::Tablebases>>, //exact results for positions with few pieces
+    syzygy: Option<std::sync::Arc<syzygy::Syzygy>>, //the syzygy tables, for classical chess alone
 }
 
+//cloning a signature, as every board clone does, only shares its data
+//the builders copy the data first if anything else shares it
+#[derive(Debug, Clone)]
+pub struct Signature(std::sync::Arc<SignatureData>);
+
 impl Signature {
+    fn data_mut(&mut self) -> &mut SignatureData {
+        std::sync::Arc::make_mut(&mut self.0)
+    }
+
     pub fn num(&self) -> usize {
-        self.num
+        self.0.num
+    }
+
+    pub fn add_custom_piece(&mut self, name: String, movement: betza::Movement) -> PieceKind {
+        self.data_mut()
+            .custom_pieces
+            .push(CustomPiece { name, movement });
+        PieceKind::Custom(self.0.custom_pieces.len() - 1)
+    }
+
+    pub fn num_custom_pieces(&self) -> usize {
+        self.0.custom_pieces.len()
+    }
+
+    pub fn get_custom_piece(&self, idx: usize) -> &CustomPiece {
+        &self.0.custom_pieces[idx]
     }
 
-    pub fn get_castles(&self) -> Vec<(Team, &CastleSignature)> {
-        self.white_castles
+    pub fn get_castles(&self) -> impl Iterator<Item = (Team, &CastleSignature)> {
+        self.0
+            .white_castles
             .iter()
             .map(|cs| (Team::White, cs))
-            .chain(self.black_castles.iter().map(|cs| (Team::Black, cs)))
-            .collect()
+            .chain(self.0.black_castles.iter().map(|cs| (Team::Black, cs)))
+    }
+
+    pub fn get_team_castles(&self, team: Team) -> &Vec<CastleSignature> {
+        match team {
+            Team::White => &self.0.white_castles,
+            Team::Black => &self.0.black_castles,
+        }
+    }
+
+    pub fn get_castle(&self, team: Team, idx: usize) -> &CastleSignature {
+        &self.get_team_castles(team)[idx]
     }
 
     pub fn get_pawn_promotion_distance(&self, sq: Square, team: Team) -> Option<usize> {
         match team {
-            Team::White => self.white_pawn_promotion_distance[sq.idx],
-            Team::Black => self.black_pawn_promotion_distance[sq.idx],
+            Team::White => self.0.white_pawn_promotion_distance[sq.idx],
+            Team::Black => self.0.black_pawn_promotion_distance[sq.idx],
         }
     }
 
     pub fn get_pawn_promotions(&self, sq: Square, team: Team) -> &Option<Vec<PieceKind>> {
         match team {
-            Team::White => &self.white_pawn_promotions[sq.idx],
-            Team::Black => &self.black_pawn_promotions[sq.idx],
+            Team::White => &self.0.white_pawn_promotions[sq.idx],
+            Team::Black => &self.0.black_pawn_promotions[sq.idx],
         }
     }
 
     pub fn get_pawn_moves(&self, sq: Square, team: Team) -> &Vec<(Square, Vec<Square>)> {
         match team {
-            Team::White => &self.white_pawn_moves[sq.idx],
-            Team::Black => &self.black_pawn_moves[sq.idx],
+            Team::White => &self.0.white_pawn_moves[sq.idx],
+            Team::Black => &self.0.black_pawn_moves[sq.idx],
         }
     }
 
     pub fn get_pawn_takes(&self, sq: Square, team: Team) -> &Vec<Square> {
         match team {
-            Team::White => &self.white_pawn_takes[sq.idx],
-            Team::Black => &self.black_pawn_takes[sq.idx],
+            Team::White => &self.0.white_pawn_takes[sq.idx],
+            Team::Black => &self.0.black_pawn_takes[sq.idx],
         }
     }
 
     pub fn get_flat_slides(&self, sq: Square) -> &Vec<Vec<Square>> {
-        &self.flat_slides[sq.idx]
+        &self.0.flat_slides[sq.idx]
     }
 
     pub fn get_diag_slides(&self, sq: Square) -> &Vec<Vec<Square>> {
-        &self.diag_slides[sq.idx]
+        &self.0.diag_slides[sq.idx]
     }
 
     pub fn get_knight_moves(&self, sq: Square) -> &Vec<Square> {
-        &self.knight_moves[sq.idx]
+        &self.0.knight_moves[sq.idx]
     }
 
     pub fn get_king_moves(&self, sq: Square) -> &Vec<Square> {
-        &self.king_moves[sq.idx]
+        &self.0.king_moves[sq.idx]
     }
 }
 
@@ -94,6 +146,7 @@ impl Signature {
     //flat_opp and diag_opp : assuming i and j represent a set from one square to anther, either horz, vert, or diag
     //return all possible (might be multiple e.g. on a wormhole board) next steps k so that i,j,k are "evenly spaced"
     //pawn_moves : return all the pawn moves. regular moves in the first list, and double moves in the second list
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         num: usize,
         flat_nbs: &dyn Fn(Square) -> Vec<Square>,
@@ -115,7 +168,7 @@ impl Signature {
                     ans.push(k);
                 }
             }
-            return ans;
+            ans
         };
 
         /*
@@ -129,14 +182,15 @@ impl Signature {
                           nbs: &dyn Fn(Square) -> Vec<Square>,
                           opp: &dyn Fn(Square, Square) -> Vec<Square>|
          -> Vec<Vec<Square>> {
+            #[allow(clippy::type_complexity)]
             struct RestSlide<'a> {
                 f: &'a dyn Fn(&RestSlide, Vec<Square>, Square, Square) -> Vec<Vec<Square>>,
             }
             let rest_slide = RestSlide {
                 f: &|rest_slide, block, i, j| {
                     if block.contains(&j) {
-                        //we found a loop
-                        return vec![block];
+                        //we found a loop, so the slide ends on the square it has come back round to
+                        vec![vec![j]]
                     } else {
                         //not found a loop, so look at all possible next steps and all continuations of the slide from that next step
                         let mut slides = vec![];
@@ -164,7 +218,7 @@ impl Signature {
                         if none {
                             slides.push(vec![j]);
                         }
-                        return slides;
+                        slides
                     }
                 },
             };
@@ -177,7 +231,7 @@ impl Signature {
                     ans.push(slide);
                 }
             }
-            return ans;
+            ans
         };
 
         let flat_slides: Vec<Vec<Vec<Square>>> = (0..num)
@@ -296,7 +350,7 @@ impl Signature {
             black_pawn_promotions.keys().cloned().collect(),
         );
 
-        Self {
+        let mut signature = Self(std::sync::Arc::new(SignatureData {
             num,
             flat_slides,
             diag_slides,
@@ -330,10 +384,356 @@ impl Signature {
             black_pawn_promotion_distance,
             white_castles,
             black_castles,
-        }
+            custom_pieces: vec![],
+            drops: false,
+            atomic: false,
+            antichess: false,
+            rules: vec![],
+            piece_square: piece_square::PieceSquareTables::empty(),
+            bitboards: None,
+            tablebases: None,
+            syzygy: None,
+        }));
+        signature.data_mut().piece_square = piece_square::PieceSquareTables::generate(&signature);
+        signature.data_mut().bitboards = bitboard::Tables::new(&signature).map(std::sync::Arc::new);
+        signature
+    }
+
+    //knight moves and pawn captures are derived in new assuming flat neighbours meet at right angles
+    //boards where that is not the case, such as hexagonal boards, can replace them afterwards
+    pub fn with_knight_moves(mut self, knight_moves: &dyn Fn(Square) -> Vec<Square>) -> Self {
+        self.data_mut().knight_moves = (0..self.0.num)
+            .map(|idx| knight_moves(Square { idx }))
+            .collect();
+        self.data_mut().piece_square = piece_square::PieceSquareTables::generate(&self);
+        self.data_mut().bitboards = bitboard::Tables::new(&self).map(std::sync::Arc::new);
+        self
+    }
+
+    pub fn with_pawn_takes(mut self, pawn_takes: &dyn Fn(Team, Square) -> Vec<Square>) -> Self {
+        self.data_mut().white_pawn_takes = (0..self.0.num)
+            .map(|idx| pawn_takes(Team::White, Square { idx }))
+            .collect();
+        self.data_mut().black_pawn_takes = (0..self.0.num)
+            .map(|idx| pawn_takes(Team::Black, Square { idx }))
+            .collect();
+        self.data_mut().bitboards = bitboard::Tables::new(&self).map(std::sync::Arc::new);
+        self
+    }
+
+    pub fn with_drops(mut self) -> Self {
+        self.data_mut().drops = true;
+        self
+    }
+
+    pub fn has_drops(&self) -> bool {
+        self.0.drops
+    }
+
+    pub fn with_atomic(mut self) -> Self {
+        self.data_mut().atomic = true;
+        self
+    }
+
+    pub fn is_atomic(&self) -> bool {
+        self.0.atomic
+    }
+
+    pub fn with_antichess(mut self) -> Self {
+        self.data_mut().antichess = true;
+        self
+    }
+
+    pub fn is_antichess(&self) -> bool {
+        self.0.antichess
+    }
+
+    pub fn with_rules(mut self, rules: impl rules::Rules + 'static) -> Self {
+        self.data_mut().rules.push(std::sync::Arc::new(rules));
+        self
+    }
+
+    pub fn get_rules(&self) -> &Vec<std::sync::Arc<dyn rules::Rules>> {
+        &self.0.rules
+    }
+
+    //replace a generated piece-square table with a hand-made one
+    pub fn with_piece_square_table(mut self, kind: PieceKind, table: piece_square::Table) -> Self {
+        self.data_mut().piece_square.set(kind, table);
+        self
+    }
+
+    pub fn get_piece_square_tables(&self) -> &piece_square::PieceSquareTables {
+        &self.0.piece_square
+    }
+
+    pub fn get_bitboard_tables(&self) -> Option<&bitboard::Tables> {
+        self.0.bitboards.as_deref()
     }
 
     pub fn get_num(&self) -> usize {
-        self.num
+        self.0.num
+    }
+
+    pub fn with_tablebases(mut self, tablebases: std::sync::Arc<tablebase::Tablebases>) -> Self {
+        self.data_mut().tablebases = Some(tablebases);
+        self
+    }
+
+    pub fn get_tablebases(&self) -> Option<&tablebase::Tablebases> {
+        self.0.tablebases.as_deref()
+    }
+
+    pub fn with_syzygy(mut self, syzygy: std::sync::Arc<syzygy::Syzygy>) -> Self {
+        self.data_mut().syzygy = Some(syzygy);
+        self
+    }
+
+    pub fn get_syzygy(&self) -> Option<&std::sync::Arc<syzygy::Syzygy>> {
+        self.0.syzygy.as_ref()
+    }
+
+    //the ways of mapping the board onto itself which keep where every piece moves, the identity first
+    //with pawns they must also keep how each team's pawns move and promote, which on most boards leaves only the mirror
+    //the squares are placed in turn, each next to one placed before, onto squares which neighbour the same placed
+    //squares in the same ways, and each full placing is then checked against every move on the board
+    pub fn symmetries(&self, pawns: bool) -> Vec<Vec<Square>> {
+        let num = self.num();
+        let squares = || (0..num).map(|idx| Square { idx });
+        let first_steps = |slides: &Vec<Vec<Square>>| slides.iter().map(|slide| slide[0]).collect();
+        let neighbours: [Vec<Vec<Square>>; 4] = [
+            squares()
+                .map(|sq| self.get_king_moves(sq).clone())
+                .collect(),
+            squares()
+                .map(|sq| self.get_knight_moves(sq).clone())
+                .collect(),
+            squares()
+                .map(|sq| first_steps(self.get_flat_slides(sq)))
+                .collect(),
+            squares()
+                .map(|sq| first_steps(self.get_diag_slides(sq)))
+                .collect(),
+        ];
+        let adjacent = neighbours.each_ref().map(|neighbours| {
+            let mut adjacent = vec![false; num * num];
+            for (idx, neighbours) in neighbours.iter().enumerate() {
+                for sq in neighbours {
+                    adjacent[idx * num + sq.idx] = true;
+                }
+            }
+            adjacent
+        });
+        //what a square's moves look like without saying where they go, which a symmetry must keep
+        let profile = |sq: Square| {
+            let lengths = |slides: &Vec<Vec<Square>>| {
+                let mut lengths = slides.iter().map(|slide| slide.len()).collect::<Vec<_>>();
+                lengths.sort();
+                lengths
+            };
+            let mut profile = format!(
+                "{:?}",
+                (
+                    neighbours
+                        .each_ref()
+                        .map(|neighbours| neighbours[sq.idx].len()),
+                    lengths(self.get_flat_slides(sq)),
+                    lengths(self.get_diag_slides(sq)),
+                )
+            );
+            if pawns {
+                for team in [Team::White, Team::Black] {
+                    profile += &format!(
+                        "{:?}",
+                        (
+                            self.get_pawn_moves(sq, team).len(),
+                            self.get_pawn_takes(sq, team).len(),
+                            self.get_pawn_promotion_distance(sq, team),
+                            self.get_pawn_promotions(sq, team),
+                        )
+                    );
+                }
+            }
+            profile
+        };
+        let profiles = squares().map(profile).collect::<Vec<_>>();
+
+        //the order squares are placed in, each with a square placed before which it neighbours
+        let mut order: Vec<(Square, Option<Square>)> = vec![];
+        let mut seen = vec![false; num];
+        for root in squares() {
+            if seen[root.idx] {
+                continue;
+            }
+            seen[root.idx] = true;
+            order.push((root, None));
+            let mut next = order.len() - 1;
+            while next < order.len() {
+                let (sq, _parent) = order[next];
+                for nb in self.get_king_moves(sq) {
+                    if !seen[nb.idx] {
+                        seen[nb.idx] = true;
+                        order.push((*nb, Some(sq)));
+                    }
+                }
+                next += 1;
+            }
+        }
+
+        //whether the placing keeps every move on the board
+        let keeps = |map: &[Square]| {
+            let sorted = |mut sqs: Vec<Square>| {
+                sqs.sort();
+                sqs
+            };
+            let slides = |slides: &Vec<Vec<Square>>, mapped: bool| {
+                let mut slides = slides
+                    .iter()
+                    .map(|slide| match mapped {
+                        true => slide.iter().map(|sq| map[sq.idx]).collect(),
+                        false => slide.clone(),
+                    })
+                    .collect::<Vec<Vec<Square>>>();
+                slides.sort();
+                slides
+            };
+            let leaps = |leaps: &Vec<Square>| sorted(leaps.iter().map(|sq| map[sq.idx]).collect());
+            squares().all(|sq| {
+                let to = map[sq.idx];
+                slides(self.get_flat_slides(sq), true) == slides(self.get_flat_slides(to), false)
+                    && slides(self.get_diag_slides(sq), true)
+                        == slides(self.get_diag_slides(to), false)
+                    && leaps(self.get_knight_moves(sq)) == sorted(self.get_knight_moves(to).clone())
+                    && leaps(self.get_king_moves(sq)) == sorted(self.get_king_moves(to).clone())
+                    && (!pawns
+                        || [Team::White, Team::Black].into_iter().all(|team| {
+                            let mut moves = self
+                                .get_pawn_moves(sq, team)
+                                .iter()
+                                .map(|(first, seconds)| (map[first.idx], leaps(seconds)))
+                                .collect::<Vec<_>>();
+                            moves.sort();
+                            let mut to_moves = self
+                                .get_pawn_moves(to, team)
+                                .iter()
+                                .map(|(first, seconds)| (*first, sorted(seconds.clone())))
+                                .collect::<Vec<_>>();
+                            to_moves.sort();
+                            moves == to_moves
+                                && leaps(self.get_pawn_takes(sq, team))
+                                    == sorted(self.get_pawn_takes(to, team).clone())
+                                && self.get_pawn_promotions(sq, team)
+                                    == self.get_pawn_promotions(to, team)
+                        }))
+            })
+        };
+
+        struct Search<'a> {
+            num: usize,
+            order: &'a [(Square, Option<Square>)],
+            adjacent: &'a [Vec<bool>; 4],
+            profiles: &'a [String],
+            king_moves: &'a [Vec<Square>],
+        }
+        fn place(
+            search: &Search,
+            placed: usize,
+            map: &mut Vec<Option<Square>>,
+            used: &mut Vec<bool>,
+            found: &mut Vec<Vec<Square>>,
+            keeps: &dyn Fn(&[Square]) -> bool,
+        ) {
+            let num = search.num;
+            if placed == search.order.len() {
+                let map = map.iter().map(|sq| sq.unwrap()).collect::<Vec<_>>();
+                if keeps(&map) {
+                    found.push(map);
+                }
+                return;
+            }
+            let (sq, parent) = search.order[placed];
+            let candidates = match parent {
+                Some(parent) => search.king_moves[map[parent.idx].unwrap().idx].clone(),
+                None => (0..num).map(|idx| Square { idx }).collect(),
+            };
+            for to in candidates {
+                let agrees = !used[to.idx]
+                    && search.profiles[sq.idx] == search.profiles[to.idx]
+                    && search.order[..placed].iter().all(|(other, _parent)| {
+                        let other_to = map[other.idx].unwrap();
+                        search.adjacent.iter().all(|adjacent| {
+                            adjacent[other.idx * num + sq.idx]
+                                == adjacent[other_to.idx * num + to.idx]
+                                && adjacent[sq.idx * num + other.idx]
+                                    == adjacent[to.idx * num + other_to.idx]
+                        })
+                    });
+                if agrees {
+                    map[sq.idx] = Some(to);
+                    used[to.idx] = true;
+                    place(search, placed + 1, map, used, found, keeps);
+                    map[sq.idx] = None;
+                    used[to.idx] = false;
+                }
+            }
+        }
+
+        let search = Search {
+            num,
+            order: &order,
+            adjacent: &adjacent,
+            profiles: &profiles,
+            king_moves: &neighbours[0],
+        };
+        let mut found = vec![];
+        place(
+            &search,
+            0,
+            &mut vec![None; num],
+            &mut vec![false; num],
+            &mut found,
+            &keeps,
+        );
+        //the identity is always found, and goes first
+        found.sort_by_key(|map| map.iter().enumerate().any(|(idx, sq)| sq.idx != idx));
+        found
+    }
+
+    //a hash of everything which decides how the pieces move and how the game ends, but not of castling
+    //two signatures with the same fingerprint can share tablebases
+    pub fn fingerprint(&self) -> u64 {
+        //the leaps are found as sets, so come in no particular order
+        let sorted = |moves: &Vec<Vec<Square>>| {
+            moves
+                .iter()
+                .map(|sqs| sqs.iter().copied().collect::<BTreeSet<_>>())
+                .collect::<Vec<_>>()
+        };
+        let parts: [&dyn std::fmt::Debug; 15] = [
+            &self.0.num,
+            &self.0.flat_slides,
+            &self.0.diag_slides,
+            &sorted(&self.0.knight_moves),
+            &sorted(&self.0.king_moves),
+            &self.0.white_pawn_moves,
+            &self.0.black_pawn_moves,
+            &self.0.white_pawn_takes,
+            &self.0.black_pawn_takes,
+            &self.0.white_pawn_promotions,
+            &self.0.black_pawn_promotions,
+            &self.0.custom_pieces,
+            &self.0.drops,
+            &(self.0.atomic, self.0.antichess),
+            &self.0.rules,
+        ];
+        //fnv-1a, which unlike the standard library's hasher is the same from one build to the next
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for part in parts {
+            for byte in format!("{:?};", part).bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
     }
 }
@@ -0,0 +1,1636 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::board_data::BoardData;
+use super::score::Score;
+use super::signature::Signature;
+use super::*;
+
+//syzygy endgame tablebases for classical chess, read from the .rtbw and .rtbz files in a directory
+//
+//a wdl table says whether the side to move wins, draws or loses, and a dtz table how many plies it is until the next
+//capture or pawn move with best play, which is enough to make progress but says nothing about when the game ends
+//the search looks positions up in the wdl tables to score them exactly, and the root picks its move by dtz
+//
+//the tables leave out positions in which the best move is a capture, and those with an en passant capture,
+//so captures are searched before a table is looked at, as the probing code that comes with the tables does
+//the game has no fifty move rule, so a win the rule would spoil is still a win, and likewise a loss
+//positions with castling rights are not in the tables, and tables are only read once a position needs them
+
+const WDL_EXTENSION: &str = "rtbw";
+const DTZ_EXTENSION: &str = "rtbz";
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+const MAX_PIECES: usize = 7;
+
+//the tables know who wins but not when, so a win is scored as coming later than any mate the search finds by itself
+const UNKNOWN_PLIES: usize = 1000;
+
+//the flags of each part of a table
+const STM: u8 = 1; //which side to move a dtz table is for
+const MAPPED: u8 = 2; //dtz values go through a map
+const WIN_PLIES: u8 = 4; //winning dtz values are in plies rather than moves
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16; //the map has 16 bit entries
+const SINGLE_VALUE: u8 = 128; //every position has the same value
+
+//pieces are numbered as in the files, pawn to king, with black's 8 more than white's
+const PIECE_LETTERS: &str = "PNBRQK";
+
+fn piece_code(piece: Piece) -> Option<u8> {
+    let kind = match piece.kind {
+        PieceKind::Pawn(..) => 1,
+        PieceKind::Knight => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Rook => 4,
+        PieceKind::Queen => 5,
+        PieceKind::King => 6,
+        _ => return None,
+    };
+    Some(match piece.team {
+        Team::White => kind,
+        Team::Black => kind + 8,
+    })
+}
+
+//the pieces of a side as the files name them, strongest first
+fn side_name(codes: &[u8]) -> String {
+    let mut kinds = codes.iter().map(|code| code & 7).collect::<Vec<_>>();
+    kinds.sort_by(|a, b| b.cmp(a));
+    kinds
+        .into_iter()
+        .map(|kind| PIECE_LETTERS.as_bytes()[kind as usize - 1] as char)
+        .collect()
+}
+
+//how far the square is above the a1-h8 diagonal, negative below it
+fn off_diagonal(sq: usize) -> i64 {
+    (sq / 8) as i64 - (sq % 8) as i64
+}
+
+//the squares a position's pieces are on, in the order they make up its index
+struct Encoding {
+    binomial: [[u64; 64]; MAX_PIECES],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES], //by the number of leading pawns and the square of the first
+    lead_pawns_size: [[u64; 4]; MAX_PIECES], //by the number of leading pawns and the file of the first
+    map_pawns: [usize; 64], //a2-h7 to 0..47, highest nearest the edge and lowest rank
+    map_b1h1h7: [usize; 64], //below the diagonal to 0..27
+    map_a1d1d4: [usize; 64], //the a1-d1-d4 triangle to 0..9, the diagonal last
+    map_kk: [[usize; 64]; 10], //the 462 ways two kings can stand, the first in the triangle
+}
+
+impl Encoding {
+    fn get() -> &'static Self {
+        static ENCODING: OnceLock<Encoding> = OnceLock::new();
+        ENCODING.get_or_init(Encoding::new)
+    }
+
+    fn new() -> Self {
+        let mut encoding = Self {
+            binomial: [[0; 64]; MAX_PIECES],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+
+        let mut code = 0;
+        for sq in 0..64 {
+            if off_diagonal(sq) < 0 {
+                encoding.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+
+        let mut diagonal = vec![];
+        code = 0;
+        for sq in [0, 1, 2, 3, 8, 9, 10, 11, 16, 17, 18, 19, 24, 25, 26, 27] {
+            match off_diagonal(sq) {
+                off if off < 0 => {
+                    encoding.map_a1d1d4[sq] = code;
+                    code += 1;
+                }
+                0 => diagonal.push(sq),
+                _ => {}
+            }
+        }
+        for sq in diagonal {
+            encoding.map_a1d1d4[sq] = code;
+            code += 1;
+        }
+
+        //with the first king on the diagonal the second is not above it, and with both on it they come last
+        let mut both_on_diagonal = vec![];
+        code = 0;
+        for idx in 0..10 {
+            for first in 0..28 {
+                //b1 is the only square of the triangle which maps to 0
+                if encoding.map_a1d1d4[first] != idx || (idx == 0 && first != 1) {
+                    continue;
+                }
+                for second in 0..64 {
+                    let touching = (first % 8).abs_diff(second % 8) <= 1
+                        && (first / 8).abs_diff(second / 8) <= 1;
+                    if touching || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        encoding.map_kk[idx][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            encoding.map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        encoding.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                encoding.binomial[k][n] = match k {
+                    0 => 0,
+                    k => encoding.binomial[k - 1][n - 1],
+                } + match k < n {
+                    true => encoding.binomial[k][n - 1],
+                    false => 0,
+                };
+            }
+        }
+
+        //with the leading pawn on a square, the others can only be on squares further from the edge or higher up
+        let mut available = 47;
+        for lead in 1..MAX_PIECES - 1 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let sq = file + 8 * rank;
+                    if lead == 1 {
+                        encoding.map_pawns[sq] = available;
+                        encoding.map_pawns[sq ^ 7] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+                    encoding.lead_pawn_idx[lead][sq] = idx;
+                    idx += encoding.binomial[lead - 1][encoding.map_pawns[sq]];
+                }
+                encoding.lead_pawns_size[lead][file] = idx;
+            }
+        }
+
+        encoding
+    }
+}
+
+fn read<const N: usize>(bytes: &[u8], at: usize) -> [u8; N] {
+    //past the end of a broken file reads as zeros rather than failing
+    let mut out = [0; N];
+    if let Some(slice) = bytes.get(at..at.saturating_add(N)) {
+        out.copy_from_slice(slice);
+    }
+    out
+}
+
+fn byte(bytes: &[u8], at: usize) -> u8 {
+    read::<1>(bytes, at)[0]
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> usize {
+    u16::from_le_bytes(read(bytes, at)) as usize
+}
+
+//the symbols a symbol of the compressed data stands for, or its value if it stands for no others
+fn children(bytes: &[u8], btree: usize, sym: usize) -> (usize, usize) {
+    let [b0, b1, b2] = read::<3>(bytes, btree + 3 * sym).map(|b| b as usize);
+    (((b1 & 0xf) << 8) | b0, (b2 << 4) | (b1 >> 4))
+}
+
+//one part of a table, for a side to move and in tables with pawns the file of the leading pawn
+//the values are huffman coded symbols, each of which stands for one value or a pair of other symbols
+#[derive(Debug, Clone, Default)]
+struct Pairs {
+    flags: u8,
+    min_sym_len: usize, //the value itself, in a part where every position has the same one
+    block_size: usize,
+    span: usize, //every span values there is an entry in the sparse index
+    lowest_sym: usize,
+    btree: usize,
+    block_lengths: usize,
+    block_lengths_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    num_blocks: usize,
+    base64: Vec<u64>,   //the lowest code of each length, padded to 64 bits
+    symlen: Vec<usize>, //how many values, less one, each symbol stands for
+    pieces: [u8; MAX_PIECES],
+    group_len: [usize; MAX_PIECES + 1], //how many pieces are encoded together, ending with a 0
+    group_idx: [u64; MAX_PIECES + 1], //what each group's index is multiplied by, the last being the size
+    map_idx: [usize; 4],
+}
+
+impl Pairs {
+    //how many values of the symbol and those it stands for are left to count
+    fn set_symlen(&mut self, bytes: &[u8], sym: usize, visited: &mut Vec<bool>) -> usize {
+        visited[sym] = true;
+        let (left, right) = children(bytes, self.btree, sym);
+        if right == 0xfff || left >= visited.len() || right >= visited.len() {
+            return 0;
+        }
+        for child in [left, right] {
+            if !visited[child] {
+                self.symlen[child] = self.set_symlen(bytes, child, visited);
+            }
+        }
+        self.symlen[left] + self.symlen[right] + 1
+    }
+
+    //read the sizes of the part, returning where the file goes on
+    fn set_sizes(&mut self, bytes: &[u8], mut at: usize) -> Result<usize, String> {
+        self.flags = byte(bytes, at);
+        at += 1;
+        if self.flags & SINGLE_VALUE != 0 {
+            self.min_sym_len = byte(bytes, at) as usize;
+            return Ok(at + 1);
+        }
+
+        let size = self.group_idx[self.group_len.iter().position(|len| *len == 0).unwrap()];
+        self.block_size = 1 << byte(bytes, at);
+        self.span = 1 << byte(bytes, at + 1);
+        self.sparse_index_size = size.div_ceil(self.span as u64) as usize;
+        let padding = byte(bytes, at + 2) as usize;
+        self.num_blocks = u32::from_le_bytes(read(bytes, at + 3)) as usize;
+        self.block_lengths_size = self.num_blocks + padding;
+        let max_sym_len = byte(bytes, at + 7) as usize;
+        self.min_sym_len = byte(bytes, at + 8) as usize;
+        at += 9;
+        if self.min_sym_len == 0 || max_sym_len < self.min_sym_len || max_sym_len > 32 {
+            return Err(format!(
+                "symbols of {} to {} bits",
+                self.min_sym_len, max_sym_len
+            ));
+        }
+
+        //longer codes have lower values, so going from the longest each length starts above the last
+        self.lowest_sym = at;
+        let lengths = max_sym_len - self.min_sym_len + 1;
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            self.base64[i] = (self.base64[i + 1] + u16_le(bytes, at + 2 * i) as u64)
+                .wrapping_sub(u16_le(bytes, at + 2 * i + 2) as u64)
+                / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base <<= 64 - i - self.min_sym_len;
+        }
+        at += 2 * lengths;
+
+        let symbols = u16_le(bytes, at);
+        at += 2;
+        self.btree = at;
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                self.symlen[sym] = self.set_symlen(bytes, sym, &mut visited);
+            }
+        }
+        Ok(at + 3 * symbols + (symbols & 1))
+    }
+
+    //the value at the index
+    fn decompress(&self, bytes: &[u8], idx: u64) -> Option<usize> {
+        if self.flags & SINGLE_VALUE != 0 {
+            return Some(self.min_sym_len);
+        }
+
+        //the sparse index gives the block and offset of a value near the one wanted, and the blocks from there
+        //are stepped through until the one holding it
+        let span = self.span as u64;
+        let entry = self.sparse_index + 6 * (idx / span) as usize;
+        let mut block = u32::from_le_bytes(read(bytes, entry)) as usize;
+        let mut offset = u16_le(bytes, entry + 4) as i64 + (idx % span) as i64 - (span / 2) as i64;
+        let block_length = |block: usize| u16_le(bytes, self.block_lengths + 2 * block) as i64;
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+            if block >= self.block_lengths_size {
+                return None;
+            }
+        }
+
+        //the symbols of the block are read until the one which stands for the value
+        let mut at = self.data + block * self.block_size;
+        let mut buf = u64::from_be_bytes(read(bytes, at));
+        let mut buf_size: i64 = 64;
+        at += 8;
+        let mut sym = loop {
+            let mut len = 0;
+            while len + 1 < self.base64.len() && buf < self.base64[len] {
+                len += 1;
+            }
+            let sym = ((buf - self.base64[len]) >> (64 - len - self.min_sym_len)) as usize
+                + u16_le(bytes, self.lowest_sym + 2 * len);
+            let symlen = *self.symlen.get(sym)? as i64;
+            if offset <= symlen {
+                break sym;
+            }
+            offset -= symlen + 1;
+            let len = len + self.min_sym_len;
+            buf <<= len;
+            buf_size -= len as i64;
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= (u32::from_be_bytes(read(bytes, at)) as u64) << (64 - buf_size);
+                at += 4;
+            }
+        };
+
+        //and then the pairs it stands for are followed down to the value
+        while self.symlen[sym] > 0 {
+            let (left, right) = children(bytes, self.btree, sym);
+            let left_len = *self.symlen.get(left)? as i64;
+            match offset <= left_len {
+                true => sym = left,
+                false => {
+                    offset -= left_len + 1;
+                    sym = right;
+                }
+            }
+            self.symlen.get(sym)?;
+        }
+        Some(children(bytes, self.btree, sym).0)
+    }
+}
+
+//a wdl or dtz file, read into memory
+struct Table {
+    bytes: Vec<u8>,
+    num_pieces: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool, //whether some piece is the only one of its kind, so the first three are encoded together
+    symmetric: bool, //whether both sides have the same pieces, in which case only white to move is stored
+    pawn_count: [usize; 2], //of the side with the leading pawns, which has fewer, and then of the other
+    pairs: [[Pairs; 4]; 2], //by side to move and the file of the leading pawn
+    map: usize,             //where the dtz values are mapped
+}
+
+impl Table {
+    //what the name of the material tells, before any of the file is read
+    fn for_material(name: &str) -> Self {
+        let (white, black) = name.split_once('v').unwrap();
+        let count = |side: &str, letter: char| side.chars().filter(|c| *c == letter).count();
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        //the side with fewer pawns leads, which compresses better
+        let pawn_count = match black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns) {
+            true => [white_pawns, black_pawns],
+            false => [black_pawns, white_pawns],
+        };
+        Self {
+            num_pieces: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: [white, black]
+                .iter()
+                .any(|side| "PNBRQ".chars().any(|letter| count(side, letter) == 1)),
+            symmetric: white == black,
+            pawn_count,
+            pairs: Default::default(),
+            map: 0,
+            bytes: vec![],
+        }
+    }
+
+    fn new(name: &str, bytes: Vec<u8>, dtz: bool) -> Result<Self, String> {
+        let magic = match dtz {
+            false => WDL_MAGIC,
+            true => DTZ_MAGIC,
+        };
+        if bytes.get(..4) != Some(&magic[..]) {
+            return Err("not a syzygy table".to_string());
+        }
+        let mut table = Self::for_material(name);
+        let flags = byte(&bytes, 4);
+        if (flags & 2 != 0) != table.has_pawns || (flags & 1 != 0) == table.symmetric {
+            return Err(format!("not a table for {}", name));
+        }
+
+        let sides = match !dtz && !table.symmetric {
+            true => 2,
+            false => 1,
+        };
+        let files = match table.has_pawns {
+            true => 4,
+            false => 1,
+        };
+        let both_pawns = table.has_pawns && table.pawn_count[1] > 0;
+        let mut at = 5;
+        for file in 0..files {
+            let first = byte(&bytes, at);
+            let second = match both_pawns {
+                true => byte(&bytes, at + 1),
+                false => 0xff,
+            };
+            let orders = [
+                [(first & 0xf) as usize, (second & 0xf) as usize],
+                [(first >> 4) as usize, (second >> 4) as usize],
+            ];
+            at += 1 + both_pawns as usize;
+            for k in 0..table.num_pieces {
+                let code = byte(&bytes, at + k);
+                table.pairs[0][file].pieces[k] = code & 0xf;
+                table.pairs[1][file].pieces[k] = code >> 4;
+            }
+            at += table.num_pieces;
+            for (side, order) in orders.iter().enumerate().take(sides) {
+                table.set_groups(side, file, *order);
+            }
+        }
+        at += at & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                at = table.pairs[side][file].set_sizes(&bytes, at)?;
+            }
+        }
+
+        if dtz {
+            table.map = at;
+            for file in 0..files {
+                let pairs = &mut table.pairs[0][file];
+                if pairs.flags & MAPPED == 0 {
+                    continue;
+                }
+                //a map for each of win, loss, cursed win and blessed loss, each starting with its length
+                if pairs.flags & WIDE != 0 {
+                    at += at & 1;
+                    for map_idx in pairs.map_idx.iter_mut() {
+                        *map_idx = (at - table.map) / 2 + 1;
+                        at += 2 * u16_le(&bytes, at) + 2;
+                    }
+                } else {
+                    for map_idx in pairs.map_idx.iter_mut() {
+                        *map_idx = at - table.map + 1;
+                        at += byte(&bytes, at) as usize + 1;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut table.pairs[side][file];
+                pairs.sparse_index = at;
+                at += 6 * pairs.sparse_index_size;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut table.pairs[side][file];
+                pairs.block_lengths = at;
+                at += 2 * pairs.block_lengths_size;
+            }
+        }
+        //the compressed data starts at a multiple of 64 bytes, though parts with a single value have none
+        let mut end = at;
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut table.pairs[side][file];
+                at = (at + 0x3f) & !0x3f;
+                pairs.data = at;
+                at += pairs.num_blocks * pairs.block_size;
+                if pairs.num_blocks > 0 {
+                    end = at;
+                }
+            }
+        }
+        if end > bytes.len() {
+            return Err(format!("{} bytes long but should be {}", bytes.len(), end));
+        }
+        table.bytes = bytes;
+        Ok(table)
+    }
+
+    //which part of the table the position is in and its index there, or None if the position is in
+    //a dtz table only stored for the other side to move
+    //with flip the colours are swapped and the board turned over to find the position the table has
+    fn index(&self, position: &Position, flip: bool, dtz: bool) -> Option<(usize, usize, u64)> {
+        let encoding = Encoding::get();
+        let flip_color = 8 * flip as u8;
+        let flip_squares = 56 * flip as usize;
+        let stm = (flip != position.black_to_move) as usize;
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces = [0; MAX_PIECES];
+        let mut size = 0;
+        let mut file = 0;
+        let mut lead_pawn = None;
+        if self.has_pawns {
+            //the leading pawn is the one nearest the edge and then lowest, and picks which part of the table to use
+            let pawn = self.pairs[0][0].pieces[0] ^ flip_color;
+            for (sq, code) in &position.pieces {
+                if *code == pawn {
+                    squares[size] = sq ^ flip_squares;
+                    pieces[size] = pawn ^ flip_color;
+                    size += 1;
+                }
+            }
+            let mut lead = 0;
+            for i in 1..size {
+                if encoding.map_pawns[squares[i]] > encoding.map_pawns[squares[lead]] {
+                    lead = i;
+                }
+            }
+            squares.swap(0, lead);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+            lead_pawn = Some(pawn);
+        }
+        let lead_pawns = size;
+
+        let side = match dtz {
+            true => {
+                let flags = self.pairs[0][file].flags;
+                //a table with the same pieces on both sides and no pawns is the same either way
+                let either = self.symmetric && !self.has_pawns;
+                if flags & STM != stm as u8 && !either {
+                    return None;
+                }
+                0
+            }
+            false => stm,
+        };
+        let pairs = &self.pairs[side][file];
+
+        for (sq, code) in &position.pieces {
+            if Some(*code) != lead_pawn {
+                squares[size] = sq ^ flip_squares;
+                pieces[size] = code ^ flip_color;
+                size += 1;
+            }
+        }
+
+        //in the order the table has them
+        for i in lead_pawns..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if pairs.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        //the board is mirrored so that the leading piece is in the a1-d1-d4 triangle
+        if squares[0] % 8 > 3 {
+            for sq in squares.iter_mut().take(size) {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = encoding.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|sq| encoding.map_pawns[*sq]);
+            for (i, sq) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                idx += encoding.binomial[i][encoding.map_pawns[*sq]];
+            }
+        } else {
+            if squares[0] / 8 > 3 {
+                for sq in squares.iter_mut().take(size) {
+                    *sq ^= 56;
+                }
+            }
+            //and the first of the leading group off the diagonal is below it
+            for i in 0..pairs.group_len[0] {
+                match off_diagonal(squares[i]) {
+                    0 => continue,
+                    off if off > 0 => {
+                        for sq in squares.iter_mut().take(size).skip(i) {
+                            *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                        }
+                    }
+                    _ => {}
+                }
+                break;
+            }
+
+            idx = match self.has_unique_pieces {
+                //three unique pieces together, by where they are relative to the diagonal
+                true => {
+                    let [s0, s1, s2] = [squares[0], squares[1], squares[2]].map(|sq| sq as u64);
+                    let adjust1 = (s1 > s0) as u64;
+                    let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+                    let map_b1h1h7 = |sq: u64| encoding.map_b1h1h7[sq as usize] as u64;
+                    if off_diagonal(squares[0]) != 0 {
+                        (encoding.map_a1d1d4[squares[0]] as u64 * 63 + s1 - adjust1) * 62 + s2
+                            - adjust2
+                    } else if off_diagonal(squares[1]) != 0 {
+                        (6 * 63 + (s0 >> 3) * 28 + map_b1h1h7(s1)) * 62 + s2 - adjust2
+                    } else if off_diagonal(squares[2]) != 0 {
+                        6 * 63 * 62
+                            + 4 * 28 * 62
+                            + (s0 >> 3) * 7 * 28
+                            + ((s1 >> 3) - adjust1) * 28
+                            + map_b1h1h7(s2)
+                    } else {
+                        6 * 63 * 62
+                            + 4 * 28 * 62
+                            + 4 * 7 * 28
+                            + (s0 >> 3) * 7 * 6
+                            + ((s1 >> 3) - adjust1) * 6
+                            + ((s2 >> 3) - adjust2)
+                    }
+                }
+                //or else just the kings
+                false => encoding.map_kk[encoding.map_a1d1d4[squares[0]]][squares[1]] as u64,
+            };
+        }
+
+        //the other groups each by the squares left for them, those of the other side's pawns being off the end ranks
+        idx *= pairs.group_idx[0];
+        let mut start = pairs.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while pairs.group_len[next] > 0 {
+            let len = pairs.group_len[next];
+            squares[start..start + len].sort();
+            let mut n = 0;
+            for i in 0..len {
+                let sq = squares[start + i];
+                let adjust = squares[..start].iter().filter(|other| sq > **other).count();
+                n += encoding.binomial[i + 1][sq - adjust - 8 * remaining_pawns as usize];
+            }
+            remaining_pawns = false;
+            idx += n * pairs.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        Some((side, file, idx))
+    }
+
+    //split the pieces into groups, which are encoded in the order the file gives
+    fn set_groups(&mut self, side: usize, file: usize, order: [usize; 2]) {
+        let encoding = Encoding::get();
+        let pairs = &mut self.pairs[side][file];
+        let mut first_len: i64 = match (self.has_pawns, self.has_unique_pieces) {
+            (true, _) => 0,
+            (false, true) => 3,
+            (false, false) => 2,
+        };
+        let mut n = 0;
+        pairs.group_len[0] = 1;
+        for i in 1..self.num_pieces {
+            first_len -= 1;
+            if first_len > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+                pairs.group_len[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        pairs.group_len[n] = 0;
+
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1 + both_pawns as usize;
+        let mut free = 64 - pairs.group_len[0] - both_pawns as usize * pairs.group_len[1];
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                //the leading pawns or pieces
+                pairs.group_idx[0] = idx;
+                idx *= match (self.has_pawns, self.has_unique_pieces) {
+                    (true, _) => encoding.lead_pawns_size[pairs.group_len[0]][file],
+                    (false, true) => 31332,
+                    (false, false) => 462,
+                };
+            } else if k == order[1] {
+                //the other side's pawns
+                pairs.group_idx[1] = idx;
+                idx *= encoding.binomial[pairs.group_len[1]][48 - pairs.group_len[0]];
+            } else {
+                pairs.group_idx[next] = idx;
+                idx *= encoding.binomial[pairs.group_len[next]][free];
+                free -= pairs.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        pairs.group_idx[n] = idx;
+    }
+}
+
+//a position as the tables see it
+struct Position {
+    name: String, //the material, white first
+    black_to_move: bool,
+    pieces: Vec<(usize, u8)>, //squares and piece codes, by square
+}
+
+impl Position {
+    fn of(board: &Board) -> Option<Self> {
+        let mut pieces = vec![];
+        for (sq, piece) in board.get_pieces() {
+            pieces.push((sq.idx, piece_code(piece)?));
+        }
+        pieces.sort();
+        let side = |black: bool| {
+            side_name(
+                &pieces
+                    .iter()
+                    .map(|(_sq, code)| *code)
+                    .filter(|code| (code & 8 != 0) == black)
+                    .collect::<Vec<_>>(),
+            )
+        };
+        Some(Self {
+            name: format!("{}v{}", side(false), side(true)),
+            black_to_move: board.get_turn() == Team::Black,
+            pieces,
+        })
+    }
+}
+
+//how the position ends for the side to move, if the fifty move rule is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss, //a loss, but for the fifty move rule
+    Draw,
+    CursedWin, //a win, but for the fifty move rule
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i64) -> Self {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn value(self) -> i64 {
+        self as i64 - 2
+    }
+
+    fn flip(self) -> Self {
+        Self::from_value(-self.value())
+    }
+
+    //the dtz of a position whose best move is a capture or pawn move with this result
+    fn dtz_before_zeroing(self) -> i64 {
+        match self {
+            Wdl::Loss => -1,
+            Wdl::BlessedLoss => -101,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 101,
+            Wdl::Win => 1,
+        }
+    }
+
+    pub fn score(self, move_num: usize) -> Score {
+        match self {
+            Wdl::Loss | Wdl::BlessedLoss => Score::Lost(move_num + UNKNOWN_PLIES),
+            Wdl::Draw => Score::Draw(move_num),
+            Wdl::CursedWin | Wdl::Win => Score::Won(move_num + UNKNOWN_PLIES),
+        }
+    }
+}
+
+//captures and pawn moves reset the fifty move counter, so dtz counts to them
+fn is_zeroing(mv: &Move) -> bool {
+    match mv {
+        Move::Standard {
+            from_piece, victim, ..
+        } => victim.is_some() || matches!(from_piece.kind, PieceKind::Pawn(..)),
+        Move::EnCroissant { .. } => true,
+        Move::Castle { .. } | Move::Drop { .. } => false,
+    }
+}
+
+fn is_capture(mv: &Move) -> bool {
+    matches!(
+        mv,
+        Move::Standard {
+            victim: Some(_),
+            ..
+        } | Move::EnCroissant { .. }
+    )
+}
+
+//the tables for a material, read when first needed
+struct Entry {
+    name: String, //as the files are named, which may have black's pieces first
+    wdl: PathBuf,
+    dtz: Option<PathBuf>,
+    wdl_table: OnceLock<Option<Table>>,
+    dtz_table: OnceLock<Option<Table>>,
+}
+
+impl Entry {
+    fn table(&self, dtz: bool) -> Option<&Table> {
+        let (path, table) = match dtz {
+            false => (Some(&self.wdl), &self.wdl_table),
+            true => (self.dtz.as_ref(), &self.dtz_table),
+        };
+        table
+            .get_or_init(|| {
+                let path = path?;
+                match std::fs::read(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| Table::new(&self.name, bytes, dtz))
+                {
+                    Ok(table) => Some(table),
+                    Err(e) => {
+                        eprintln!("{}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+}
+
+pub struct Syzygy {
+    entries: HashMap<String, std::sync::Arc<Entry>>, //by the material with either side first
+    max_pieces: usize,
+}
+
+impl std::fmt::Debug for Syzygy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Syzygy")
+            .field("tables", &self.entries.len())
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+impl Syzygy {
+    //whether the tables are for games on the signature, which must be that of classical chess
+    pub fn supports(signature: &Signature) -> bool {
+        signature.fingerprint() == crate::classical::create_signature().fingerprint()
+    }
+
+    //every wdl table in the directory, along with the dtz tables next to them
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let mut syzygy = Self {
+            entries: HashMap::new(),
+            max_pieces: 0,
+        };
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("{}: {}", dir.display(), e))?
+                .path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(WDL_EXTENSION) {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let (white, black) = match name.split_once('v') {
+                Some(sides) => sides,
+                None => continue,
+            };
+            let valid = |side: &str| {
+                side.starts_with('K') && side.chars().all(|c| PIECE_LETTERS.contains(c))
+            };
+            if !valid(white) || !valid(black) || white.len() + black.len() > MAX_PIECES {
+                continue;
+            }
+            let dtz = path.with_extension(DTZ_EXTENSION);
+            let entry = std::sync::Arc::new(Entry {
+                name: name.clone(),
+                wdl: path.clone(),
+                dtz: dtz.exists().then_some(dtz),
+                wdl_table: OnceLock::new(),
+                dtz_table: OnceLock::new(),
+            });
+            syzygy.max_pieces = syzygy.max_pieces.max(white.len() + black.len());
+            syzygy
+                .entries
+                .insert(format!("{}v{}", black, white), entry.clone());
+            syzygy.entries.insert(name, entry);
+        }
+        if syzygy.entries.is_empty() {
+            return Err(format!("{}: no syzygy tables", dir.display()));
+        }
+        Ok(syzygy)
+    }
+
+    //whether the position could be in the tables
+    fn covers(&self, board: &Board) -> bool {
+        board.white_pieces.len() + board.black_pieces.len() <= self.max_pieces
+            && board.white_pocket.is_empty()
+            && board.black_pocket.is_empty()
+            && !tablebase::can_castle(board)
+    }
+
+    //the value the table stores for the position, None if it is not in the tables
+    //and Some(None) if the position is in a dtz table only stored for the other side to move
+    fn probe_table(&self, position: &Position, dtz: Option<Wdl>) -> Option<Option<i64>> {
+        let entry = self.entries.get(&position.name)?;
+        let table = entry.table(dtz.is_some())?;
+
+        //the tables are for white as the stronger side, and for white to move when both sides are the same
+        //so otherwise the colours are swapped and the board turned over
+        let flip = position.name != entry.name || (table.symmetric && position.black_to_move);
+        let (side, file, idx) = match table.index(position, flip, dtz.is_some()) {
+            Some(index) => index,
+            None => return Some(None),
+        };
+        let pairs = &table.pairs[side][file];
+        let value = pairs.decompress(&table.bytes, idx)? as i64;
+        Some(Some(match dtz {
+            None => value - 2,
+            Some(wdl) => {
+                let flags = pairs.flags;
+                let mut value = value;
+                if flags & MAPPED != 0 {
+                    let map = pairs.map_idx[match wdl {
+                        Wdl::Win | Wdl::Draw => 0,
+                        Wdl::Loss => 1,
+                        Wdl::CursedWin => 2,
+                        Wdl::BlessedLoss => 3,
+                    }] + value as usize;
+                    value = match flags & WIDE != 0 {
+                        true => u16_le(&table.bytes, table.map + 2 * map) as i64,
+                        false => byte(&table.bytes, table.map + map) as i64,
+                    };
+                }
+                //in plies, which some tables store as moves
+                let in_moves = match wdl {
+                    Wdl::Win => flags & WIN_PLIES == 0,
+                    Wdl::Loss => flags & LOSS_PLIES == 0,
+                    Wdl::Draw => false,
+                    Wdl::CursedWin | Wdl::BlessedLoss => true,
+                };
+                match in_moves {
+                    true => 2 * value + 1,
+                    false => value + 1,
+                }
+            }
+        }))
+    }
+
+    //the result with the captures searched first, and with dtz the pawn moves too, and whether one of them is best
+    //a table is not looked at if every move has been searched, since it may be wrong with an en passant capture
+    fn search(&self, board: &mut Board, zeroing: bool) -> Option<(Wdl, bool)> {
+        let moves = BoardData::by_rules(board, &evaluation::DEFAULT_PARAMS)
+            .get_moves()
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for mv in &moves {
+            if !(is_capture(mv) || zeroing && is_zeroing(mv)) {
+                continue;
+            }
+            searched += 1;
+            board.make_move(*mv);
+            let value = self.search(board, false);
+            board.unmake_move().unwrap();
+            let value = value?.0.flip();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = match all_searched {
+            true => best,
+            false => self.probe_wdl_table(board)?,
+        };
+        match best >= value {
+            true => Some((best, best > Wdl::Draw || all_searched)),
+            false => Some((value, false)),
+        }
+    }
+
+    fn probe_wdl_table(&self, board: &Board) -> Option<Wdl> {
+        if board.white_pieces.len() + board.black_pieces.len() == 2 {
+            return Some(Wdl::Draw);
+        }
+        let position = Position::of(board)?;
+        self.probe_table(&position, None)?.map(Wdl::from_value)
+    }
+
+    //how the position ends for the side to move
+    pub fn probe_wdl(&self, board: &mut Board) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.search(board, false).map(|(wdl, _zeroing)| wdl)
+    }
+
+    //how many plies until the next capture or pawn move with best play, negative if the side to move is losing
+    //and 0 for a draw, with 100 added for a win or loss the fifty move rule would spoil
+    pub fn probe_dtz(&self, board: &mut Board) -> Option<i64> {
+        if !self.covers(board) {
+            return None;
+        }
+        let (wdl, zeroing) = self.search(board, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(wdl.dtz_before_zeroing());
+        }
+        let sign = wdl.value().signum();
+        let position = Position::of(board)?;
+        if let Some(dtz) = self.probe_table(&position, Some(wdl))? {
+            let rule = match wdl {
+                Wdl::CursedWin | Wdl::BlessedLoss => 100,
+                _ => 0,
+            };
+            return Some((dtz + rule) * sign);
+        }
+
+        //the table is for the other side to move, so the best dtz is found a ply further on
+        let data = BoardData::by_rules(board, &evaluation::DEFAULT_PARAMS);
+        let mut min_dtz = i64::MAX;
+        for mv in data.get_moves() {
+            board.make_move(*mv);
+            let dtz = self.dtz_after(board, is_zeroing(mv));
+            board.unmake_move().unwrap();
+            let dtz = dtz?;
+            if dtz.signum() == sign && dtz < min_dtz {
+                min_dtz = dtz;
+            }
+        }
+        //with no moves the side to move is mated
+        Some(match min_dtz {
+            i64::MAX => -1,
+            min_dtz => min_dtz,
+        })
+    }
+
+    //the dtz before the move just made on the board, from the side which made it
+    fn dtz_after(&self, board: &mut Board, zeroing: bool) -> Option<i64> {
+        let dtz = match zeroing {
+            //only the result matters, as the count starts again
+            true => -self.search(board, false)?.0.dtz_before_zeroing(),
+            false => match -self.probe_dtz(board)? {
+                dtz if dtz > 0 => dtz + 1,
+                dtz if dtz < 0 => dtz - 1,
+                dtz => dtz,
+            },
+        };
+        //a mate is as near to the end as can be
+        if dtz == 2 {
+            let data = BoardData::by_rules(board, &evaluation::DEFAULT_PARAMS);
+            if data.is_check() && data.is_terminal() {
+                return Some(1);
+            }
+        }
+        Some(dtz)
+    }
+
+    //the exact result of the position, if it is in the tables
+    pub fn probe(&self, board: &mut Board) -> Option<Score> {
+        self.probe_wdl(board)
+            .map(|wdl| wdl.score(board.get_move_num()))
+    }
+
+    //which of the moves to play, winning by the shortest dtz and losing by the longest, along with the result
+    //every move which keeps a win brings the next capture or pawn move nearer, so the win is never let slip
+    pub fn choose_move(&self, board: &mut Board, moves: &[Move]) -> Option<(usize, Score)> {
+        let score = self.probe(board)?;
+        let mut best = None;
+        for (idx, mv) in moves.iter().enumerate() {
+            board.make_move(*mv);
+            let dtz = self.dtz_after(board, is_zeroing(mv));
+            board.unmake_move().unwrap();
+            let rank = match dtz? {
+                dtz if dtz > 0 => (2, -dtz),
+                0 => (1, 0),
+                dtz => (0, -dtz),
+            };
+            if best.is_none_or(|(_idx, best)| rank > best) {
+                best = Some((idx, rank));
+            }
+        }
+        best.map(|(idx, _rank)| (idx, score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::fen::from_fen;
+
+    //what a part of a made up table holds: the same value everywhere, or a value for each index
+    enum Part {
+        Single(u8),
+        Values(Vec<u8>),
+    }
+
+    //a table file in the syzygy format, with the pieces in the order given and every part coded with
+    //three values as codes of 3, 3 and 2 bits and the pair of the first two as a code of 1 bit
+    fn write_table(
+        name: &str,
+        dtz: bool,
+        pieces: &[u8],
+        part: impl Fn(usize, usize, u64) -> Part,
+    ) -> Vec<u8> {
+        const BLOCK_BITS: usize = 512;
+        const SPAN: u64 = 16;
+        let mut table = Table::for_material(name);
+        let sides = match !dtz && !table.symmetric {
+            true => 2,
+            false => 1,
+        };
+        let files = match table.has_pawns {
+            true => 4,
+            false => 1,
+        };
+        let both_pawns = table.has_pawns && table.pawn_count[1] > 0;
+        let mut bytes = match dtz {
+            false => WDL_MAGIC.to_vec(),
+            true => DTZ_MAGIC.to_vec(),
+        };
+        bytes.push(!table.symmetric as u8 | (table.has_pawns as u8) << 1);
+        for file in 0..files {
+            bytes.push(0);
+            if both_pawns {
+                bytes.push(0x11);
+            }
+            bytes.extend(pieces.iter().map(|code| code | code << 4));
+            for side in 0..sides {
+                table.pairs[side][file].pieces[..pieces.len()].copy_from_slice(pieces);
+                table.set_groups(side, file, [0, 1 + 14 * !both_pawns as usize]);
+            }
+        }
+        if bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+
+        //each part's values as blocks of codes, with the number of values in each
+        let mut parts = vec![];
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &table.pairs[side][file];
+                let size =
+                    pairs.group_idx[pairs.group_len.iter().position(|len| *len == 0).unwrap()];
+                match part(side, file, size) {
+                    Part::Single(value) => {
+                        bytes.extend([SINGLE_VALUE, value]);
+                        parts.push(None);
+                    }
+                    Part::Values(values) => {
+                        let mut leaves = values.clone();
+                        leaves.sort();
+                        leaves.dedup();
+                        assert!(leaves.len() <= 3);
+                        leaves.resize(3, leaves[0]);
+                        let mut blocks: Vec<(Vec<bool>, usize)> = vec![(vec![], 0)];
+                        let mut idx = 0;
+                        while idx < values.len() {
+                            let (bits, count) = match values.get(idx..idx + 2) {
+                                Some([a, b]) if *a == leaves[0] && *b == leaves[1] => {
+                                    (vec![true], 2)
+                                }
+                                _ if values[idx] == leaves[0] => (vec![false, false, false], 1),
+                                _ if values[idx] == leaves[1] => (vec![false, false, true], 1),
+                                _ => (vec![false, true], 1),
+                            };
+                            if blocks.last().unwrap().0.len() + bits.len() > BLOCK_BITS {
+                                blocks.push((vec![], 0));
+                            }
+                            let block = blocks.last_mut().unwrap();
+                            block.0.extend(bits);
+                            block.1 += count;
+                            idx += count;
+                        }
+                        bytes.extend([0, 6, SPAN.trailing_zeros() as u8, 0]);
+                        bytes.extend((blocks.len() as u32).to_le_bytes());
+                        bytes.extend([3, 1]);
+                        for lowest in [3u16, 2, 0] {
+                            bytes.extend(lowest.to_le_bytes());
+                        }
+                        bytes.extend(4u16.to_le_bytes());
+                        for (left, right) in [
+                            (leaves[0] as usize, 0xfff),
+                            (leaves[1] as usize, 0xfff),
+                            (leaves[2] as usize, 0xfff),
+                            (0, 1),
+                        ] {
+                            bytes.extend([
+                                left as u8,
+                                (left >> 8) as u8 | ((right & 0xf) << 4) as u8,
+                                (right >> 4) as u8,
+                            ]);
+                        }
+                        parts.push(Some((size, blocks)));
+                    }
+                }
+            }
+        }
+        if dtz && bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+
+        //the sparse index points at the value in the middle of each span
+        for (size, blocks) in parts.iter().flatten() {
+            for k in 0..size.div_ceil(SPAN) {
+                let mut idx = (k * SPAN + SPAN / 2) as usize;
+                let mut block = 0;
+                while block + 1 < blocks.len() && idx >= blocks[block].1 {
+                    idx -= blocks[block].1;
+                    block += 1;
+                }
+                bytes.extend((block as u32).to_le_bytes());
+                bytes.extend((idx as u16).to_le_bytes());
+            }
+        }
+        for (_size, blocks) in parts.iter().flatten() {
+            for (_bits, count) in blocks {
+                bytes.extend((*count as u16 - 1).to_le_bytes());
+            }
+        }
+        for (_size, blocks) in parts.iter().flatten() {
+            bytes.resize((bytes.len() + 0x3f) & !0x3f, 0);
+            for (bits, _count) in blocks {
+                let mut block = vec![0u8; BLOCK_BITS / 8];
+                for (i, bit) in bits.iter().enumerate() {
+                    block[i / 8] |= (*bit as u8) << (7 - i % 8);
+                }
+                bytes.extend(block);
+            }
+        }
+        bytes
+    }
+
+    fn load(files: &[(String, Vec<u8>)]) -> Syzygy {
+        let dir =
+            std::env::temp_dir().join(format!("syzygy-test-{}-{}", std::process::id(), files[0].0));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, bytes) in files {
+            std::fs::write(dir.join(name), bytes).unwrap();
+        }
+        let syzygy = Syzygy::load(&dir).unwrap();
+        //the tables are read as soon as they are needed, and then kept
+        for entry in syzygy.entries.values() {
+            entry.table(false);
+            entry.table(true);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        syzygy
+    }
+
+    //the eight ways of turning the board over, or the two of mirroring it with pawns
+    fn symmetries(pawns: bool) -> Vec<fn(usize) -> usize> {
+        let mut symmetries: Vec<fn(usize) -> usize> = vec![|sq| sq, |sq| sq ^ 7];
+        if !pawns {
+            symmetries.extend([
+                (|sq| sq ^ 56) as fn(usize) -> usize,
+                |sq| sq ^ 63,
+                |sq| (sq >> 3) | ((sq & 7) << 3),
+                |sq| ((sq >> 3) | ((sq & 7) << 3)) ^ 7,
+                |sq| ((sq >> 3) | ((sq & 7) << 3)) ^ 56,
+                |sq| ((sq >> 3) | ((sq & 7) << 3)) ^ 63,
+            ]);
+        }
+        symmetries
+    }
+
+    #[test]
+    fn test_encoding() {
+        let encoding = Encoding::get();
+        let kings = encoding.map_kk.iter().flatten().max().unwrap();
+        assert_eq!(*kings, 461);
+        assert_eq!(encoding.binomial[3][10], 120);
+        for file in 0..4 {
+            assert_eq!(encoding.lead_pawns_size[1][file], 6);
+        }
+        let mut pawns = (8..56).map(|sq| encoding.map_pawns[sq]).collect::<Vec<_>>();
+        pawns.sort();
+        assert_eq!(pawns, (0..48).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_index() {
+        //every value is read back, and positions which are the same but for turning the board over share an index
+        //while those which are not do not
+        let value = |side: usize, idx: u64| [0, 2, 4, 0, 2, 2, 4][(idx as usize + 3 * side) % 7];
+        let krk = write_table("KRvK", false, &[6, 4, 14], |side, _file, size| {
+            Part::Values((0..size).map(|idx| value(side, idx)).collect())
+        });
+        let syzygy = load(&[("KRvK.rtbw".to_string(), krk)]);
+        let table = syzygy.entries["KRvK"].table(false).unwrap();
+        for side in 0..2 {
+            let pairs = &table.pairs[side][0];
+            assert_eq!(pairs.group_idx[1], 31332);
+            for idx in 0..31332 {
+                assert_eq!(
+                    pairs.decompress(&table.bytes, idx),
+                    Some(value(side, idx) as usize)
+                );
+            }
+        }
+
+        let mut canonical = HashMap::new();
+        for white_king in 0..64 {
+            for rook in 0..64 {
+                for black_king in 0..64 {
+                    if white_king == rook || rook == black_king || white_king == black_king {
+                        continue;
+                    }
+                    let position = |f: fn(usize) -> usize| Position {
+                        name: "KRvK".to_string(),
+                        black_to_move: false,
+                        pieces: vec![(f(white_king), 6), (f(rook), 4), (f(black_king), 14)],
+                    };
+                    let (_side, _file, idx) =
+                        table.index(&position(|sq| sq), false, false).unwrap();
+                    assert!(idx < 31332);
+                    let same = symmetries(false)
+                        .into_iter()
+                        .map(|f| {
+                            let mut pieces = position(f).pieces;
+                            pieces.sort();
+                            pieces
+                        })
+                        .min()
+                        .unwrap();
+                    for f in symmetries(false) {
+                        assert_eq!(table.index(&position(f), false, false).unwrap().2, idx);
+                    }
+                    assert_eq!(*canonical.entry(idx).or_insert(same.clone()), same);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pawns() {
+        //with pawns the board is only mirrored, and with black the stronger side the colours are swapped
+        for (name, pieces) in [("KPvK", vec![1, 6, 14]), ("KPvKP", vec![1, 9, 6, 14])] {
+            let file = write_table(name, false, &pieces, |side, file, size| {
+                Part::Values(
+                    (0..size)
+                        .map(|idx| [0, 2, 4][(idx as usize * 7 + side + file) % 11 % 3])
+                        .collect(),
+                )
+            });
+            let syzygy = load(&[(format!("{}.rtbw", name), file)]);
+            let mut positions = 0;
+            for white_king in (0..64).step_by(3) {
+                for black_king in (1..64).step_by(5) {
+                    for white_pawn in 8..56 {
+                        for black_pawn in (8..56).step_by(match pieces.len() {
+                            3 => 64,
+                            _ => 3,
+                        }) {
+                            let mut squares = vec![white_king, black_king, white_pawn];
+                            let mut codes = vec![6, 14, 1];
+                            if pieces.len() == 4 {
+                                squares.push(black_pawn);
+                                codes.push(9);
+                            }
+                            let mut unique = squares.clone();
+                            unique.sort();
+                            unique.dedup();
+                            if unique.len() < squares.len() {
+                                continue;
+                            }
+                            for black_to_move in [false, true] {
+                                let position = |f: &dyn Fn(usize, u8) -> (usize, u8), turn| {
+                                    let mut pieces = squares
+                                        .iter()
+                                        .zip(&codes)
+                                        .map(|(sq, code)| f(*sq, *code))
+                                        .collect::<Vec<_>>();
+                                    pieces.sort();
+                                    let side = |black: bool| {
+                                        side_name(
+                                            &pieces
+                                                .iter()
+                                                .map(|(_sq, code)| *code)
+                                                .filter(|code| (code & 8 != 0) == black)
+                                                .collect::<Vec<_>>(),
+                                        )
+                                    };
+                                    Position {
+                                        name: format!("{}v{}", side(false), side(true)),
+                                        black_to_move: turn,
+                                        pieces,
+                                    }
+                                };
+                                let value = syzygy
+                                    .probe_table(
+                                        &position(&|sq, code| (sq, code), black_to_move),
+                                        None,
+                                    )
+                                    .unwrap();
+                                let mirrored = position(&|sq, code| (sq ^ 7, code), black_to_move);
+                                let swapped =
+                                    position(&|sq, code| (sq ^ 56, code ^ 8), !black_to_move);
+                                assert!(value.is_some());
+                                assert_eq!(syzygy.probe_table(&mirrored, None).unwrap(), value);
+                                assert_eq!(syzygy.probe_table(&swapped, None).unwrap(), value);
+                                positions += 1;
+                            }
+                        }
+                    }
+                }
+            }
+            assert!(positions > 1000);
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        //made up tables in which the side with the queen always wins, and has always a move left before zeroing
+        let wdl = write_table("KQvK", false, &[6, 5, 14], |side, _file, _size| {
+            Part::Single([4, 0][side])
+        });
+        let dtz = write_table("KQvK", true, &[6, 5, 14], |_side, _file, _size| {
+            Part::Single(1)
+        });
+        let syzygy = std::sync::Arc::new(load(&[
+            ("KQvK.rtbw".to_string(), wdl),
+            ("KQvK.rtbz".to_string(), dtz),
+        ]));
+        assert!(Syzygy::supports(&crate::classical::create_signature()));
+        assert!(Syzygy::load(Path::new("/nonexistent/syzygy")).is_err());
+
+        for (fen, score) in [
+            ("k7/8/1K6/8/8/8/8/7Q w - - 0 1", Score::Won(UNKNOWN_PLIES)),
+            ("k7/8/1K6/8/8/8/8/7Q b - - 0 1", Score::Lost(UNKNOWN_PLIES)),
+            ("K7/8/1k6/8/8/8/8/7q b - - 0 1", Score::Won(UNKNOWN_PLIES)),
+            ("K7/8/1k6/8/8/8/8/7q w - - 0 1", Score::Lost(UNKNOWN_PLIES)),
+            //the queen is taken
+            ("k7/1Q6/8/8/8/8/8/7K b - - 0 1", Score::Draw(0)),
+        ] {
+            let mut board = from_fen(fen).unwrap();
+            board.set_syzygy(syzygy.clone());
+            assert_eq!(
+                BoardData::new(&mut board).get_evaluation(),
+                score,
+                "{}",
+                fen
+            );
+        }
+
+        //of the moves which keep the win the mates are nearest to zeroing
+        let mut board = from_fen("k7/8/1K6/8/8/8/8/7Q w - - 0 1").unwrap();
+        let moves = BoardData::new(&mut board)
+            .get_moves()
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        let (idx, score) = syzygy.choose_move(&mut board, &moves).unwrap();
+        assert_eq!(score, Score::Won(UNKNOWN_PLIES));
+        board.make_move(moves[idx]);
+        let data = BoardData::new(&mut board);
+        assert!(data.is_check() && data.is_terminal());
+    }
+
+    #[test]
+    fn test_compressed() {
+        //a king and queen against a king win unless the queen is taken or the king stalemated, which is written
+        //out position by position so the probes go through huffman coded values as a real table's do
+        let pieces = [6, 5, 14];
+        let blank = load(&[(
+            "KQvK.rtbw".to_string(),
+            write_table("KQvK", false, &pieces, |_side, _file, _size| {
+                Part::Single(0)
+            }),
+        )]);
+        let blank = blank.entries["KQvK"].table(false).unwrap();
+        let pairs = &blank.pairs[1][0];
+        let mut values = vec![
+            0;
+            pairs.group_idx[pairs.group_len.iter().position(|len| *len == 0).unwrap()]
+                as usize
+        ];
+        //the squares next to a square, and those the white pieces attack through the black king
+        let near = |a: usize, b: usize| {
+            a != b && (a % 8).abs_diff(b % 8) <= 1 && (a / 8).abs_diff(b / 8) <= 1
+        };
+        let attacked = |sq: usize, white_king: usize, queen: usize| {
+            let (df, dr) = (
+                (sq % 8) as i64 - (queen % 8) as i64,
+                (sq / 8) as i64 - (queen / 8) as i64,
+            );
+            let line = sq != queen
+                && (df == 0 || dr == 0 || df.abs() == dr.abs())
+                && (1..df.abs().max(dr.abs())).all(|step| {
+                    queen as i64 + step * (df.signum() + 8 * dr.signum()) != white_king as i64
+                });
+            near(sq, white_king) || line
+        };
+        for white_king in 0..64 {
+            for queen in 0..64 {
+                for black_king in 0..64 {
+                    if white_king == queen || queen == black_king || white_king == black_king {
+                        continue;
+                    }
+                    let position = Position {
+                        name: "KQvK".to_string(),
+                        black_to_move: true,
+                        pieces: vec![(white_king, 6), (queen, 5), (black_king, 14)],
+                    };
+                    let (_side, _file, idx) = blank.index(&position, false, false).unwrap();
+                    let stalemated = !attacked(black_king, white_king, queen)
+                        && (0..64)
+                            .all(|sq| !near(sq, black_king) || attacked(sq, white_king, queen));
+                    let takes = near(black_king, queen) && !near(queen, white_king);
+                    values[idx as usize] = match stalemated || takes {
+                        true => 2,
+                        false => 0,
+                    };
+                }
+            }
+        }
+        let wdl = write_table("KQvK", false, &pieces, |side, _file, _size| match side {
+            0 => Part::Single(4),
+            _ => Part::Values(values.clone()),
+        });
+        let syzygy = load(&[("KQvK.rtbw".to_string(), wdl)]);
+
+        for (fen, wdl) in [
+            ("k7/8/1K6/8/8/8/8/7Q w - - 0 1", Wdl::Win),
+            ("k7/8/2K5/8/8/8/8/1Q6 b - - 0 1", Wdl::Loss),
+            ("K7/8/2k5/8/8/8/8/1q6 w - - 0 1", Wdl::Loss),
+            //stalemated, which only the table tells
+            ("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1", Wdl::Draw),
+            ("8/8/8/8/8/1k6/2q5/K7 w - - 0 1", Wdl::Draw),
+            //the queen is taken
+            ("k7/1Q6/8/8/8/8/8/7K b - - 0 1", Wdl::Draw),
+        ] {
+            let mut board = from_fen(fen).unwrap();
+            assert_eq!(syzygy.probe_wdl(&mut board), Some(wdl), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_files() {
+        //real tables are too big to keep with the code, so beyond the small ones checked in under testdata/syzygy
+        //they are read from SYZYGY_DIR when it is set
+        let (dir, names) = match std::env::var_os("SYZYGY_DIR") {
+            Some(dir) => (std::path::PathBuf::from(dir), vec!["KQvK", "KRvK"]),
+            None => (
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/syzygy"),
+                vec!["KQvK"],
+            ),
+        };
+        if !dir.join("KQvK.rtbw").exists() || !dir.join("KQvK.rtbz").exists() {
+            eprintln!(
+                "no tables in {}, so the real tables are not tested",
+                dir.display()
+            );
+            return;
+        }
+        let syzygy = Syzygy::load(&dir).unwrap();
+        for name in &names {
+            let entry = &syzygy.entries[*name];
+            assert!(
+                entry.table(false).is_some() && entry.table(true).is_some(),
+                "{}",
+                name
+            );
+        }
+
+        for (name, fen, wdl, dtz) in [
+            //mate in one
+            ("KQvK", "k7/8/1K6/8/8/8/8/7Q w - - 0 1", Wdl::Win, 1),
+            ("KRvK", "k7/8/1K6/8/8/8/8/7R w - - 0 1", Wdl::Win, 1),
+            //the only move is into mate
+            ("KQvK", "k7/8/2K5/8/8/8/8/1Q6 b - - 0 1", Wdl::Loss, -2),
+            ("KQvK", "K7/8/2k5/8/8/8/8/1q6 w - - 0 1", Wdl::Loss, -2),
+            //stalemated
+            ("KQvK", "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1", Wdl::Draw, 0),
+            //the queen is taken
+            ("KQvK", "k7/1Q6/8/8/8/8/8/7K b - - 0 1", Wdl::Draw, 0),
+            //the rook is taken
+            ("KRvK", "k7/1R6/8/8/8/8/8/7K b - - 0 1", Wdl::Draw, 0),
+        ] {
+            if !names.contains(&name) {
+                continue;
+            }
+            let mut board = from_fen(fen).unwrap();
+            assert_eq!(syzygy.probe_wdl(&mut board), Some(wdl), "{}", fen);
+            assert_eq!(syzygy.probe_dtz(&mut board), Some(dtz), "{}", fen);
+        }
+    }
+}
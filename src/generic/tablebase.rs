@@ -0,0 +1,964 @@
+use std::path::{Path, PathBuf};
+
+use super::board_data::BoardData;
+use super::score::Score;
+use super::signature::Signature;
+use super::*;
+
+//endgame tablebases, built by retrograde analysis for whatever signature the game is played on
+//
+//a table holds, for every way of placing a set of pieces with either side to move, whether the side to move wins,
+//loses or draws and in how many plies the game is won with best play
+//tables are built with the same move generation as the search, so fairy pieces, other boards and the rules of
+//atomic, antichess and king of the hill are all followed, and the search looks them up to score positions exactly
+//
+//positions are taken to have no castling rights and no en passant capture, and positions with either are not looked up
+//positions the board's symmetries map onto each other share an entry, and kings which could never touch are not
+//placed touching, so a table of five pieces on an 8x8 board without pawns has about 240 million entries
+//building a table keeps only those entries in memory, generating the moves of the positions again on each pass
+
+const EXTENSION: &str = "tb";
+const MAGIC: &[u8; 4] = b"TBL2";
+
+//how a position ends with best play, and in how many plies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Outcome {
+    Draw,
+    Lost(u16),
+    Won(u16),
+}
+
+impl Outcome {
+    //0 for a draw, then odd for losses and even for wins
+    fn pack(self) -> u16 {
+        match self {
+            Outcome::Draw => 0,
+            Outcome::Lost(plies) => 1 + 2 * plies,
+            Outcome::Won(plies) => 2 + 2 * plies,
+        }
+    }
+
+    fn unpack(entry: u16) -> Self {
+        match entry {
+            0 => Outcome::Draw,
+            entry if entry % 2 == 1 => Outcome::Lost((entry - 1) / 2),
+            entry => Outcome::Won((entry - 2) / 2),
+        }
+    }
+
+    fn score(self, move_num: usize) -> Score {
+        match self {
+            Outcome::Draw => Score::Draw(move_num),
+            Outcome::Lost(plies) => Score::Lost(move_num + plies as usize),
+            Outcome::Won(plies) => Score::Won(move_num + plies as usize),
+        }
+    }
+
+    fn plies(self) -> u16 {
+        match self {
+            Outcome::Draw => 0,
+            Outcome::Lost(plies) | Outcome::Won(plies) => plies,
+        }
+    }
+}
+
+//kings first, so that tables are named the usual way
+fn kind_order(kind: &PieceKind) -> (bool, PieceKind) {
+    (*kind != PieceKind::King, *kind)
+}
+
+//pawns are the same piece whether or not they may be taken en passant
+fn normalise(kind: PieceKind) -> PieceKind {
+    match kind {
+        PieceKind::Pawn(..) => PieceKind::Pawn(EnCroissantable::No),
+        kind => kind,
+    }
+}
+
+//the pieces of each side, in the order their squares make up a table's index
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Material {
+    white: Vec<PieceKind>,
+    black: Vec<PieceKind>,
+}
+
+impl Material {
+    pub fn new(white: Vec<PieceKind>, black: Vec<PieceKind>) -> Self {
+        let sorted = |kinds: Vec<PieceKind>| {
+            let mut kinds = kinds.into_iter().map(normalise).collect::<Vec<_>>();
+            kinds.sort_by_key(kind_order);
+            kinds
+        };
+        Self {
+            white: sorted(white),
+            black: sorted(black),
+        }
+    }
+
+    //written the usual way, as in KRvK, with X and a number for the signature's custom pieces
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (white, black) = match text.split_once('v') {
+            Some(sides) => sides,
+            None => {
+                return Err(format!(
+                    "expected white and black pieces either side of 'v' in '{}'",
+                    text
+                ))
+            }
+        };
+        let side = |side: &str| -> Result<Vec<PieceKind>, String> {
+            let mut kinds = vec![];
+            let mut chars = side.chars().peekable();
+            while let Some(c) = chars.next() {
+                kinds.push(match c {
+                    'P' => PieceKind::Pawn(EnCroissantable::No),
+                    'G' => PieceKind::Grasshopper,
+                    'R' => PieceKind::Rook,
+                    'N' => PieceKind::Knight,
+                    'B' => PieceKind::Bishop,
+                    'Q' => PieceKind::Queen,
+                    'A' => PieceKind::Archbishop,
+                    'C' => PieceKind::Chancellor,
+                    'K' => PieceKind::King,
+                    'X' => {
+                        let mut digits = String::new();
+                        while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                            digits.push(digit);
+                        }
+                        match digits.parse() {
+                            Ok(idx) => PieceKind::Custom(idx),
+                            Err(_) => {
+                                return Err(format!(
+                                    "expected the number of a custom piece after X in '{}'",
+                                    text
+                                ))
+                            }
+                        }
+                    }
+                    c => return Err(format!("unknown piece '{}' in '{}'", c, text)),
+                });
+            }
+            Ok(kinds)
+        };
+        Ok(Self::new(side(white)?, side(black)?))
+    }
+
+    pub fn num_pieces(&self) -> usize {
+        self.white.len() + self.black.len()
+    }
+
+    fn pieces(&self) -> impl Iterator<Item = (Team, PieceKind)> + '_ {
+        self.white
+            .iter()
+            .map(|kind| (Team::White, *kind))
+            .chain(self.black.iter().map(|kind| (Team::Black, *kind)))
+    }
+
+    //the material on the board, and where each piece of it is
+    fn of(board: &Board) -> (Self, Vec<Square>) {
+        let side = |pieces: &BTreeMap<Square, Piece>| {
+            let mut pieces = pieces
+                .iter()
+                .map(|(sq, piece)| (normalise(piece.kind), *sq))
+                .collect::<Vec<_>>();
+            pieces.sort_by_key(|(kind, sq)| (kind_order(kind), *sq));
+            pieces
+        };
+        let white = side(&board.white_pieces);
+        let black = side(&board.black_pieces);
+        let squares = white
+            .iter()
+            .chain(black.iter())
+            .map(|(_kind, sq)| *sq)
+            .collect();
+        (
+            Self {
+                white: white.into_iter().map(|(kind, _sq)| kind).collect(),
+                black: black.into_iter().map(|(kind, _sq)| kind).collect(),
+            },
+            squares,
+        )
+    }
+
+    //the material a single move can leave, by capturing, by promoting or by both
+    //kings can only be lost in atomic, where captures take out several pieces at once, and in antichess
+    fn next(&self, signature: &Signature) -> Vec<Self> {
+        let pieces = self.pieces().collect::<Vec<_>>();
+        let removals = match signature.is_atomic() {
+            true => (1..1usize << pieces.len())
+                .map(|set| (0..pieces.len()).filter(|i| set >> i & 1 == 1).collect())
+                .collect::<Vec<Vec<usize>>>(),
+            false => (0..pieces.len())
+                .filter(|i| pieces[*i].1 != PieceKind::King || signature.is_antichess())
+                .map(|i| vec![i])
+                .collect(),
+        };
+        let promotions = |team: Team| {
+            let mut kinds = (0..signature.num())
+                .filter_map(|idx| signature.get_pawn_promotions(Square { idx }, team).as_ref())
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            kinds.sort();
+            kinds.dedup();
+            kinds
+        };
+
+        let mut next = vec![];
+        for removed in std::iter::once(vec![]).chain(removals) {
+            let left = (0..pieces.len())
+                .filter(|i| !removed.contains(i))
+                .map(|i| pieces[i])
+                .collect::<Vec<_>>();
+            let material = |left: &[(Team, PieceKind)]| {
+                let side = |team: Team| {
+                    left.iter()
+                        .filter(|(piece_team, _kind)| *piece_team == team)
+                        .map(|(_team, kind)| *kind)
+                        .collect()
+                };
+                Self::new(side(Team::White), side(Team::Black))
+            };
+            if !removed.is_empty() {
+                next.push(material(&left));
+            }
+            for (i, (team, kind)) in left.iter().enumerate() {
+                if matches!(kind, PieceKind::Pawn(..)) {
+                    for promotion in promotions(*team) {
+                        let mut promoted = left.clone();
+                        promoted[i] = (*team, promotion);
+                        next.push(material(&promoted));
+                    }
+                }
+            }
+        }
+        next.sort();
+        next.dedup();
+        next.retain(|material| material != self);
+        next
+    }
+}
+
+impl std::fmt::Display for Material {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let side = |kinds: &Vec<PieceKind>| {
+            kinds
+                .iter()
+                .map(|kind| match kind {
+                    PieceKind::Pawn(..) => String::from("P"),
+                    PieceKind::Grasshopper => String::from("G"),
+                    PieceKind::Rook => String::from("R"),
+                    PieceKind::Knight => String::from("N"),
+                    PieceKind::Bishop => String::from("B"),
+                    PieceKind::Queen => String::from("Q"),
+                    PieceKind::Archbishop => String::from("A"),
+                    PieceKind::Chancellor => String::from("C"),
+                    PieceKind::King => String::from("K"),
+                    PieceKind::Custom(idx) => format!("X{}", idx),
+                })
+                .collect::<String>()
+        };
+        write!(f, "{}v{}", side(&self.white), side(&self.black))
+    }
+}
+
+//how the positions of a material are laid out in its table
+//a position's entry is found from the least of the ways the symmetries place its pieces, alike pieces in order of
+//their squares, and comparing first the leading pieces, which are the two kings when each side has one
+//the side to move comes first in the index, then the leading pieces as one of the ways they may stand, then the
+//square of each other piece in turn
+#[derive(Debug)]
+struct Layout {
+    num: usize,
+    kinds: Vec<(Team, PieceKind)>,
+    symmetries: std::sync::Arc<Vec<Vec<Square>>>,
+    order: Vec<usize>, //the pieces in the order they are compared and indexed, the leading ones first
+    leads: Vec<(Square, Option<Square>)>, //the ways the leading pieces may stand
+    lead_idx: HashMap<(Square, Option<Square>), usize>,
+}
+
+impl Layout {
+    fn new(
+        signature: &Signature,
+        material: &Material,
+        symmetries: std::sync::Arc<Vec<Vec<Square>>>,
+    ) -> Self {
+        let num = signature.num();
+        let kinds = material.pieces().collect::<Vec<_>>();
+        let kings = material.white.first() == Some(&PieceKind::King)
+            && material.black.first() == Some(&PieceKind::King);
+        let second = match kings {
+            true => material.white.len(),
+            false => 1,
+        };
+        let mut order = (0..kinds.len()).collect::<Vec<_>>();
+        if second < kinds.len() {
+            order.remove(second);
+            order.insert(1, second);
+        }
+        //kings which are royal never stand next to each other
+        let apart = kings && !signature.is_atomic() && !signature.is_antichess();
+        let alike = second == 1 && kinds.len() > 1 && kinds[0] == kinds[1];
+
+        let mut leads = vec![];
+        for a in (0..num).map(|idx| Square { idx }) {
+            if symmetries.iter().any(|map| map[a.idx] < a) {
+                continue;
+            }
+            if kinds.len() < 2 {
+                leads.push((a, None));
+                continue;
+            }
+            let fixing = symmetries
+                .iter()
+                .filter(|map| map[a.idx] == a)
+                .collect::<Vec<_>>();
+            for b in (0..num).map(|idx| Square { idx }) {
+                if b == a
+                    || fixing.iter().any(|map| map[b.idx] < b)
+                    || (alike && b < a)
+                    || (apart && signature.get_king_moves(a).contains(&b))
+                {
+                    continue;
+                }
+                leads.push((a, Some(b)));
+            }
+        }
+        let lead_idx = leads
+            .iter()
+            .enumerate()
+            .map(|(idx, lead)| (*lead, idx))
+            .collect();
+        Self {
+            num,
+            kinds,
+            symmetries,
+            order,
+            leads,
+            lead_idx,
+        }
+    }
+
+    fn size(&self) -> Option<usize> {
+        (2..self.kinds.len().max(2))
+            .try_fold(2 * self.leads.len(), |size, _| size.checked_mul(self.num))
+    }
+
+    //where in the table the position goes, given the square of each piece of the material in turn
+    //none if pieces share a square or the leading pieces cannot stand as they do
+    fn index(&self, turn: Team, squares: &[Square]) -> Option<usize> {
+        for (i, sq) in squares.iter().enumerate() {
+            if squares[..i].contains(sq) {
+                return None;
+            }
+        }
+        let mut placed = vec![Square { idx: 0 }; squares.len()];
+        let mut least: Option<Vec<Square>> = None;
+        for map in self.symmetries.iter() {
+            for (i, sq) in squares.iter().enumerate() {
+                placed[i] = map[sq.idx];
+            }
+            let mut start = 0;
+            for end in 1..=placed.len() {
+                if end == placed.len() || self.kinds[end] != self.kinds[start] {
+                    placed[start..end].sort();
+                    start = end;
+                }
+            }
+            let key = self.order.iter().map(|i| placed[*i]).collect::<Vec<_>>();
+            if least.as_ref().is_none_or(|least| key < *least) {
+                least = Some(key);
+            }
+        }
+        let key = least?;
+        let lead = self.lead_idx.get(&(key[0], key.get(1).copied()))?;
+        let rest = key
+            .iter()
+            .skip(2)
+            .rev()
+            .fold(0, |idx, sq| idx * self.num + sq.idx);
+        Some(
+            2 * (rest * self.leads.len() + lead)
+                + match turn {
+                    Team::White => 0,
+                    Team::Black => 1,
+                },
+        )
+    }
+
+    //the position with the entry, or none if the entry is left out because its position has another
+    fn position(&self, idx: usize) -> Option<(Team, Vec<(Square, Piece)>)> {
+        let turn = match idx % 2 {
+            0 => Team::White,
+            _ => Team::Black,
+        };
+        let mut rest = idx / 2;
+        let (a, b) = self.leads[rest % self.leads.len()];
+        rest /= self.leads.len();
+        let mut squares = vec![Square { idx: 0 }; self.kinds.len()];
+        for (n, i) in self.order.iter().enumerate() {
+            squares[*i] = match n {
+                0 => a,
+                1 => b.unwrap(),
+                _ => {
+                    let sq = Square {
+                        idx: rest % self.num,
+                    };
+                    rest /= self.num;
+                    sq
+                }
+            };
+        }
+        if self.index(turn, &squares) != Some(idx) {
+            return None;
+        }
+        //moved, so that nobody may castle
+        let pieces = squares
+            .into_iter()
+            .zip(&self.kinds)
+            .map(|(sq, (team, kind))| {
+                (
+                    sq,
+                    Piece {
+                        kind: *kind,
+                        team: *team,
+                        moved: true,
+                    },
+                )
+            })
+            .collect();
+        Some((turn, pieces))
+    }
+}
+
+//whether the side to move has an en passant capture, which the tables do not know about
+fn en_passant(board: &Board) -> bool {
+    board
+        .get_pieces()
+        .iter()
+        .any(|(_sq, piece)| match piece.kind {
+            PieceKind::Pawn(EnCroissantable::Yes { move_num, take_sq }) => {
+                move_num == board.get_move_num()
+                    && piece.team != board.get_turn()
+                    && board
+                        .get_vision(board.get_turn(), take_sq)
+                        .iter()
+                        .any(|vision| matches!(vision.piece().kind, PieceKind::Pawn(..)))
+            }
+            _ => false,
+        })
+}
+
+pub fn can_castle(board: &Board) -> bool {
+    board.signature.get_castles().any(|(team, castle)| {
+        match (
+            board.get_square(castle.king_from),
+            board.get_square(castle.rook_from),
+        ) {
+            (Some(king), Some(rook)) => {
+                king.team == team && rook.team == team && !king.moved && !rook.moved
+            }
+            _ => false,
+        }
+    })
+}
+
+//the value of an entry not yet known while a table is built
+const UNKNOWN: u16 = u16::MAX;
+
+struct Table {
+    layout: Layout,
+    entries: Vec<u16>,
+}
+
+impl Table {
+    fn outcome(&self, turn: Team, squares: &[Square]) -> Option<Outcome> {
+        let idx = self.layout.index(turn, squares)?;
+        Some(Outcome::unpack(self.entries[idx]))
+    }
+}
+
+pub struct Tablebases {
+    fingerprint: u64, //of the signature the tables are for
+    tables: HashMap<Material, Table>,
+    max_pieces: usize,
+    //the symmetries of the board for tables without pawns and with them
+    //none but the identity when the game has rules of its own, which may care where the pieces stand
+    symmetries: std::sync::Arc<Vec<Vec<Square>>>,
+    pawn_symmetries: std::sync::Arc<Vec<Vec<Square>>>,
+}
+
+impl std::fmt::Debug for Tablebases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut materials = self
+            .tables
+            .keys()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>();
+        materials.sort();
+        f.debug_struct("Tablebases")
+            .field("fingerprint", &self.fingerprint)
+            .field("materials", &materials)
+            .finish()
+    }
+}
+
+impl Tablebases {
+    pub fn new(signature: &Signature) -> Self {
+        let symmetries = |pawns: bool| {
+            std::sync::Arc::new(match signature.get_rules().is_empty() {
+                true => signature.symmetries(pawns),
+                false => vec![(0..signature.num()).map(|idx| Square { idx }).collect()],
+            })
+        };
+        Self {
+            fingerprint: signature.fingerprint(),
+            tables: HashMap::new(),
+            max_pieces: 0,
+            symmetries: symmetries(false),
+            pawn_symmetries: symmetries(true),
+        }
+    }
+
+    //every table in the directory which was built for the signature
+    pub fn load(signature: &Signature, dir: &Path) -> Result<Self, String> {
+        let mut tablebases = Self::new(signature);
+        let suffix = format!("-{:016x}.{}", tablebases.fingerprint, EXTENSION);
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("{}: {}", dir.display(), e))?
+                .path();
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+            if let Some(material) = name.strip_suffix(&suffix) {
+                let material = Material::parse(material)?;
+                let table = tablebases.read(&material, signature, &path)?;
+                tablebases.insert(material, table);
+            }
+        }
+        Ok(tablebases)
+    }
+
+    pub fn get_materials(&self) -> Vec<&Material> {
+        self.tables.keys().collect()
+    }
+
+    //the exact result of the position, if it is in a table
+    pub fn probe(&self, board: &Board) -> Option<Score> {
+        if board.white_pieces.len() + board.black_pieces.len() > self.max_pieces
+            || !board.white_pocket.is_empty()
+            || !board.black_pocket.is_empty()
+            || can_castle(board)
+            || en_passant(board)
+        {
+            return None;
+        }
+        let (material, squares) = Material::of(board);
+        let table = self.tables.get(&material)?;
+        Some(
+            table
+                .outcome(board.get_turn(), &squares)?
+                .score(board.get_move_num()),
+        )
+    }
+
+    //build the table for the material, and those for whatever it can turn into first
+    //with a directory, tables already there are read rather than built again, and new ones are written to it
+    pub fn generate(
+        &mut self,
+        signature: &Signature,
+        material: &Material,
+        dir: Option<&Path>,
+    ) -> Result<(), String> {
+        if self.tables.contains_key(material) {
+            return Ok(());
+        }
+        if signature.fingerprint() != self.fingerprint {
+            return Err(String::from("the tablebases are for a different signature"));
+        }
+        if signature.has_drops()
+            || signature
+                .get_rules()
+                .iter()
+                .any(|rules| rules.depends_on_history())
+        {
+            return Err(String::from(
+                "positions in this game depend on more than where the pieces are",
+            ));
+        }
+        if let Some(dir) = dir {
+            let path = self.path(dir, material);
+            if path.exists() {
+                let table = self.read(material, signature, &path)?;
+                self.insert(material.clone(), table);
+                return Ok(());
+            }
+        }
+        for next in material.next(signature) {
+            self.generate(signature, &next, dir)?;
+        }
+        let table = self.build(signature, material)?;
+        if let Some(dir) = dir {
+            self.write(&table, &self.path(dir, material))?;
+        }
+        self.insert(material.clone(), table);
+        Ok(())
+    }
+
+    fn insert(&mut self, material: Material, table: Table) {
+        self.max_pieces = self.max_pieces.max(material.num_pieces());
+        self.tables.insert(material, table);
+    }
+
+    fn path(&self, dir: &Path, material: &Material) -> PathBuf {
+        dir.join(format!(
+            "{}-{:016x}.{}",
+            material, self.fingerprint, EXTENSION
+        ))
+    }
+
+    //custom pieces are left unmapped, since how they move is not known to keep to the board's symmetries
+    fn layout(&self, signature: &Signature, material: &Material) -> Layout {
+        let has = |has: fn(&PieceKind) -> bool| material.pieces().any(|(_team, kind)| has(&kind));
+        let symmetries = match (
+            has(|kind| matches!(kind, PieceKind::Custom(..))),
+            has(|kind| matches!(kind, PieceKind::Pawn(..))),
+        ) {
+            (true, _) => std::sync::Arc::new(self.pawn_symmetries[..1].to_vec()),
+            (false, true) => self.pawn_symmetries.clone(),
+            (false, false) => self.symmetries.clone(),
+        };
+        Layout::new(signature, material, symmetries)
+    }
+
+    fn write(&self, table: &Table, path: &Path) -> Result<(), String> {
+        let mut bytes = Vec::with_capacity(12 + 2 * table.entries.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&(table.entries.len() as u64).to_le_bytes());
+        for entry in &table.entries {
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        std::fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn read(
+        &self,
+        material: &Material,
+        signature: &Signature,
+        path: &Path,
+    ) -> Result<Table, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let layout = self.layout(signature, material);
+        let size = layout.size();
+        if bytes.len() < 12
+            || &bytes[..4] != MAGIC
+            || Some(u64::from_le_bytes(bytes[4..12].try_into().unwrap()) as usize) != size
+            || Some(bytes.len()) != size.map(|size| 12 + 2 * size)
+        {
+            return Err(format!("{}: not a table for {}", path.display(), material));
+        }
+        Ok(Table {
+            layout,
+            entries: bytes[12..]
+                .chunks_exact(2)
+                .map(|entry| u16::from_le_bytes([entry[0], entry[1]]))
+                .collect(),
+        })
+    }
+
+    //how the position the move just made on the board leads to ends, as far as is known while building the table
+    //a position with an en passant capture is not in any table, so is worked out from its own moves
+    fn next(
+        &self,
+        board: &mut Board,
+        material: &Material,
+        layout: &Layout,
+        values: &[u16],
+        plies: u16,
+    ) -> Result<Option<Outcome>, String> {
+        if en_passant(board) {
+            return self.decide(board, material, layout, values, plies);
+        }
+        let (next_material, squares) = Material::of(board);
+        if next_material == *material {
+            let idx = layout.index(board.get_turn(), &squares).unwrap();
+            return Ok(match values[idx] {
+                UNKNOWN => None,
+                entry => Some(Outcome::unpack(entry)),
+            });
+        }
+        match self.tables.get(&next_material) {
+            Some(table) => Ok(table.outcome(board.get_turn(), &squares)),
+            None => Err(format!(
+                "no table for {} after a move in {}",
+                next_material, material
+            )),
+        }
+    }
+
+    //how the game ends from the position on the board, if that is known from the positions whose ends are known to
+    //take fewer plies
+    //a position is won once a move leads to a loss, and lost once every move leads to a win
+    fn decide(
+        &self,
+        board: &mut Board,
+        material: &Material,
+        layout: &Layout,
+        values: &[u16],
+        plies: u16,
+    ) -> Result<Option<Outcome>, String> {
+        let data = BoardData::new(board);
+        if data.is_terminal() {
+            return Ok(Some(terminal(&data)));
+        }
+        let mut won: Option<u16> = None;
+        let mut lost = Some(0);
+        for mv in data.get_moves() {
+            board.make_move(*mv);
+            let next = self.next(board, material, layout, values, plies);
+            board.unmake_move().unwrap();
+            match next? {
+                Some(Outcome::Lost(next)) if next < plies => {
+                    won = Some(won.map_or(next + 1, |won| won.min(next + 1)));
+                }
+                Some(Outcome::Won(next)) if next < plies => {
+                    lost = lost.map(|lost: u16| lost.max(next + 1));
+                }
+                _ => lost = None,
+            }
+        }
+        Ok(won.map(Outcome::Won).or(lost.map(Outcome::Lost)))
+    }
+
+    //the ends of the game are known to begin with, and each pass then finds the positions which end a ply later
+    //the moves of each position not yet known are generated again on each pass rather than kept
+    fn build(&self, signature: &Signature, material: &Material) -> Result<Table, String> {
+        use rayon::prelude::*;
+
+        let layout = self.layout(signature, material);
+        let size = match layout.size() {
+            Some(size) if size <= u32::MAX as usize => size,
+            _ => return Err(format!("{} has too many positions for a table", material)),
+        };
+        let board = Board::new(
+            Team::White,
+            signature.clone(),
+            HashMap::new(),
+            HashMap::new(),
+        );
+
+        //entries left out of the table are never reached
+        let mut values = (0..size)
+            .into_par_iter()
+            .map_init(
+                || board.clone(),
+                |board, idx| match layout.position(idx) {
+                    None => Outcome::Draw.pack(),
+                    Some((turn, pieces)) => {
+                        board.set_position(turn, &pieces);
+                        let data = BoardData::new(board);
+                        match data.is_terminal() {
+                            true => terminal(&data).pack(),
+                            false => UNKNOWN,
+                        }
+                    }
+                },
+            )
+            .collect::<Vec<u16>>();
+
+        //a capture or promotion may lead to an end as far off as any in the tables it leads to
+        let furthest = material
+            .next(signature)
+            .iter()
+            .filter_map(|next| self.tables.get(next))
+            .flat_map(|table| table.entries.iter())
+            .map(|entry| Outcome::unpack(*entry).plies())
+            .max()
+            .unwrap_or(0);
+        let mut plies: u16 = 1;
+        loop {
+            let found = (0..size)
+                .into_par_iter()
+                .filter(|idx| values[*idx] == UNKNOWN)
+                .map_init(
+                    || board.clone(),
+                    |board, idx| {
+                        let (turn, pieces) = layout.position(idx).unwrap();
+                        board.set_position(turn, &pieces);
+                        let outcome = self.decide(board, material, &layout, &values, plies)?;
+                        Ok(outcome.map(|outcome| (idx, outcome)))
+                    },
+                )
+                .collect::<Result<Vec<_>, String>>()?;
+            let found = found.into_iter().flatten().collect::<Vec<_>>();
+            if found.is_empty() && plies > furthest {
+                break;
+            }
+            for (idx, outcome) in found {
+                values[idx] = outcome.pack();
+            }
+            plies = match plies.checked_add(1) {
+                Some(plies) if plies < u16::MAX / 2 => plies,
+                _ => return Err(format!("{} has games too long for a table", material)),
+            };
+        }
+
+        //whatever is left can never be forced either way
+        for value in values.iter_mut() {
+            if *value == UNKNOWN {
+                *value = Outcome::Draw.pack();
+            }
+        }
+        Ok(Table {
+            layout,
+            entries: values,
+        })
+    }
+}
+
+//how a game which is over ended, for the side to move
+fn terminal(data: &BoardData) -> Outcome {
+    match data.get_evaluation() {
+        Score::Lost(_) => Outcome::Lost(0),
+        Score::Won(_) => Outcome::Won(0),
+        Score::Draw(_) | Score::Heuristic(_) => Outcome::Draw,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //tables on a board of 3 files and 4 ranks, starting from a king and rook against a king
+    fn small_tablebases(materials: &[&str], dir: Option<&Path>) -> (Board, Tablebases) {
+        let board = crate::variant::parse("files = 3\nranks = 4\nstart = k2/3/3/1RK")
+            .unwrap()
+            .create_game();
+        let mut tablebases = Tablebases::new(board.get_signature());
+        for material in materials {
+            tablebases
+                .generate(
+                    board.get_signature(),
+                    &Material::parse(material).unwrap(),
+                    dir,
+                )
+                .unwrap();
+        }
+        (board, tablebases)
+    }
+
+    #[test]
+    fn test_retrograde() {
+        //king and pawn against king, which promotes into the others
+        let (mut board, tablebases) = small_tablebases(&["KPvK"], None);
+        let mut materials = tablebases
+            .get_materials()
+            .into_iter()
+            .map(|material| material.to_string())
+            .collect::<Vec<_>>();
+        materials.sort();
+        assert_eq!(
+            materials,
+            ["KBvK", "KNvK", "KPvK", "KQvK", "KRvK", "KvK"].map(String::from)
+        );
+
+        //every position scores as the best of its moves does, which with the ends of the game scored right makes
+        //every score exact
+        let draws = |score: Score| match score {
+            Score::Draw(_) => Score::Draw(0),
+            score => score,
+        };
+        let mut won = 0;
+        for table in tablebases.tables.values() {
+            for idx in 0..table.entries.len() {
+                let Some((turn, pieces)) = table.layout.position(idx) else {
+                    continue;
+                };
+                board.set_position(turn, &pieces);
+                let score = draws(tablebases.probe(&board).unwrap());
+                let data = BoardData::new(&mut board);
+                let best = match data.is_terminal() {
+                    true => draws(data.get_evaluation()),
+                    false => data
+                        .get_moves()
+                        .into_iter()
+                        .map(|mv| {
+                            board.make_move(*mv);
+                            let score = -tablebases.probe(&board).unwrap();
+                            board.unmake_move().unwrap();
+                            draws(score)
+                        })
+                        .max()
+                        .unwrap(),
+                };
+                assert_eq!(score, best, "{:?} {:?}", turn, pieces);
+                if matches!(score, Score::Won(_)) {
+                    won += 1;
+                }
+            }
+        }
+        assert!(won > 0);
+    }
+
+    #[test]
+    fn test_search() {
+        //the search sees the exact result, while the moves are still there to choose between
+        let (mut board, tablebases) = small_tablebases(&["KRvK"], None);
+        board.set_tablebases(std::sync::Arc::new(tablebases));
+        let data = BoardData::new(&mut board);
+        assert!(matches!(data.get_evaluation(), Score::Won(_)));
+        assert!(!data.is_terminal());
+    }
+
+    #[test]
+    fn test_files() {
+        let dir = std::env::temp_dir().join(format!("tablebase-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (mut board, written) = small_tablebases(&["KRvK"], Some(&dir));
+        let read = Tablebases::load(board.get_signature(), &dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(read.get_materials().len(), written.get_materials().len());
+        let layout = &written.tables[&Material::parse("KRvK").unwrap()].layout;
+        for idx in 0..layout.size().unwrap() {
+            if let Some((turn, pieces)) = layout.position(idx) {
+                board.set_position(turn, &pieces);
+                assert_eq!(read.probe(&board), written.probe(&board));
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        //the eight symmetries of the square board, or the mirror alone with pawns
+        let signature = crate::classical::create_signature();
+        let tablebases = Tablebases::new(&signature);
+        assert_eq!(tablebases.symmetries.len(), 8);
+        assert_eq!(tablebases.pawn_symmetries.len(), 2);
+
+        //the kings stand one of 462 ways, so five pieces fit in a table
+        let material = Material::parse("KQRvKR").unwrap();
+        let layout = tablebases.layout(&signature, &material);
+        assert_eq!(layout.leads.len(), 462);
+        assert_eq!(layout.size(), Some(2 * 462 * 64 * 64 * 64));
+
+        //a position and its mirror images share an entry
+        let squares = |idxs: [usize; 5]| idxs.map(|idx| Square { idx });
+        let idx = layout.index(Team::White, &squares([4, 3, 0, 60, 63]));
+        assert!(idx.is_some());
+        assert_eq!(layout.index(Team::White, &squares([3, 4, 7, 59, 56])), idx);
+        assert_eq!(layout.index(Team::White, &squares([60, 59, 56, 4, 7])), idx);
+        assert_ne!(layout.index(Team::Black, &squares([4, 3, 0, 60, 63])), idx);
+
+        //and touching kings have none
+        assert_eq!(layout.index(Team::White, &squares([4, 3, 0, 12, 63])), None);
+
+        //the board of 3 files and 4 ranks the other tests build on can be turned over either way
+        let (_board, tablebases) = small_tablebases(&[], None);
+        assert_eq!(tablebases.symmetries.len(), 4);
+        assert_eq!(tablebases.pawn_symmetries.len(), 2);
+    }
+}
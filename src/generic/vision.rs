@@ -0,0 +1,392 @@
+use super::*;
+
+//what each piece sees: the squares it attacks, and for riders every square along the way whose occupant matters
+//the board keeps the vision of every piece as pieces are put on and taken off it, rather than finding it anew at each node
+//when a square changes, whatever is on it is looked at again, as is each piece whose vision passes through it
+//a teleport does not depend on what is in the way, so leapers and pawns are only looked at again when they move
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrasshopperVisionKind {
+    Slide,
+    Hurdle,
+    Land,
+}
+
+//one of the slides from a square, by its place among the flat or diagonal slides the signature has there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlideRef {
+    pub diag: bool,
+    pub idx: usize,
+}
+
+impl SlideRef {
+    pub fn get(self, board: &Board, from: Square) -> &[Square] {
+        match self.diag {
+            false => &board.signature.get_flat_slides(from)[self.idx],
+            true => &board.signature.get_diag_slides(from)[self.idx],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vision {
+    Teleport {
+        piece: Piece,
+        from: Square,
+        to: Square,
+    },
+    Slide {
+        piece: Piece,
+        from: Square,
+        slide: SlideRef,
+        slide_idx: usize,
+    },
+    Grasshopper {
+        piece: Piece,
+        from: Square,
+        slide: SlideRef,
+        slide_idx: usize,
+        kind: GrasshopperVisionKind,
+    },
+    Custom {
+        piece: Piece,
+        from: Square,
+        rule: usize,   //index into the rules of the piece's movement
+        attacks: bool, //false if the piece only depends on what is here without attacking it
+    },
+}
+
+impl Vision {
+    //whether the piece could capture on the square, rather than merely depending on what is there
+    pub fn attacks(&self) -> bool {
+        match self {
+            Vision::Teleport { .. } => true,
+            Vision::Slide { .. } => true,
+            Vision::Grasshopper { kind, .. } => *kind == GrasshopperVisionKind::Land,
+            Vision::Custom { attacks, .. } => *attacks,
+        }
+    }
+
+    pub fn piece(&self) -> Piece {
+        match self {
+            Vision::Teleport { piece, .. } => *piece,
+            Vision::Slide { piece, .. } => *piece,
+            Vision::Grasshopper { piece, .. } => *piece,
+            Vision::Custom { piece, .. } => *piece,
+        }
+    }
+
+    pub fn from(&self) -> Square {
+        match self {
+            Vision::Teleport { from, .. } => *from,
+            Vision::Slide { from, .. } => *from,
+            Vision::Grasshopper { from, .. } => *from,
+            Vision::Custom { from, .. } => *from,
+        }
+    }
+
+    //whether what the piece sees could change with what is on this square
+    fn depends(&self) -> bool {
+        !matches!(self, Vision::Teleport { .. })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VisionMap {
+    //for each square, what each team's pieces see there, ordered by the square they are seen from
+    white: Vec<Vec<Vision>>,
+    black: Vec<Vec<Vision>>,
+    //for each square, everything the piece on it sees, in the order it was found
+    seen_from: Vec<Vec<(Square, Vision)>>,
+    //scratch space, the squares already reached by the current piece being those marked with the stamp
+    done: Vec<usize>,
+    landed: Vec<usize>,
+    stamp: usize,
+    dependents: Vec<Square>,
+    reach: Vec<(usize, Square, betza::Reach)>, //where a custom piece reaches
+}
+
+impl VisionMap {
+    pub fn new(board: &Board) -> Self {
+        let num = board.signature.num();
+        let mut map = Self {
+            white: vec![vec![]; num],
+            black: vec![vec![]; num],
+            seen_from: vec![vec![]; num],
+            done: vec![0; num],
+            landed: vec![0; num],
+            stamp: 0,
+            dependents: vec![],
+            reach: vec![],
+        };
+        for idx in 0..num {
+            map.add(board, Square { idx });
+        }
+        map
+    }
+
+    pub fn get(&self, team: Team, sq: Square) -> &Vec<Vision> {
+        match team {
+            Team::White => &self.white[sq.idx],
+            Team::Black => &self.black[sq.idx],
+        }
+    }
+
+    //what the team sees on each square
+    pub fn get_all(&self, team: Team) -> &Vec<Vec<Vision>> {
+        match team {
+            Team::White => &self.white,
+            Team::Black => &self.black,
+        }
+    }
+
+    pub fn get_seen_from(&self, sq: Square) -> &Vec<(Square, Vision)> {
+        &self.seen_from[sq.idx]
+    }
+
+    //bring the vision up to date after the piece on the square, if any, has changed
+    pub fn update(&mut self, board: &Board, sq: Square) {
+        self.remove(sq);
+        let mut dependents = std::mem::take(&mut self.dependents);
+        dependents.clear();
+        for vision in self.white[sq.idx].iter().chain(self.black[sq.idx].iter()) {
+            if vision.depends() && !dependents.contains(&vision.from()) {
+                dependents.push(vision.from());
+            }
+        }
+        for from in &dependents {
+            self.remove(*from);
+            self.add(board, *from);
+        }
+        self.dependents = dependents;
+        self.add(board, sq);
+    }
+
+    fn remove(&mut self, from: Square) {
+        let mut seen = std::mem::take(&mut self.seen_from[from.idx]);
+        for (to, vision) in &seen {
+            let team_vision = match vision.piece().team {
+                Team::White => &mut self.white[to.idx],
+                Team::Black => &mut self.black[to.idx],
+            };
+            team_vision.retain(|other| other.from() != from);
+        }
+        seen.clear();
+        self.seen_from[from.idx] = seen;
+    }
+
+    fn add(&mut self, board: &Board, from: Square) {
+        let piece = match board.get_square(from) {
+            Some(piece) => piece,
+            None => return,
+        };
+        let mut seen = std::mem::take(&mut self.seen_from[from.idx]);
+        self.find(board, from, piece, &mut seen);
+        for (to, vision) in &seen {
+            let team_vision = match piece.team {
+                Team::White => &mut self.white[to.idx],
+                Team::Black => &mut self.black[to.idx],
+            };
+            //after everything seen from earlier squares, so that the order is the same however the board came about
+            let idx = team_vision.partition_point(|other| other.from().idx <= from.idx);
+            team_vision.insert(idx, *vision);
+        }
+        self.seen_from[from.idx] = seen;
+    }
+
+    //everything the piece sees from the square
+    fn find(
+        &mut self,
+        board: &Board,
+        from: Square,
+        piece: Piece,
+        seen: &mut Vec<(Square, Vision)>,
+    ) {
+        let signature = &board.signature;
+        let done = &mut self.done;
+        let landed = &mut self.landed;
+        let reach = &mut self.reach;
+        self.stamp += 1;
+        let stamp = self.stamp;
+
+        let slides = |diag: bool| {
+            match diag {
+                false => signature.get_flat_slides(from),
+                true => signature.get_diag_slides(from),
+            }
+            .iter()
+            .enumerate()
+            .map(move |(idx, slide)| (SlideRef { diag, idx }, slide))
+        };
+        let mut teleports = |to_sqs: &Vec<Square>| {
+            for to in to_sqs {
+                seen.push((
+                    *to,
+                    Vision::Teleport {
+                        piece,
+                        from,
+                        to: *to,
+                    },
+                ));
+            }
+        };
+
+        let diags: &[bool] = match piece.kind {
+            PieceKind::Pawn(..) => {
+                teleports(signature.get_pawn_takes(from, piece.team));
+                &[]
+            }
+            PieceKind::Knight => {
+                teleports(signature.get_knight_moves(from));
+                &[]
+            }
+            PieceKind::King => {
+                teleports(signature.get_king_moves(from));
+                &[]
+            }
+            PieceKind::Rook | PieceKind::Chancellor => &[false],
+            PieceKind::Bishop | PieceKind::Archbishop => &[true],
+            PieceKind::Queen => &[false, true],
+            PieceKind::Grasshopper => {
+                for diag in [false, true] {
+                    for (slide_ref, slide) in slides(diag) {
+                        for (slide_idx, jump_sq) in slide.iter().enumerate() {
+                            //a hurdle already seen along another slide still has to be jumped along this one
+                            let first_visit = done[jump_sq.idx] != stamp;
+                            done[jump_sq.idx] = stamp;
+                            let vision = |slide_idx, kind| Vision::Grasshopper {
+                                piece,
+                                from,
+                                slide: slide_ref,
+                                slide_idx,
+                                kind,
+                            };
+                            if board.get_square(*jump_sq).is_none() {
+                                if first_visit {
+                                    seen.push((
+                                        *jump_sq,
+                                        vision(slide_idx, GrasshopperVisionKind::Slide),
+                                    ));
+                                }
+                                continue;
+                            }
+                            if first_visit {
+                                seen.push((
+                                    *jump_sq,
+                                    vision(slide_idx, GrasshopperVisionKind::Hurdle),
+                                ));
+                            }
+                            if let Some(land_sq) = slide.get(slide_idx + 1) {
+                                if landed[land_sq.idx] != stamp {
+                                    landed[land_sq.idx] = stamp;
+                                    seen.push((
+                                        *land_sq,
+                                        vision(slide_idx + 1, GrasshopperVisionKind::Land),
+                                    ));
+                                }
+                            }
+                            break;
+                        }
+                    }
+                }
+                &[]
+            }
+            PieceKind::Custom(idx) => {
+                let movement = &signature.get_custom_piece(idx).movement;
+                movement.reach(board, from, reach);
+                for (rule_idx, to_sq, reach) in reach.iter().copied() {
+                    //a rule which cannot capture never gives check, so nothing needs to watch it
+                    if movement.rules[rule_idx].can_capture {
+                        seen.push((
+                            to_sq,
+                            Vision::Custom {
+                                piece,
+                                from,
+                                rule: rule_idx,
+                                attacks: reach == betza::Reach::Land,
+                            },
+                        ));
+                    }
+                }
+                &[]
+            }
+        };
+
+        for diag in diags {
+            for (slide_ref, slide) in slides(*diag) {
+                for (slide_idx, to_sq) in slide.iter().enumerate() {
+                    if done[to_sq.idx] != stamp {
+                        done[to_sq.idx] = stamp;
+                        seen.push((
+                            *to_sq,
+                            Vision::Slide {
+                                piece,
+                                from,
+                                slide: slide_ref,
+                                slide_idx,
+                            },
+                        ));
+                    }
+                    //a square already seen along another slide still blocks this one
+                    if board.get_square(*to_sq).is_some() {
+                        break;
+                    }
+                }
+            }
+        }
+        //the compound pieces leap as well as slide
+        if matches!(piece.kind, PieceKind::Archbishop | PieceKind::Chancellor) {
+            for to in signature.get_knight_moves(from) {
+                seen.push((
+                    *to,
+                    Vision::Teleport {
+                        piece,
+                        from,
+                        to: *to,
+                    },
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::fen::from_fen;
+    use crate::classical::ClassicalGameType;
+    use crate::generic::board_data::BoardData;
+
+    #[test]
+    fn test_incremental() {
+        //after every move and take back the vision kept by the board is what it would be found to be from scratch
+        fn check(board: &mut Board, depth: usize) {
+            let fresh = VisionMap::new(board);
+            for team in [Team::White, Team::Black] {
+                assert_eq!(board.vision.get_all(team), fresh.get_all(team));
+            }
+            if depth == 0 {
+                return;
+            }
+            let moves = BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .copied()
+                .collect::<Vec<_>>();
+            for m in moves {
+                board.make_move(m);
+                check(board, depth - 1);
+                board.unmake_move().unwrap();
+            }
+        }
+
+        for mut board in [
+            ClassicalGameType::Grasshopper.create_game(),
+            ClassicalGameType::Torus.create_game(),
+            crate::hexagonal::HexGameType::Glinski.create_game(),
+            from_fen("r3k2r/1p4p1/8/2Pp4/8/8/1P4P1/R3K2R w KQkq d6 0 1").unwrap(),
+        ] {
+            check(&mut board, 2);
+        }
+    }
+}
@@ -10,11 +10,11 @@ pub struct State {
 pub trait Canvas {
     type Init;
 
-    fn new(facade: &impl Facade, init : Self::Init) -> Self;
+    fn new(facade: &impl Facade, init: Self::Init) -> Self;
     fn tick(&mut self, state: &State, dt: f64);
     fn draw(&mut self, state: &State, display: &Display);
     fn event(&mut self, state: &State, ev: &Event<'_, ()>);
-    fn run(init : Self::Init) -> !
+    fn run(init: Self::Init) -> !
     where
         Self: Sized + 'static,
     {
@@ -58,8 +58,8 @@ pub trait Canvas {
             // println!("{:?}", ev);
 
             //events
-            match &ev {
-                glium::glutin::event::Event::WindowEvent { event, .. } => match event {
+            if let glium::glutin::event::Event::WindowEvent { event, .. } = &ev {
+                match event {
                     glium::glutin::event::WindowEvent::CloseRequested => {
                         stop = true;
                     }
@@ -70,8 +70,7 @@ pub trait Canvas {
                         state.display_size = (size.width, size.height);
                     }
                     _ => {}
-                },
-                _ => {}
+                }
             }
             canvas.event(&state, &ev);
 
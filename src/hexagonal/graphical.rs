@@ -0,0 +1,103 @@
+use crate::classical::graphical::{Cell, Game, Geometry};
+
+use super::*;
+
+const SQRT_3: f64 = 1.7320508075688772;
+
+//flat topped cells with the centre cell at the origin and y going up, a unit being the distance from a cell's centre to its corners
+pub struct HexGeometry {}
+
+//the centre of a cell
+fn hex_centre(sq: Square) -> (f64, f64) {
+    let (q, r) = sq_to_hex(sq);
+    (1.5 * q as f64, SQRT_3 * (r as f64 + q as f64 / 2.0))
+}
+
+//round fractional axial coordinates to the nearest cell
+fn hex_round(q: f64, r: f64) -> (i8, i8) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i8, rr as i8)
+}
+
+impl Geometry for HexGeometry {
+    fn size(&self) -> (f64, f64) {
+        (
+            3.0 * RADIUS as f64 + 2.0,
+            SQRT_3 * (2.0 * RADIUS as f64 + 1.0),
+        )
+    }
+
+    fn outline(&self, cell: Cell) -> Vec<(f64, f64)> {
+        let (x, y) = self.centre(cell);
+        (0..6)
+            .map(|i| {
+                let angle = std::f64::consts::PI * i as f64 / 3.0;
+                (x + angle.cos(), y + angle.sin())
+            })
+            .collect()
+    }
+
+    fn centre(&self, cell: Cell) -> (f64, f64) {
+        match cell {
+            Cell::Square(sq) => hex_centre(sq),
+            Cell::Pocket(..) => unreachable!(), //hexagonal chess has no drops
+        }
+    }
+
+    //three colours so that no two neighbouring cells match and each bishop keeps to one
+    fn colour(&self, cell: Cell) -> [f32; 3] {
+        let colours = [[0.9, 0.5, 0.15], [0.75, 0.4, 0.1], [0.6, 0.3, 0.05]];
+        match cell {
+            Cell::Square(sq) => {
+                let (q, r) = sq_to_hex(sq);
+                colours[(q - r).rem_euclid(3) as usize]
+            }
+            Cell::Pocket(..) => unreachable!(),
+        }
+    }
+
+    fn cell_at(&self, point: (f64, f64)) -> Option<Cell> {
+        let q = point.0 / 1.5;
+        let (q, r) = hex_round(q, point.1 / SQRT_3 - q / 2.0);
+        hex_to_sq(q, r).map(Cell::Square)
+    }
+
+    fn icon_size(&self) -> f64 {
+        1.4
+    }
+}
+
+impl Game for HexGameType {
+    fn geometry(&self) -> Box<dyn Geometry> {
+        Box::new(HexGeometry {})
+    }
+
+    fn create_game(&self) -> Board {
+        HexGameType::create_game(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round() {
+        for idx in 0..num_cells() {
+            let sq = Square { idx };
+            //nudge towards a corner but stay inside the cell
+            let (x, y) = hex_centre(sq);
+            assert_eq!(
+                HexGeometry {}.cell_at((x + 0.4, y + 0.3)),
+                Some(Cell::Square(sq))
+            );
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use crate::generic::*;
+use std::collections::{HashMap, HashSet};
+
+pub mod graphical;
+
+//a hexagon of hexagonal cells in axial coordinates (q, r)
+//q counts files from left to right with 0 the centre file, and r increases going up a file
+const RADIUS: i8 = 5;
+const FILE_NAMES: [char; 11] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'k', 'l'];
+
+//orthogonal directions round the hexagon, starting up the file and going clockwise
+const FLAT_DIRS: [(i8, i8); 6] = [(0, 1), (1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1)];
+
+fn diag_dir(i: usize) -> (i8, i8) {
+    let (a, b) = (FLAT_DIRS[i % 6], FLAT_DIRS[(i + 1) % 6]);
+    (a.0 + b.0, a.1 + b.1)
+}
+
+//the lowest and highest r in file q
+fn file_range(q: i8) -> (i8, i8) {
+    ((-RADIUS).max(-RADIUS - q), RADIUS.min(RADIUS - q))
+}
+
+fn num_cells() -> usize {
+    (-RADIUS..=RADIUS)
+        .map(|q| {
+            let (lo, hi) = file_range(q);
+            (hi - lo + 1) as usize
+        })
+        .sum()
+}
+
+pub fn hex_to_sq(q: i8, r: i8) -> Option<Square> {
+    if !(-RADIUS..=RADIUS).contains(&q) {
+        return None;
+    }
+    let (lo, hi) = file_range(q);
+    if r < lo || r > hi {
+        return None;
+    }
+    let mut idx = 0;
+    for prev_q in -RADIUS..q {
+        let (prev_lo, prev_hi) = file_range(prev_q);
+        idx += (prev_hi - prev_lo + 1) as usize;
+    }
+    Some(Square {
+        idx: idx + (r - lo) as usize,
+    })
+}
+
+pub fn sq_to_hex(sq: Square) -> (i8, i8) {
+    let mut idx = sq.idx;
+    for q in -RADIUS..=RADIUS {
+        let (lo, hi) = file_range(q);
+        let len = (hi - lo + 1) as usize;
+        if idx < len {
+            return (q, lo + idx as i8);
+        }
+        idx -= len;
+    }
+    panic!()
+}
+
+fn step(sq: Square, dir: (i8, i8)) -> Option<Square> {
+    let (q, r) = sq_to_hex(sq);
+    hex_to_sq(q + dir.0, r + dir.1)
+}
+
+//cells are named by file letter (there is no j file) and rank counted up from the bottom of the file
+fn cell(name: &str) -> Square {
+    let mut chars = name.chars();
+    let file = chars.next().unwrap();
+    let rank: i8 = chars.as_str().parse().unwrap();
+    let q = FILE_NAMES.iter().position(|f| *f == file).unwrap() as i8 - RADIUS;
+    let (lo, _hi) = file_range(q);
+    hex_to_sq(q, lo + rank - 1).unwrap()
+}
+
+//reflect top to bottom, taking white's setup to black's
+fn mirror(sq: Square) -> Square {
+    let (q, r) = sq_to_hex(sq);
+    hex_to_sq(q, -r - q).unwrap()
+}
+
+fn create_signature(white_pawn_starts: &HashSet<Square>) -> signature::Signature {
+    let flat_nbs =
+        |sq: Square| -> Vec<Square> { FLAT_DIRS.iter().filter_map(|dir| step(sq, *dir)).collect() };
+
+    let diag_nbs =
+        |sq: Square| -> Vec<Square> { (0..6).filter_map(|i| step(sq, diag_dir(i))).collect() };
+
+    //a flat or diagonal step from i to j continues in the same direction
+    let opp = |i: Square, j: Square| -> Vec<Square> {
+        let (qi, ri) = sq_to_hex(i);
+        let (qj, rj) = sq_to_hex(j);
+        match hex_to_sq(2 * qj - qi, 2 * rj - ri) {
+            Some(k) => vec![k],
+            None => vec![],
+        }
+    };
+
+    let forward = |team: Team| -> (i8, i8) {
+        match team {
+            Team::White => FLAT_DIRS[0],
+            Team::Black => FLAT_DIRS[3],
+        }
+    };
+
+    let pawn_moves = |team: Team, sq: Square| -> Vec<(Square, Vec<Square>)> {
+        let starts = match team {
+            Team::White => white_pawn_starts.contains(&sq),
+            Team::Black => white_pawn_starts.contains(&mirror(sq)),
+        };
+        match step(sq, forward(team)) {
+            Some(first) => {
+                //a pawn on the last cell of its file has already promoted so never gets here
+                match step(first, forward(team)) {
+                    Some(second) if starts => vec![(first, vec![second])],
+                    _ => vec![(first, vec![])],
+                }
+            }
+            None => vec![],
+        }
+    };
+
+    //pawns capture to the two orthogonal neighbours either side of straight ahead
+    let pawn_takes = |team: Team, sq: Square| -> Vec<Square> {
+        let (left, right) = match team {
+            Team::White => (FLAT_DIRS[5], FLAT_DIRS[1]),
+            Team::Black => (FLAT_DIRS[4], FLAT_DIRS[2]),
+        };
+        [left, right]
+            .into_iter()
+            .filter_map(|dir| step(sq, dir))
+            .collect()
+    };
+
+    //two steps straight on then one step at 60 degrees
+    let knight_moves = |sq: Square| -> Vec<Square> {
+        let mut moves = vec![];
+        for (i, a) in FLAT_DIRS.iter().enumerate() {
+            for j in [i + 1, i + 5] {
+                let b = FLAT_DIRS[j % 6];
+                if let Some(to) = step(sq, (2 * a.0 + b.0, 2 * a.1 + b.1)) {
+                    moves.push(to);
+                }
+            }
+        }
+        moves
+    };
+
+    let promotions = vec![
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ];
+
+    signature::Signature::new(
+        num_cells(),
+        &flat_nbs,
+        &diag_nbs,
+        &opp,
+        &opp,
+        &pawn_moves,
+        (-RADIUS..=RADIUS)
+            .map(|q| (hex_to_sq(q, file_range(q).1).unwrap(), promotions.clone()))
+            .collect(),
+        (-RADIUS..=RADIUS)
+            .map(|q| (hex_to_sq(q, file_range(q).0).unwrap(), promotions.clone()))
+            .collect(),
+        vec![],
+        vec![],
+    )
+    .with_knight_moves(&knight_moves)
+    .with_pawn_takes(&pawn_takes)
+}
+
+pub enum HexGameType {
+    Glinski,
+    McCooey,
+}
+
+impl HexGameType {
+    //white's pieces, black's are the mirror image
+    fn setup(&self) -> Vec<(&'static str, PieceKind)> {
+        let pawn = PieceKind::Pawn(EnCroissantable::No);
+        match self {
+            HexGameType::Glinski => vec![
+                ("g1", PieceKind::King),
+                ("e1", PieceKind::Queen),
+                ("f1", PieceKind::Bishop),
+                ("f2", PieceKind::Bishop),
+                ("f3", PieceKind::Bishop),
+                ("d1", PieceKind::Knight),
+                ("h1", PieceKind::Knight),
+                ("c1", PieceKind::Rook),
+                ("i1", PieceKind::Rook),
+                ("b1", pawn),
+                ("c2", pawn),
+                ("d3", pawn),
+                ("e4", pawn),
+                ("f5", pawn),
+                ("g4", pawn),
+                ("h3", pawn),
+                ("i2", pawn),
+                ("k1", pawn),
+            ],
+            HexGameType::McCooey => vec![
+                ("g1", PieceKind::King),
+                ("e1", PieceKind::Queen),
+                ("f1", PieceKind::Bishop),
+                ("f2", PieceKind::Bishop),
+                ("f3", PieceKind::Bishop),
+                ("e2", PieceKind::Knight),
+                ("g2", PieceKind::Knight),
+                ("d1", PieceKind::Rook),
+                ("h1", PieceKind::Rook),
+                ("c1", pawn),
+                ("d2", pawn),
+                ("e3", pawn),
+                ("f4", pawn),
+                ("g3", pawn),
+                ("h2", pawn),
+                ("i1", pawn),
+            ],
+        }
+    }
+
+    pub fn create_game(&self) -> Board {
+        let setup = self.setup();
+
+        //pawns may double step from any of their team's starting cells
+        let white_pawn_starts = setup
+            .iter()
+            .filter(|(_name, kind)| matches!(kind, PieceKind::Pawn(..)))
+            .map(|(name, _kind)| cell(name))
+            .collect();
+
+        let mut white_pieces = HashMap::new();
+        let mut black_pieces = HashMap::new();
+        for (name, kind) in setup {
+            white_pieces.insert(cell(name), kind);
+            black_pieces.insert(mirror(cell(name)), kind);
+        }
+
+        Board::new(
+            Team::White,
+            create_signature(&white_pawn_starts),
+            white_pieces,
+            black_pieces,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test() {
+        assert_eq!(num_cells(), 91);
+        for idx in 0..num_cells() {
+            let sq = Square { idx };
+            let (q, r) = sq_to_hex(sq);
+            assert_eq!(hex_to_sq(q, r), Some(sq));
+            assert_eq!(mirror(mirror(sq)), sq);
+        }
+        assert_eq!(cell("f6"), hex_to_sq(0, 0).unwrap());
+        assert_eq!(mirror(cell("g1")), cell("g10"));
+        assert_eq!(mirror(cell("b1")), cell("b7"));
+
+        let signature = create_signature(&HashSet::new());
+        //from the centre the king sees 12 cells and the knight 12
+        assert_eq!(signature.get_king_moves(cell("f6")).len(), 12);
+        assert_eq!(signature.get_knight_moves(cell("f6")).len(), 12);
+        //bishops stay on cells of one colour
+        for slide in signature.get_diag_slides(cell("f6")) {
+            for sq in slide {
+                let (q, r) = sq_to_hex(*sq);
+                assert_eq!((q - r).rem_euclid(3), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_piece_square() {
+        let signature = create_signature(&HashSet::new());
+        let tables = signature.get_piece_square_tables();
+        let value = |kind, team, sq| {
+            tables.value(
+                &Piece {
+                    kind,
+                    team,
+                    moved: false,
+                },
+                sq,
+            )
+        };
+        //pieces prefer the centre, and so does the king once the pieces are off
+        assert!(
+            value(PieceKind::Knight, Team::White, cell("f6")).0
+                > value(PieceKind::Knight, Team::White, cell("a1")).0
+        );
+        assert!(
+            value(PieceKind::King, Team::White, cell("f6")).1
+                > value(PieceKind::King, Team::White, cell("a1")).1
+        );
+        assert!(
+            value(PieceKind::King, Team::White, cell("f6")).0
+                < value(PieceKind::King, Team::White, cell("a1")).0
+        );
+        //pawns are worth the same to each team from mirrored cells
+        let pawn = PieceKind::Pawn(EnCroissantable::No);
+        for idx in 0..num_cells() {
+            let sq = Square { idx };
+            assert_eq!(
+                value(pawn, Team::White, sq),
+                value(pawn, Team::Black, mirror(sq))
+            );
+        }
+        assert!(value(pawn, Team::White, cell("f10")).1 > value(pawn, Team::White, cell("f6")).1);
+    }
+
+    #[test]
+    fn test_opening_moves() {
+        let mut board = HexGameType::Glinski.create_game();
+        assert_eq!(board_data::BoardData::new(&mut board).get_moves().len(), 51);
+        let mut board = HexGameType::McCooey.create_game();
+        assert_eq!(board_data::BoardData::new(&mut board).get_moves().len(), 32);
+    }
+}
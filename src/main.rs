@@ -1,10 +1,191 @@
+use classical::graphical::{Game, Tuned, WithSyzygy, WithTablebases};
 use classical::ClassicalGameType;
 use graphical::Canvas;
 
+pub mod bench;
+pub mod capablanca;
 pub mod classical;
 pub mod generic;
 pub mod graphical;
+pub mod hexagonal;
+pub mod variant;
 
 fn main() {
-    classical::graphical::GameInterface::run(ClassicalGameType::Classical);
+    //usage: myapp [variant file | --game name] [--eval weights file] [--tablebases directory] [--syzygy directory]
+    //       myapp [variant file | --game name] --generate-tablebase directory material, e.g. KRvK
+    //       myapp --bench [seconds per search]
+    //the games are classical, grasshopper, chess960 followed by its start position from 0 to 959, cylinder, torus,
+    //crazyhouse, atomic, antichess, kingofthehill, threecheck, horde, capablanca, gothic, glinski and mccooey
+    //without a variant file or a game classical chess is played, which alone can use the syzygy tables
+    let mut variant_path = None;
+    let mut chosen_game: Option<Box<dyn Game>> = None;
+    let mut eval_path = None;
+    let mut tablebase_dir = None;
+    let mut syzygy_dir = None;
+    let mut generate = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bench" => {
+                let seconds = match args.next().map(|s| s.parse::<f64>()) {
+                    None => 10.0,
+                    Some(Ok(seconds)) if seconds > 0.0 => seconds,
+                    Some(_) => {
+                        eprintln!("--bench: expected a positive number of seconds");
+                        std::process::exit(1);
+                    }
+                };
+                if let Err(e) = bench::run(std::time::Duration::from_secs_f64(seconds)) {
+                    eprintln!("--bench: {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            "--game" => {
+                chosen_game = Some(match args.next().as_deref() {
+                    Some("classical") => Box::new(ClassicalGameType::Classical),
+                    Some("grasshopper") => Box::new(ClassicalGameType::Grasshopper),
+                    Some("chess960") => match args.next().map(|s| s.parse::<usize>()) {
+                        Some(Ok(n)) if n < classical::chess960::NUM_POSITIONS => {
+                            Box::new(ClassicalGameType::Chess960(n))
+                        }
+                        _ => {
+                            eprintln!("--game chess960: expected a start position from 0 to 959");
+                            std::process::exit(1);
+                        }
+                    },
+                    Some("cylinder") => Box::new(ClassicalGameType::Cylinder),
+                    Some("torus") => Box::new(ClassicalGameType::Torus),
+                    Some("crazyhouse") => Box::new(ClassicalGameType::Crazyhouse),
+                    Some("atomic") => Box::new(ClassicalGameType::Atomic),
+                    Some("antichess") => Box::new(ClassicalGameType::Antichess),
+                    Some("kingofthehill") => Box::new(ClassicalGameType::KingOfTheHill),
+                    Some("threecheck") => Box::new(ClassicalGameType::ThreeCheck),
+                    Some("horde") => Box::new(ClassicalGameType::Horde),
+                    Some("capablanca") => Box::new(capablanca::CapablancaGameType::Capablanca),
+                    Some("gothic") => Box::new(capablanca::CapablancaGameType::Gothic),
+                    Some("glinski") => Box::new(hexagonal::HexGameType::Glinski),
+                    Some("mccooey") => Box::new(hexagonal::HexGameType::McCooey),
+                    Some(name) => {
+                        eprintln!("--game: unknown game '{}'", name);
+                        std::process::exit(1);
+                    }
+                    None => {
+                        eprintln!("--game: expected the name of a game");
+                        std::process::exit(1);
+                    }
+                })
+            }
+            "--eval" => match args.next() {
+                Some(path) => eval_path = Some(path),
+                None => {
+                    eprintln!("--eval: expected a file");
+                    std::process::exit(1);
+                }
+            },
+            "--tablebases" => match args.next() {
+                Some(dir) => tablebase_dir = Some(dir),
+                None => {
+                    eprintln!("--tablebases: expected a directory");
+                    std::process::exit(1);
+                }
+            },
+            "--syzygy" => match args.next() {
+                Some(dir) => syzygy_dir = Some(dir),
+                None => {
+                    eprintln!("--syzygy: expected a directory");
+                    std::process::exit(1);
+                }
+            },
+            "--generate-tablebase" => match (args.next(), args.next()) {
+                (Some(dir), Some(material)) => generate = Some((dir, material)),
+                _ => {
+                    eprintln!(
+                        "--generate-tablebase: expected a directory and the pieces, e.g. KRvK"
+                    );
+                    std::process::exit(1);
+                }
+            },
+            _ => variant_path = Some(arg),
+        }
+    }
+
+    let game: Box<dyn Game> = match (variant_path, chosen_game) {
+        (Some(_), Some(_)) => {
+            eprintln!("--game: a variant file was given too");
+            std::process::exit(1);
+        }
+        (Some(path), None) => match variant::load(std::path::Path::new(&path)) {
+            Ok(variant) => Box::new(variant),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        (None, Some(game)) => game,
+        (None, None) => Box::new(ClassicalGameType::Classical),
+    };
+    let game: Box<dyn Game> = match eval_path {
+        Some(path) => match generic::evaluation::EvalParams::load(std::path::Path::new(&path)) {
+            Ok(params) => Box::new(Tuned { game, params }),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => game,
+    };
+    if let Some((dir, material)) = generate {
+        let signature = game.create_game().get_signature().clone();
+        let dir = std::path::Path::new(&dir);
+        let result = generic::tablebase::Material::parse(&material).and_then(|material| {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            generic::tablebase::Tablebases::new(&signature).generate(
+                &signature,
+                &material,
+                Some(dir),
+            )
+        });
+        if let Err(e) = result {
+            eprintln!("--generate-tablebase: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let game: Box<dyn Game> = match tablebase_dir {
+        Some(dir) => {
+            let signature = game.create_game().get_signature().clone();
+            match generic::tablebase::Tablebases::load(&signature, std::path::Path::new(&dir)) {
+                Ok(tablebases) => Box::new(WithTablebases {
+                    game,
+                    tablebases: std::sync::Arc::new(tablebases),
+                }),
+                Err(e) => {
+                    eprintln!("{}: {}", dir, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => game,
+    };
+    let game: Box<dyn Game> = match syzygy_dir {
+        Some(dir) => {
+            if !generic::syzygy::Syzygy::supports(game.create_game().get_signature()) {
+                eprintln!("--syzygy: the tables are only for classical chess");
+                std::process::exit(1);
+            }
+            match generic::syzygy::Syzygy::load(std::path::Path::new(&dir)) {
+                Ok(syzygy) => Box::new(WithSyzygy {
+                    game,
+                    syzygy: std::sync::Arc::new(syzygy),
+                }),
+                Err(e) => {
+                    eprintln!("--syzygy: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => game,
+    };
+    classical::graphical::GameInterface::run(game)
 }
@@ -0,0 +1,751 @@
+use crate::classical::graphical::{Game, Geometry, GridGeometry};
+use crate::classical::grid::{self, Grid};
+use crate::classical::Topology;
+use crate::generic::*;
+use std::collections::{HashMap, HashSet};
+
+//variants described by a text file of `key = value` lines, with `#` starting a comment
+//
+//  name = Los Alamos
+//  files = 6
+//  ranks = 6
+//  topology = flat            flat, cylinder or torus
+//  piece e = chancellor       give a letter to a piece, the usual p g r n b q a c k need no entry
+//  piece x = cannon mRcpR     or define a new piece by its name and Betza notation, the name choosing its icon
+//  promotion = n r q          what pawns may promote to
+//  promotion_ranks = 6        where white's pawns promote, by default the last rank
+//  double_step = 2            ranks from which white's pawns may move two squares
+//  castle = e1 h1 g1 f1       king from, rook from, king to, rook to
+//  drops = yes                captured pieces join the capturer's pocket to be dropped later, as in crazyhouse
+//  atomic = yes               captures blow up the capturer and every piece but a pawn around it
+//  antichess = yes            captures are compulsory, kings are not royal and the aim is to run out of moves
+//  start = rnqknr/pppppp/6/6/PPPPPP/RNQKNR
+//  turn = white
+//  win = hill d4 e4, checks 3 besides checkmate, a king reaching the hill or giving check so many times wins
+//                             and with horde a side may start without a king, losing once its pieces are all taken
+//
+//squares, ranks and castles are given for white and reflected top to bottom for black
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantError {
+    pub line: Option<usize>, //the line of the offending entry, if there is one
+    pub message: String,
+}
+
+impl std::fmt::Display for VariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WinCondition {
+    Checkmate,
+    Hill(Vec<Square>), //a king reaching any of the squares wins
+    Checks(usize),     //giving check this many times wins
+    Horde,             //a side without a king loses once all its pieces are captured
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: String,
+    pub files: u8,
+    pub ranks: u8,
+    pub win_conditions: Vec<WinCondition>,
+    board: Board,
+}
+
+impl Variant {
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn get_signature(&self) -> &signature::Signature {
+        self.board.get_signature()
+    }
+
+    pub fn create_game(&self) -> Board {
+        self.board.clone()
+    }
+}
+
+impl Game for Variant {
+    fn geometry(&self) -> Box<dyn Geometry> {
+        Box::new(GridGeometry::new(
+            self.files,
+            self.ranks,
+            self.get_signature().has_drops(),
+        ))
+    }
+
+    fn create_game(&self) -> Board {
+        Variant::create_game(self)
+    }
+}
+
+struct Entry<'a> {
+    line: usize,
+    key: &'a str,
+    arg: Option<&'a str>,
+    value: &'a str,
+}
+
+impl Entry<'_> {
+    fn error(&self, message: String) -> VariantError {
+        VariantError {
+            line: Some(self.line),
+            message: format!("{}: {}", self.key, message),
+        }
+    }
+
+    fn number(&self, value: &str, min: usize, max: usize) -> Result<usize, VariantError> {
+        match value.parse::<usize>() {
+            Ok(n) if min <= n && n <= max => Ok(n),
+            _ => Err(self.error(format!(
+                "expected a number from {} to {} but found '{}'",
+                min, max, value
+            ))),
+        }
+    }
+}
+
+fn parse_entries(text: &str) -> Result<Vec<Entry<'_>>, VariantError> {
+    let mut entries = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line_num = i + 1;
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (lhs, value) = match line.split_once('=') {
+            Some(split) => split,
+            None => {
+                return Err(VariantError {
+                    line: Some(line_num),
+                    message: format!("expected `key = value` but found '{}'", line),
+                })
+            }
+        };
+        let mut words = lhs.split_whitespace();
+        let key = match words.next() {
+            Some(key) => key,
+            None => {
+                return Err(VariantError {
+                    line: Some(line_num),
+                    message: String::from("missing key"),
+                })
+            }
+        };
+        let arg = words.next();
+        if words.next().is_some() {
+            return Err(VariantError {
+                line: Some(line_num),
+                message: format!("unexpected words in '{}'", lhs.trim()),
+            });
+        }
+        entries.push(Entry {
+            line: line_num,
+            key,
+            arg,
+            value: value.trim(),
+        });
+    }
+    Ok(entries)
+}
+
+fn piece_by_name(name: &str) -> Option<PieceKind> {
+    match name {
+        "pawn" => Some(PieceKind::Pawn(EnCroissantable::No)),
+        "grasshopper" => Some(PieceKind::Grasshopper),
+        "rook" => Some(PieceKind::Rook),
+        "knight" => Some(PieceKind::Knight),
+        "bishop" => Some(PieceKind::Bishop),
+        "queen" => Some(PieceKind::Queen),
+        "archbishop" => Some(PieceKind::Archbishop),
+        "chancellor" => Some(PieceKind::Chancellor),
+        "king" => Some(PieceKind::King),
+        _ => None,
+    }
+}
+
+fn create_signature(
+    grid: &Grid,
+    promotions: &[PieceKind],
+    promotion_ranks: &[u8],
+    double_step_ranks: &[u8],
+    castles: &[(Square, Square, Square, Square)],
+) -> signature::Signature {
+    let castle = |(king_from, rook_from, king_to, rook_to): (Square, Square, Square, Square)| {
+        let x = |sq: Square| grid.xy(sq).0;
+        grid::castle_signature(
+            grid.files,
+            grid.xy(king_from).1,
+            (x(king_from), x(rook_from)),
+            (x(king_to), x(rook_to)),
+        )
+    };
+    grid::create_signature(
+        grid,
+        promotions,
+        promotion_ranks,
+        &|_team, y| Some(double_step_ranks.contains(&y)),
+        castles
+            .iter()
+            .map(|castle_squares| castle(*castle_squares))
+            .collect(),
+        castles
+            .iter()
+            .map(|(kf, rf, kt, rt)| {
+                castle((
+                    grid.mirror(*kf),
+                    grid.mirror(*rf),
+                    grid.mirror(*kt),
+                    grid.mirror(*rt),
+                ))
+            })
+            .collect(),
+    )
+}
+
+pub fn parse(text: &str) -> Result<Variant, VariantError> {
+    let entries = parse_entries(text)?;
+
+    //each key other than piece and castle may be given once
+    let mut seen = HashSet::new();
+    for entry in &entries {
+        match (entry.key, entry.arg) {
+            ("piece", Some(_)) => {}
+            ("piece", None) => return Err(entry.error(String::from("expected `piece <letter>`"))),
+            (_, Some(arg)) => return Err(entry.error(format!("unexpected '{}'", arg))),
+            ("castle", None) => {}
+            (
+                "name" | "files" | "ranks" | "topology" | "promotion" | "promotion_ranks"
+                | "double_step" | "drops" | "atomic" | "antichess" | "start" | "turn" | "win",
+                None,
+            ) => {
+                if !seen.insert(entry.key) {
+                    return Err(entry.error(String::from("given more than once")));
+                }
+            }
+            (key, None) => {
+                return Err(VariantError {
+                    line: Some(entry.line),
+                    message: format!("unknown key '{}'", key),
+                })
+            }
+        }
+    }
+    let single = |key: &str| entries.iter().find(|entry| entry.key == key);
+    let required = |key: &str| match single(key) {
+        Some(entry) => Ok(entry),
+        None => Err(VariantError {
+            line: None,
+            message: format!("missing key '{}'", key),
+        }),
+    };
+
+    let name = match single("name") {
+        Some(entry) => entry.value.to_string(),
+        None => String::from("Unnamed"),
+    };
+
+    let files_entry = required("files")?;
+    let files = files_entry.number(files_entry.value, 1, 26)? as u8;
+    let ranks_entry = required("ranks")?;
+    let ranks = ranks_entry.number(ranks_entry.value, 2, 99)? as u8;
+
+    let topology = match single("topology") {
+        None => Topology::Flat,
+        Some(entry) => {
+            let topology = match entry.value {
+                "flat" => Topology::Flat,
+                "cylinder" => Topology::Cylinder,
+                "torus" => Topology::Torus,
+                other => return Err(entry.error(format!("unknown topology '{}'", other))),
+            };
+            //a glued edge needs at least three squares around it for directions to be unambiguous
+            if (topology != Topology::Flat && files < 3)
+                || (topology == Topology::Torus && ranks < 3)
+            {
+                return Err(entry.error(String::from("the board is too small to wrap around")));
+            }
+            topology
+        }
+    };
+    let grid = Grid {
+        files,
+        ranks,
+        topology,
+    };
+
+    let mut pieces = HashMap::new();
+    let mut custom_pieces = vec![];
+    for (c, name) in [
+        ('p', "pawn"),
+        ('g', "grasshopper"),
+        ('r', "rook"),
+        ('n', "knight"),
+        ('b', "bishop"),
+        ('q', "queen"),
+        ('a', "archbishop"),
+        ('c', "chancellor"),
+        ('k', "king"),
+    ] {
+        pieces.insert(c, piece_by_name(name).unwrap());
+    }
+    for entry in entries.iter().filter(|entry| entry.key == "piece") {
+        let letter = entry.arg.unwrap();
+        let c = match letter.chars().collect::<Vec<_>>()[..] {
+            [c] if c.is_ascii_lowercase() => c,
+            _ => {
+                return Err(entry.error(format!(
+                    "expected a single lowercase letter but found '{}'",
+                    letter
+                )))
+            }
+        };
+        let words: Vec<&str> = entry.value.split_whitespace().collect();
+        let (name, notation) = match words[..] {
+            [name] => match piece_by_name(name) {
+                Some(kind) => {
+                    pieces.insert(c, kind);
+                    continue;
+                }
+                None => (letter, name),
+            },
+            [name, notation] => (name, notation),
+            _ => {
+                return Err(entry.error(String::from(
+                    "expected a piece name, or an optional name and Betza notation",
+                )))
+            }
+        };
+        match betza::parse(notation) {
+            Ok(movement) => {
+                pieces.insert(c, PieceKind::Custom(custom_pieces.len()));
+                custom_pieces.push((name.to_string(), movement));
+            }
+            Err(e) => return Err(entry.error(format!("in '{}': {}", notation, e))),
+        }
+    }
+    let letter_piece = |entry: &Entry, c: char| match pieces.get(&c.to_ascii_lowercase()) {
+        Some(kind) => Ok(*kind),
+        None => Err(entry.error(format!("unknown piece '{}'", c))),
+    };
+
+    let promotions = match single("promotion") {
+        None => vec![
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+        ],
+        Some(entry) => {
+            let mut promotions = vec![];
+            for word in entry.value.split_whitespace() {
+                let mut chars = word.chars();
+                let kind = match (chars.next(), chars.next()) {
+                    (Some(c), None) => letter_piece(entry, c)?,
+                    _ => {
+                        return Err(
+                            entry.error(format!("expected a piece letter but found '{}'", word))
+                        )
+                    }
+                };
+                if matches!(kind, PieceKind::Pawn(..) | PieceKind::King) {
+                    return Err(entry.error(format!("pawns may not promote to '{}'", word)));
+                }
+                promotions.push(kind);
+            }
+            promotions
+        }
+    };
+
+    let rank_list = |entry: &Entry| -> Result<Vec<u8>, VariantError> {
+        entry
+            .value
+            .split_whitespace()
+            .map(|word| Ok(entry.number(word, 1, ranks as usize)? as u8 - 1))
+            .collect()
+    };
+    let promotion_ranks = match single("promotion_ranks") {
+        None => vec![ranks - 1],
+        Some(entry) => rank_list(entry)?,
+    };
+    let double_step_ranks = match single("double_step") {
+        None => vec![],
+        Some(entry) => rank_list(entry)?,
+    };
+
+    let yes_no = |key: &str| -> Result<bool, VariantError> {
+        match single(key) {
+            None => Ok(false),
+            Some(entry) => match entry.value {
+                "yes" => Ok(true),
+                "no" => Ok(false),
+                other => Err(entry.error(format!("expected yes or no but found '{}'", other))),
+            },
+        }
+    };
+    let drops = yes_no("drops")?;
+    let atomic = yes_no("atomic")?;
+    let antichess = yes_no("antichess")?;
+
+    let turn = match single("turn") {
+        None => Team::White,
+        Some(entry) => match entry.value {
+            "white" => Team::White,
+            "black" => Team::Black,
+            other => return Err(entry.error(format!("unknown side to move '{}'", other))),
+        },
+    };
+
+    //checkmate is always a win, and the others are extra ways for the game to end
+    let mut win_conditions = vec![WinCondition::Checkmate];
+    if let Some(entry) = single("win") {
+        for text in entry.value.split(',') {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            let condition = match words[..] {
+                ["checkmate"] => continue,
+                ["hill", ref squares @ ..] if !squares.is_empty() => {
+                    let mut hill = vec![];
+                    for word in squares {
+                        match grid.parse_square(word) {
+                            Some(sq) => hill.push(sq),
+                            None => return Err(entry.error(format!("'{}' is not a square", word))),
+                        }
+                    }
+                    WinCondition::Hill(hill)
+                }
+                ["checks", n] => WinCondition::Checks(entry.number(n, 1, 99)?),
+                ["horde"] => WinCondition::Horde,
+                _ => return Err(entry.error(format!("unknown win condition '{}'", text.trim()))),
+            };
+            if win_conditions
+                .iter()
+                .any(|other| std::mem::discriminant(other) == std::mem::discriminant(&condition))
+            {
+                return Err(entry.error(format!("'{}' given more than once", text.trim())));
+            }
+            win_conditions.push(condition);
+        }
+    }
+    let horde = win_conditions.contains(&WinCondition::Horde);
+
+    //the start position, written like the first field of a FEN from the top rank down
+    let start = required("start")?;
+    let mut white_pieces = HashMap::new();
+    let mut black_pieces = HashMap::new();
+    let rows: Vec<&str> = start.value.split('/').collect();
+    if rows.len() != ranks as usize {
+        return Err(start.error(format!("expected {} ranks but found {}", ranks, rows.len())));
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let y = ranks - 1 - i as u8;
+        let mut x = 0usize;
+        let mut empty = 0usize;
+        for c in row.chars() {
+            if let Some(d) = c.to_digit(10) {
+                empty = 10 * empty + d as usize;
+                continue;
+            }
+            x += empty;
+            empty = 0;
+            let kind = letter_piece(start, c)?;
+            if x >= files as usize {
+                return Err(start.error(format!("rank {} is too long", y + 1)));
+            }
+            if c.is_ascii_uppercase() {
+                white_pieces.insert(grid.sq(x as u8, y), kind);
+            } else {
+                black_pieces.insert(grid.sq(x as u8, y), kind);
+            }
+            x += 1;
+        }
+        x += empty;
+        if x != files as usize {
+            return Err(start.error(format!("rank {} does not have {} squares", y + 1, files)));
+        }
+    }
+    for (team, pieces) in [(Team::White, &white_pieces), (Team::Black, &black_pieces)] {
+        match pieces.values().filter(|k| **k == PieceKind::King).count() {
+            1 => {}
+            0 if horde => {}
+            _ if horde => return Err(start.error(format!("{:?} has more than one king", team))),
+            _ => return Err(start.error(format!("{:?} must have exactly one king", team))),
+        }
+        for (sq, kind) in pieces.iter() {
+            let (_x, y) = match team {
+                Team::White => grid.xy(*sq),
+                Team::Black => grid.xy(grid.mirror(*sq)),
+            };
+            if matches!(kind, PieceKind::Pawn(..))
+                && (promotion_ranks.contains(&y) || y + 1 == ranks)
+            {
+                return Err(start.error(format!("{:?} has a pawn where it cannot move", team)));
+            }
+        }
+    }
+
+    let mut castles = vec![];
+    for entry in entries.iter().filter(|entry| entry.key == "castle") {
+        let mut squares = vec![];
+        for word in entry.value.split_whitespace() {
+            match grid.parse_square(word) {
+                Some(sq) => squares.push(sq),
+                None => return Err(entry.error(format!("'{}' is not a square", word))),
+            }
+        }
+        let (king_from, rook_from, king_to, rook_to) = match squares[..] {
+            [kf, rf, kt, rt] => (kf, rf, kt, rt),
+            _ => {
+                return Err(entry.error(String::from(
+                    "expected king from, rook from, king to and rook to",
+                )))
+            }
+        };
+        if squares
+            .iter()
+            .any(|sq| grid.xy(*sq).1 != grid.xy(king_from).1)
+            || king_from == rook_from
+            || king_to == rook_to
+        {
+            return Err(entry.error(String::from(
+                "the king and rook must start and end on different squares of one rank",
+            )));
+        }
+        if !white_pieces.contains_key(&king_from)
+            || !white_pieces.contains_key(&rook_from)
+            || !black_pieces.contains_key(&grid.mirror(king_from))
+            || !black_pieces.contains_key(&grid.mirror(rook_from))
+        {
+            return Err(entry.error(String::from(
+                "both teams need pieces on the castling squares",
+            )));
+        }
+        castles.push((king_from, rook_from, king_to, rook_to));
+    }
+
+    let mut signature = create_signature(
+        &grid,
+        &promotions,
+        &promotion_ranks,
+        &double_step_ranks,
+        &castles,
+    );
+    if drops {
+        signature = signature.with_drops();
+    }
+    if atomic {
+        signature = signature.with_atomic();
+    }
+    if antichess {
+        signature = signature.with_antichess();
+    }
+    for condition in &win_conditions {
+        signature = match condition {
+            WinCondition::Checkmate => signature,
+            WinCondition::Hill(hill) => {
+                signature.with_rules(rules::KingOfTheHill { hill: hill.clone() })
+            }
+            WinCondition::Checks(checks) => {
+                signature.with_rules(rules::ThreeCheck { checks: *checks })
+            }
+            WinCondition::Horde => signature.with_rules(rules::Horde {}),
+        };
+    }
+    for (idx, (name, movement)) in custom_pieces.into_iter().enumerate() {
+        let kind = signature.add_custom_piece(name, movement);
+        debug_assert_eq!(kind, PieceKind::Custom(idx));
+    }
+    Ok(Variant {
+        name,
+        files,
+        ranks,
+        win_conditions,
+        board: Board::new(turn, signature, white_pieces, black_pieces),
+    })
+}
+
+pub fn load(path: &std::path::Path) -> Result<Variant, VariantError> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => parse(&text),
+        Err(e) => Err(VariantError {
+            line: None,
+            message: format!("could not read {}: {}", path.display(), e),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classical::ClassicalGameType;
+
+    #[test]
+    fn test_examples() {
+        let classical = parse(include_str!("examples/classical.variant")).unwrap();
+        let board = ClassicalGameType::Classical.create_game();
+        assert_eq!(classical.get_board(), &board);
+        let castles = |signature: &signature::Signature| {
+            let mut castles: Vec<_> = signature
+                .get_castles()
+                .map(|(team, cs)| {
+                    let mut cs = cs.clone();
+                    cs.not_occupied.sort();
+                    (team, format!("{:?}", cs))
+                })
+                .collect();
+            castles.sort_by(|a, b| a.1.cmp(&b.1));
+            castles
+        };
+        assert_eq!(
+            castles(classical.get_signature()),
+            castles(board.get_signature())
+        );
+        let mut game = classical.create_game();
+        assert_eq!(board_data::BoardData::new(&mut game).get_moves().len(), 20);
+
+        let crazyhouse = parse(include_str!("examples/crazyhouse.variant")).unwrap();
+        assert_eq!(
+            crazyhouse.get_board(),
+            &ClassicalGameType::Crazyhouse.create_game()
+        );
+        assert!(crazyhouse.get_signature().has_drops());
+        assert!(!crazyhouse.get_signature().is_atomic());
+
+        //fairy pieces are given in Betza notation
+        let cannons =
+            parse("files = 3\nranks = 3\npiece x = cannon mRcpR\npiece w = W\nstart = kx1/3/WXK")
+                .unwrap();
+        let signature = cannons.get_signature();
+        assert_eq!(signature.get_custom_piece(0).name, "cannon");
+        assert_eq!(signature.get_custom_piece(1).name, "w");
+        assert_eq!(
+            cannons
+                .get_board()
+                .get_square(Square { idx: 1 })
+                .unwrap()
+                .kind,
+            PieceKind::Custom(0)
+        );
+
+        //6 pawn moves and 4 knight moves
+        let los_alamos = parse(include_str!("examples/los_alamos.variant")).unwrap();
+        assert_eq!((los_alamos.files, los_alamos.ranks), (6, 6));
+        let mut game = los_alamos.create_game();
+        assert_eq!(board_data::BoardData::new(&mut game).get_moves().len(), 10);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| parse(text).unwrap_err();
+        let base = "files = 3\nranks = 3\n";
+
+        assert_eq!(error("files = 3").line, None);
+        assert_eq!(error("files 3").line, Some(1));
+        assert_eq!(error("files = 3\nfiles = 4").line, Some(2));
+        assert_eq!(error("files = 30").line, Some(1));
+        assert_eq!(error("colour = blue").line, Some(1));
+        assert_eq!(error(&format!("{}start = k2/3", base)).line, Some(3));
+        assert_eq!(error(&format!("{}start = k2/3/2K1", base)).line, Some(3));
+        assert_eq!(error(&format!("{}start = k2/3/2X", base)).line, Some(3));
+        assert_eq!(error(&format!("{}start = k1P/3/2K", base)).line, Some(3));
+        assert_eq!(
+            error(&format!(
+                "{}\n# comment\npiece x = wazir\nstart = k2/3/2K",
+                base
+            ))
+            .line,
+            Some(5)
+        );
+        assert_eq!(
+            error(&format!("{}start = k2/3/2K\ncastle = c1 a1 b1 c1", base)).line,
+            Some(4)
+        );
+        assert_eq!(
+            error(&format!(
+                "{}topology = torus\nstart = 1k1/3/1K1\ntopology = flat",
+                base
+            ))
+            .line,
+            Some(5)
+        );
+        assert!(parse(&format!("{}start = 2k/3/K2\ntopology = torus", base)).is_ok());
+        assert_eq!(
+            error(&format!("{}piece x = cannon mRnR\nstart = k2/3/2K", base)).line,
+            Some(3)
+        );
+        assert_eq!(
+            error(&format!("{}atomic = maybe\nstart = k2/3/2K", base)).line,
+            Some(3)
+        );
+        assert_eq!(
+            error(&format!("{}win = stalemate\nstart = k2/3/2K", base)).line,
+            Some(3)
+        );
+        assert_eq!(
+            error(&format!("{}win = hill d4\nstart = k2/3/2K", base)).line,
+            Some(3)
+        );
+        assert_eq!(
+            error(&format!(
+                "{}win = checks 2, checks 3\nstart = k2/3/2K",
+                base
+            ))
+            .line,
+            Some(3)
+        );
+        //only a horde may go without a king
+        assert_eq!(error(&format!("{}start = k2/3/2P", base)).line, Some(3));
+        assert!(parse(&format!("{}win = horde\nstart = k2/3/2P", base)).is_ok());
+    }
+
+    #[test]
+    fn test_win_conditions() {
+        let play = |board: &mut Board, from: Square, to: Square| {
+            let m = board_data::BoardData::new(board)
+                .get_moves()
+                .into_iter()
+                .find(|m| matches!(m, Move::Standard { from_sq, to_sq, .. } if *from_sq == from && *to_sq == to))
+                .copied()
+                .unwrap();
+            board.make_move(m);
+            board_data::BoardData::new(board).get_evaluation()
+        };
+        let sq = |x: usize, y: usize| Square { idx: x + 4 * y };
+
+        //the king walking onto the hill wins
+        let variant =
+            parse("files = 4\nranks = 4\nwin = checkmate, hill b2 c2\nstart = 3k/4/4/K3").unwrap();
+        assert_eq!(
+            variant.win_conditions,
+            vec![
+                WinCondition::Checkmate,
+                WinCondition::Hill(vec![sq(1, 1), sq(2, 1)])
+            ]
+        );
+        let mut board = variant.create_game();
+        assert_eq!(play(&mut board, sq(0, 0), sq(1, 1)), score::Score::Lost(1));
+
+        //as does a single check when one is enough
+        let mut board = parse("files = 4\nranks = 4\nwin = checks 1\nstart = 3k/4/4/KR2")
+            .unwrap()
+            .create_game();
+        assert_eq!(play(&mut board, sq(1, 0), sq(3, 0)), score::Score::Lost(1));
+
+        //and taking the last piece of a side without a king
+        let mut board =
+            parse("files = 4\nranks = 4\nwin = horde\nstart = 4/1k2/P3/4\nturn = black")
+                .unwrap()
+                .create_game();
+        assert_eq!(play(&mut board, sq(1, 2), sq(0, 1)), score::Score::Lost(1));
+    }
+}